@@ -1,5 +1,16 @@
+pub mod ai;
 pub mod board;
+pub mod book;
+pub mod chess960;
+pub mod drill;
+pub mod epd;
 pub mod game;
 pub mod macros;
+pub mod magic;
 pub mod moves;
+pub mod openings;
 pub mod parser;
+pub mod pgn;
+pub mod rng;
+pub mod tablebase;
+pub mod zobrist;
@@ -0,0 +1,617 @@
+use crate::engine::board::{piece_value, Board};
+use crate::engine::game::{Game, Move, Status};
+use crate::engine::parser::Piece;
+use crate::engine::rng::Rng;
+use crate::engine::tablebase;
+use crate::engine::tablebase::{TbResult, Wdl};
+use std::cmp::Ordering;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::thread;
+
+/// score of being checkmated, reduced by the distance so quicker mates are preferred
+pub const MATE_SCORE: i32 = 1_000_000;
+
+/// score of a won tablebase position before its bonus for the pawn's progress and
+/// the dtz penalty, under a queen's worth so the search still promotes
+pub const TB_WIN_SCORE: i32 = 400;
+
+/// upper bound of positions visited by [`Game::best_move`], deeper lines are cut
+/// off with the static evaluation once it is reached
+pub const MAX_SEARCH_NODES: u64 = 500_000;
+
+/// computer opponent without any search, picks uniformly among the legal moves.
+/// The same seed always picks the same move so games can be reproduced.
+/// `None` when the game is over
+pub fn pick_random_move(game: &Game, seed: u64) -> Option<Move> {
+    let moves = game.legal_moves();
+    if game.status() != Status::Ongoing || moves.is_empty() {
+        return None;
+    }
+    Some(moves[Rng::new(seed).next_range(moves.len())])
+}
+
+/// static evaluation in centipawns from white's point of view: material plus small
+/// bonuses for centralized knights/bishops and advanced central pawns, plus the
+/// mating term of king and queen (or rook) against king. Known drawn
+/// endgames (see [`Board::is_wrong_bishop_draw`]) score 0 whatever the material
+pub fn evaluate(board: &Board) -> i32 {
+    if board.is_wrong_bishop_draw() {
+        return 0;
+    }
+    let side = |is_white: bool| {
+        let mut score = board.material(is_white) * 100;
+        for (piece, weight) in [(Piece::Knight, 10), (Piece::Bishop, 5), (Piece::Pawn, 5)] {
+            let mut pieces = board.get_pieces(piece, is_white);
+            while pieces != 0 {
+                let idx = pieces.trailing_zeros() as i32;
+                pieces &= pieces - 1;
+                let (file, rank) = (idx % 8, idx / 8);
+                // 0 on the edge, 3 on the 4 center squares
+                let centrality = 3 - (2 * file - 7).abs().max((2 * rank - 7).abs()) / 2;
+                score += centrality * weight;
+                if piece == Piece::Pawn {
+                    let advanced = if is_white { rank - 1 } else { 6 - rank };
+                    score += advanced * 5;
+                }
+            }
+        }
+        score
+    };
+    side(true) - side(false) + mop_up(board)
+}
+
+/// king and queen (or rook) against king: the lone king is driven to the edge, the
+/// box it's cut off in shrinks and the other king comes closer to help mating, which
+/// the search alone doesn't see at a low depth
+fn mop_up(board: &Board) -> i32 {
+    for is_white in [true, false] {
+        let (own_pieces, opponent_pieces) = if is_white {
+            (board.white_pieces, board.black_pieces)
+        } else {
+            (board.black_pieces, board.white_pieces)
+        };
+        let king = board.get_pieces(Piece::King, is_white);
+        let defending_king = board.get_pieces(Piece::King, !is_white);
+        let major = board.get_pieces(Piece::Queen, is_white) | board.get_pieces(Piece::Rook, is_white);
+        if major.count_ones() != 1 || own_pieces != king | major || opponent_pieces != defending_king {
+            continue;
+        }
+        let square = |bitboard: u64| {
+            let idx = bitboard.trailing_zeros() as i32;
+            (idx % 8, idx / 8)
+        };
+        let (king_file, king_rank) = square(king);
+        let (major_file, major_rank) = square(major);
+        let (file, rank) = square(defending_king);
+        // 0 on the 4 center squares, 6 in the corners
+        let edge = (3 - file).max(file - 4) + (3 - rank).max(rank - 4);
+        let kings_distance = (king_file - file).abs().max((king_rank - rank).abs());
+        // squares of the box the lone king is cut off in by the queen's or rook's lines
+        let side = |king: i32, line: i32| match king.cmp(&line) {
+            Ordering::Less => line,
+            Ordering::Greater => 7 - line,
+            Ordering::Equal => 8,
+        };
+        let area = side(file, major_file) * side(rank, major_rank);
+        let score = edge * 10 + (7 - kings_distance) * 4 + (64 - area) * 2;
+        return if is_white { score } else { -score };
+    }
+    0
+}
+
+/// plies searched for the computer's moves by default
+pub const DEFAULT_DEPTH: u8 = 3;
+
+/// search score (centipawns, from the computer's side) under which a move counts
+/// as hopeless, about a queen down
+pub const DEFAULT_RESIGN_THRESHOLD: i32 = -800;
+
+/// settings of the built-in computer opponent
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct AiConfig {
+    /// plies searched per move, see [`Game::best_move`]
+    pub depth: u8,
+    /// resigns once the search score stays under the threshold for `resign_moves`
+    /// moves in a row, `None` plays every game to the end
+    pub resign_threshold: Option<i32>,
+    pub resign_moves: u8,
+}
+
+impl Default for AiConfig {
+    fn default() -> AiConfig {
+        AiConfig {
+            depth: DEFAULT_DEPTH,
+            resign_threshold: None,
+            resign_moves: 3,
+        }
+    }
+}
+
+/// what the computer does on its turn
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AiMove {
+    Play(Move),
+    Resign,
+}
+
+/// computer opponent playing the best move of the search, it keeps count of the
+/// hopeless moves in a row to resign
+#[derive(Debug, Default, Clone)]
+pub struct Ai {
+    pub config: AiConfig,
+    hopeless_moves: u8,
+}
+
+impl Ai {
+    pub fn new(config: AiConfig) -> Ai {
+        Ai { config, hopeless_moves: 0 }
+    }
+
+    /// searches the position for the side to move, `None` when the game is over
+    pub fn choose(&mut self, game: &Game) -> Option<AiMove> {
+        let (mv, score) = game.best_move(self.config.depth)?;
+        Some(self.judge(mv, score))
+    }
+
+    /// plays the searched move, or resigns when its score was hopeless for the last
+    /// `resign_moves` moves
+    pub fn judge(&mut self, mv: Move, score: i32) -> AiMove {
+        if self.config.resign_threshold.is_some_and(|threshold| score < threshold) {
+            self.hopeless_moves = self.hopeless_moves.saturating_add(1);
+        } else {
+            self.hopeless_moves = 0;
+        }
+        if self.hopeless_moves >= self.config.resign_moves.max(1) {
+            AiMove::Resign
+        } else {
+            AiMove::Play(mv)
+        }
+    }
+
+    /// forgets the hopeless moves of the previous game
+    pub fn reset(&mut self) {
+        self.hopeless_moves = 0;
+    }
+}
+
+/// slots of [`EvalCache`], a power of two so the low bits of the hash are the index
+pub const EVAL_CACHE_SIZE: usize = 1 << 16;
+
+/// static evaluations of the positions met during a search, by Zobrist hash (see
+/// [`Game::hash`]). Each slot keeps the full hash so another position landing on the
+/// same slot is evaluated again instead of taking its score
+pub struct EvalCache {
+    entries: Vec<Option<(u64, i32)>>,
+}
+
+impl Default for EvalCache {
+    fn default() -> EvalCache {
+        EvalCache { entries: vec![None; EVAL_CACHE_SIZE] }
+    }
+}
+
+impl EvalCache {
+    /// [`evaluate`] of the game's position, computed only the first time
+    pub fn evaluate(&mut self, game: &Game) -> i32 {
+        self.evaluate_board(game.hash(), &game.board)
+    }
+
+    fn evaluate_board(&mut self, hash: u64, board: &Board) -> i32 {
+        let entry = &mut self.entries[hash as usize & (EVAL_CACHE_SIZE - 1)];
+        match *entry {
+            Some((key, score)) if key == hash => score,
+            _ => {
+                let score = evaluate(board);
+                *entry = Some((hash, score));
+                score
+            }
+        }
+    }
+}
+
+/// progress of a search running in the background, see [`spawn_search`]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SearchUpdate {
+    /// best move and score once the search to `depth` plies is done
+    Depth { depth: u8, mv: Move, score: i32 },
+    /// the deepest search is done, `None` when the game is over
+    Done(Option<(Move, i32)>),
+}
+
+/// searches with iterative deepening (1 ply, 2 plies, .. up to `depth`) in a worker
+/// thread so the UI keeps running. Every finished depth is sent on the channel, then
+/// the result. The search stops early once the receiver is dropped
+pub fn spawn_search(game: &Game, depth: u8) -> Receiver<SearchUpdate> {
+    let (sender, receiver) = mpsc::channel();
+    let game = game.clone();
+    thread::spawn(move || {
+        let mut best = None;
+        for depth in 1..=depth.max(1) {
+            best = game.best_move(depth);
+            let Some((mv, score)) = best else {
+                break;
+            };
+            if sender.send(SearchUpdate::Depth { depth, mv, score }).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(SearchUpdate::Done(best));
+    });
+    receiver
+}
+
+impl Game {
+    /// negamax search with alpha-beta pruning over cloned games, returns the best
+    /// move and its score in centipawns for the side to move. `None` when the
+    /// game is over. The search is bounded by [`MAX_SEARCH_NODES`]
+    pub fn best_move(&self, depth: u8) -> Option<(Move, i32)> {
+        if self.status() != Status::Ongoing {
+            return None;
+        }
+        let mut nodes = 0;
+        let mut cache = EvalCache::default();
+        let mut best: Option<(Move, i32)> = None;
+        let mut alpha = -MATE_SCORE - 1;
+        for mv in ordered_moves(self) {
+            let mut next = self.clone();
+            next.apply_move(mv).expect("legal move");
+            let score = -negamax(&next, depth.saturating_sub(1), 1, -MATE_SCORE - 1, -alpha, &mut nodes, &mut cache);
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some((mv, score));
+            }
+        }
+        best
+    }
+}
+
+impl Game {
+    /// every legal move (SAN) with its score for the side to move after searching
+    /// `depth` plies, best first. Each move gets an exact score (no pruning against
+    /// the other moves), so it's much slower than [`Game::best_move`]
+    pub fn rank_moves(&self, depth: u8) -> Vec<(String, i32)> {
+        if self.status() != Status::Ongoing {
+            return Vec::new();
+        }
+        let mut cache = EvalCache::default();
+        let mut ranked: Vec<(String, i32)> = ordered_moves(self)
+            .into_iter()
+            .map(|mv| {
+                let mut next = self.clone();
+                let san = next.apply_move(mv).expect("legal move").san;
+                let mut nodes = 0;
+                let score = -negamax(&next, depth.saturating_sub(1), 1, -MATE_SCORE - 1, MATE_SCORE + 1, &mut nodes, &mut cache);
+                (san, score)
+            })
+            .collect();
+        ranked.sort_by_key(|(_, score)| -score);
+        ranked
+    }
+}
+
+/// score for the side to move, `ply` is the distance from the root
+fn negamax(game: &Game, depth: u8, ply: i32, mut alpha: i32, beta: i32, nodes: &mut u64, cache: &mut EvalCache) -> i32 {
+    *nodes += 1;
+    match game.status() {
+        Status::Checkmate | Status::Resigned => return -MATE_SCORE + ply,
+        Status::Draw => return 0,
+        Status::Ongoing => {}
+    }
+    // exact result of the endgame tables (`tablebase` feature) over the evaluation
+    if let Some(result) = tablebase::probe(&game.board, game.is_white()) {
+        return tablebase_score(result, &game.board);
+    }
+    if depth == 0 || *nodes >= MAX_SEARCH_NODES {
+        let score = cache.evaluate(game);
+        return if game.is_white() { score } else { -score };
+    }
+
+    for mv in ordered_moves(game) {
+        let mut next = game.clone();
+        next.apply_move(mv).expect("legal move");
+        let score = -negamax(&next, depth - 1, ply + 1, -beta, -alpha, nodes, cache);
+        if score >= beta {
+            return beta;
+        }
+        alpha = alpha.max(score);
+    }
+    alpha
+}
+
+/// score of a tablebase position for the side to move. Wins with a more advanced
+/// pawn score higher so the search keeps pushing it (the dtz only counts until the
+/// next pawn move), then the quickest one
+fn tablebase_score(result: TbResult, board: &Board) -> i32 {
+    // king and pawn against king, the only ending in the tables
+    let rank = (board.white_pawns | board.black_pawns).trailing_zeros() as i32 / 8;
+    let advanced = if board.white_pawns != 0 { rank - 1 } else { 6 - rank };
+    let win = TB_WIN_SCORE + advanced * 50 - result.dtz as i32;
+    match result.wdl {
+        Wdl::Win => win,
+        Wdl::Draw => 0,
+        Wdl::Loss => -win,
+    }
+}
+
+/// legal moves with promotions and captures (most valuable victim first) tried
+/// first, which makes the alpha-beta cutoffs happen earlier
+fn ordered_moves(game: &Game) -> Vec<Move> {
+    let mut moves = game.legal_moves().to_vec();
+    moves.sort_by_key(|mv| {
+        let victim = game.board.get_piece_type_at(mv.to).map_or(0, |(piece, _)| piece_value(piece));
+        let promotion = mv.promotion.map_or(0, piece_value);
+        -(victim + promotion)
+    });
+    moves
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_random_move() {
+        let game = Game::default();
+        let mv = pick_random_move(&game, 42).unwrap();
+        assert!(game.legal_moves().contains(&mv));
+        assert_eq!(Some(mv), pick_random_move(&game, 42));
+
+        // different seeds spread over the 20 opening moves
+        let mut picked: Vec<Move> = (0..200).filter_map(|seed| pick_random_move(&game, seed)).collect();
+        picked.sort_by_key(|mv| mv.uci());
+        picked.dedup();
+        assert_eq!(20, picked.len());
+
+        // only legal move is taking the queen
+        let game = Game::from_fen("7k/8/8/8/8/8/6q1/7K w - - 0 1").unwrap();
+        assert_eq!("h1g2", pick_random_move(&game, 7).unwrap().uci());
+
+        // checkmated
+        let game = Game::from_fen("7k/8/8/8/8/8/6qr/7K w - - 0 1").unwrap();
+        assert_eq!(None, pick_random_move(&game, 7));
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(0, evaluate(&Board::default()));
+        // white is a queen up
+        let board = Board::from_fen("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR");
+        assert!(evaluate(&board) > 800);
+        // developing and central pawns score better
+        let developed = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/5N2/PPPP1PPP/RNBQKB1R");
+        assert!(evaluate(&developed) > 0);
+        let rim = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/7N/PPPPPPPP/RNBQKB1R");
+        assert!(evaluate(&rim) < evaluate(&Board::from_fen("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R")));
+
+        // bishop and rook pawn up but the bishop can't cover the corner
+        assert_eq!(0, evaluate(&Board::from_fen("7k/8/5K2/7P/8/8/8/3B4")));
+        assert_eq!(0, evaluate(&Board::from_fen("8/8/8/8/p7/1b6/8/K6k")));
+        assert!(evaluate(&Board::from_fen("7k/8/5K2/7P/8/8/8/2B5")) > 300);
+    }
+
+    #[test]
+    fn test_mop_up() {
+        // lone king on the edge and kings close together score better
+        let center = evaluate(&Board::from_fen("8/8/8/4k3/8/8/8/R3K3"));
+        let edge = evaluate(&Board::from_fen("4k3/8/8/8/8/8/8/R3K3"));
+        let close = evaluate(&Board::from_fen("4k3/8/4K3/8/8/8/8/R7"));
+        assert!(center < edge && edge < close);
+        assert_eq!(0, mop_up(&Board::from_fen("4k3/8/8/8/8/8/P7/R3K3")));
+        assert!(mop_up(&Board::from_fen("8/8/8/8/8/8/8/q3K2k")) < 0);
+    }
+
+    #[test]
+    fn test_mates_with_rook() {
+        // moves the computer needs to mate, the defender searches its replies as well
+        let mates_in = |fen: &str| {
+            let mut game = Game::from_fen(fen).unwrap();
+            let mut moves = 0;
+            while game.status() == Status::Ongoing && moves < 50 {
+                let (mv, _) = game.best_move(3).unwrap();
+                game.apply_move(mv).unwrap();
+                if let Some((reply, _)) = game.best_move(2) {
+                    game.apply_move(reply).unwrap();
+                }
+                moves += 1;
+            }
+            assert_eq!(Status::Checkmate, game.status(), "{}", game.to_fen());
+            moves
+        };
+        assert!(mates_in("8/8/8/4k3/8/8/8/R3K3 w - - 0 1") <= 30);
+        assert!(mates_in("8/8/8/3k4/8/8/8/4K2R w - - 0 1") <= 30);
+        assert!(mates_in("r3k3/8/8/8/4K3/8/8/8 b - - 0 1") <= 30);
+        assert!(mates_in("8/8/8/3k4/8/8/8/Q3K3 w - - 0 1") <= 20);
+    }
+
+    #[test]
+    fn test_eval_cache() {
+        let mut cache = EvalCache::default();
+        let game = Game::from_fen("rnbqkbnr/pppppppp/8/8/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2").unwrap();
+        let score = evaluate(&game.board);
+        assert_eq!(score, cache.evaluate(&game));
+        assert_eq!(score, cache.evaluate(&game));
+        assert_eq!(Some((game.hash(), score)), cache.entries[game.hash() as usize & (EVAL_CACHE_SIZE - 1)]);
+
+        // another position on the same slot is evaluated, not given the cached score
+        let other = Board::from_fen("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR");
+        let colliding = game.hash() ^ EVAL_CACHE_SIZE as u64;
+        assert_eq!(evaluate(&other), cache.evaluate_board(colliding, &other));
+        assert_ne!(score, evaluate(&other));
+        // which took the slot, the first position is evaluated again
+        assert_eq!(score, cache.evaluate(&game));
+        assert_eq!(Some((game.hash(), score)), cache.entries[game.hash() as usize & (EVAL_CACHE_SIZE - 1)]);
+    }
+
+    #[test]
+    fn test_evaluate_symmetry() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/4P3/5N2/PPPP1PPP/RNBQKB1R",
+            "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R",
+            "4k3/8/3p4/4p3/3Q4/8/8/4K3",
+            "6k1/5ppp/8/8/8/8/8/R5K1",
+            "7k/8/5K2/7P/8/8/8/2B5",
+            "8/8/8/8/2k5/8/8/1Q2K3",
+        ] {
+            let board = Board::from_fen(fen);
+            assert_eq!(evaluate(&board), -evaluate(&board.mirror_vertical()), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_ai_resigns() {
+        let config = AiConfig {
+            depth: 2,
+            resign_threshold: Some(DEFAULT_RESIGN_THRESHOLD),
+            resign_moves: 3,
+        };
+        // bare king against queen and rook, resigns on the third hopeless move
+        let lost = Game::from_fen("4k3/8/8/8/8/8/8/Q3K2R b - - 0 1").unwrap();
+        let mut ai = Ai::new(config);
+        assert!(matches!(ai.choose(&lost), Some(AiMove::Play(_))));
+        assert!(matches!(ai.choose(&lost), Some(AiMove::Play(_))));
+        assert_eq!(Some(AiMove::Resign), ai.choose(&lost));
+
+        // a pawn down is merely worse
+        let worse = Game::from_fen("r3k3/pppp4/8/8/8/8/PPPPP3/R3K3 b - - 0 1").unwrap();
+        let mut ai = Ai::new(config);
+        for _ in 0..5 {
+            assert!(matches!(ai.choose(&worse), Some(AiMove::Play(_))));
+        }
+
+        // a better move in between starts the count again
+        let mut ai = Ai::new(config);
+        ai.choose(&lost);
+        ai.choose(&lost);
+        ai.choose(&worse);
+        assert!(matches!(ai.choose(&lost), Some(AiMove::Play(_))));
+
+        // never resigns without a threshold
+        let mut ai = Ai::new(AiConfig { depth: 2, ..AiConfig::default() });
+        for _ in 0..5 {
+            assert!(matches!(ai.choose(&lost), Some(AiMove::Play(_))));
+        }
+        let game_over = Game::from_fen("7k/8/8/8/8/8/6qr/7K w - - 0 1").unwrap();
+        assert_eq!(None, ai.choose(&game_over));
+    }
+
+    #[test]
+    fn test_spawn_search() {
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let updates: Vec<SearchUpdate> = spawn_search(&game, 3).iter().collect();
+        let depths: Vec<u8> = updates
+            .iter()
+            .filter_map(|update| match update {
+                SearchUpdate::Depth { depth, .. } => Some(*depth),
+                SearchUpdate::Done(_) => None,
+            })
+            .collect();
+        assert_eq!(vec![1, 2, 3], depths);
+        assert_eq!(Some(&SearchUpdate::Done(game.best_move(3))), updates.last());
+        assert_eq!(4, updates.len());
+
+        // game over, nothing to search
+        let game = Game::from_fen("7k/8/8/8/8/8/6qr/7K w - - 0 1").unwrap();
+        let updates: Vec<SearchUpdate> = spawn_search(&game, 3).iter().collect();
+        assert_eq!(vec![SearchUpdate::Done(None)], updates);
+    }
+
+    #[test]
+    fn test_rank_moves() {
+        // the knight fork wins the rook, Na7 hangs the knight
+        let game = Game::from_fen("r3k3/8/8/1N6/8/8/7P/4K3 w - - 0 1").unwrap();
+        let ranked = game.rank_moves(3);
+        assert_eq!(game.legal_moves().len(), ranked.len());
+        assert!(ranked.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        assert_eq!("Nc7+", ranked[0].0);
+        assert!(ranked[0].1 > 300);
+        let (_, hangs_knight) = ranked.iter().find(|(san, _)| san == "Na7").unwrap();
+        assert!(*hangs_knight < -300);
+
+        // mate in one comes first with the mate score
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(("Ra8#".to_string(), MATE_SCORE - 1), game.rank_moves(2)[0]);
+        assert!(Game::from_fen("7k/8/8/8/8/8/6qr/7K w - - 0 1").unwrap().rank_moves(2).is_empty());
+    }
+
+    #[test]
+    fn test_tablebase_search() {
+        // rook pawn with the defending king in the corner is a dead draw, the
+        // evaluation only sees the extra pawn
+        let game = Game::from_fen("k7/8/8/8/8/8/P7/K7 w - - 0 1").unwrap();
+        let (_, score) = game.best_move(3).unwrap();
+        if cfg!(feature = "tablebase") {
+            assert_eq!(0, score);
+        } else {
+            assert!(score > 50);
+        }
+
+        // king in front of the pawn wins, the search keeps the win
+        let mut game = Game::from_fen("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1").unwrap();
+        let (mv, score) = game.best_move(3).unwrap();
+        game.apply_move(mv).unwrap();
+        if cfg!(feature = "tablebase") {
+            assert!(score > TB_WIN_SCORE, "{}", score);
+            assert_eq!(Wdl::Loss, tablebase::probe(&game.board, false).unwrap().wdl);
+            // and goes on to promote
+            for _ in 0..30 {
+                if game.board.white_queens != 0 {
+                    break;
+                }
+                let (reply, _) = game.best_move(2).unwrap();
+                game.apply_move(reply).unwrap();
+                let (mv, _) = game.best_move(3).unwrap();
+                game.apply_move(mv).unwrap();
+            }
+            assert_ne!(0, game.board.white_queens, "{}", game.to_fen());
+        } else {
+            assert!((50..TB_WIN_SCORE).contains(&score), "{}", score);
+        }
+
+        // more pieces than the tables have, same score either way
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        assert_eq!(None, tablebase::probe(&game.board, true));
+        assert!(game.best_move(2).unwrap().1 > 0);
+    }
+
+    #[test]
+    fn test_best_move() {
+        // mate in one (back rank)
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let (mv, score) = game.best_move(3).unwrap();
+        assert_eq!("a1a8", mv.uci());
+        assert_eq!(MATE_SCORE - 1, score);
+
+        // takes the hanging queen
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        assert_eq!("d2d5", game.best_move(2).unwrap().0.uci());
+
+        // moves the attacked queen away without taking the defended pawn
+        let mut game = Game::from_fen("4k3/8/3p4/4p3/3Q4/8/8/4K3 w - - 0 1").unwrap();
+        let (mv, _) = game.best_move(2).unwrap();
+        game.apply_move(mv).unwrap();
+        let (reply, _) = game.best_move(1).unwrap();
+        game.apply_move(reply).unwrap();
+        assert_ne!(0, game.board.white_queens, "{} {}", mv.uci(), reply.uci());
+
+        // sensible opening move at depth 4
+        let game = Game::default();
+        let (mv, _) = game.best_move(4).unwrap();
+        assert!(["e2e4", "d2d4", "g1f3", "b1c3", "e2e3", "d2d3", "c2c4", "c2c3", "f2f4"].contains(&mv.uci().as_str()), "{}", mv.uci());
+
+        // game over
+        let game = Game::from_fen("7k/8/8/8/8/8/6qr/7K w - - 0 1").unwrap();
+        assert_eq!(None, game.best_move(2));
+    }
+
+    #[test]
+    fn test_random_game() {
+        let mut game = Game::default();
+        let mut seed = 1;
+        while let Some(mv) = pick_random_move(&game, seed) {
+            game.apply_move(mv).unwrap();
+            seed += 1;
+        }
+        assert_ne!(Status::Ongoing, game.status());
+    }
+}
@@ -1,10 +1,11 @@
+use crate::engine::magic::{bishop_attacks, rook_attacks};
 use crate::engine::moves::{
     compute_bishops_moves, compute_king_moves, compute_knights_moves, compute_pawns_moves,
-    compute_queens_moves, compute_rooks_moves, WHITE_PAWN_MOVES,
+    compute_queens_moves, compute_rooks_moves, KING_MOVES, KNIGHT_MOVES, WHITE_PAWN_MOVES,
 };
 use crate::engine::parser::Piece;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Board {
     pub white_pawns: u64,
     pub white_knights: u64,
@@ -43,9 +44,23 @@ pub struct Board {
     pub black_pawns_attack_moves: u64, // pawn can only attack diagonally
     pub white_attack_moves: u64,
     pub black_attack_moves: u64,
+
+    // piece on every square (indexed by bit index), kept in sync with the bitboards
+    mailbox: [Option<(Piece, bool)>; 64],
 }
 
+const PIECES: [Piece; 6] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Rook,
+    Piece::Bishop,
+    Piece::Queen,
+    Piece::King,
+];
+
 impl Board {
+    /// board from the piece placement field of a FEN, the remaining fields (if any)
+    /// are ignored, see [`Game::from_fen`](crate::engine::game::Game::from_fen)
     pub fn from_fen(fen: &str) -> Board {
         let mut white_pawns_builder = PositionBuilder::new();
         let mut white_knights_builder = PositionBuilder::new();
@@ -63,7 +78,8 @@ impl Board {
         let mut rank = 8;
         let mut file = 'a';
 
-        for c in fen.chars() {
+        let placement = fen.split_whitespace().next().unwrap_or_default();
+        for c in placement.chars() {
             match c {
                 'P' => {
                     white_pawns_builder = white_pawns_builder.add_piece(file, rank);
@@ -199,8 +215,11 @@ impl Board {
             black_pawns_attack_moves: 0,
             white_attack_moves: 0,
             black_attack_moves: 0,
+
+            mailbox: [None; 64],
         };
 
+        board.mailbox = board.compute_mailbox();
         board.update_compute_moves();
         board
     }
@@ -305,6 +324,11 @@ impl Board {
         if let Some(piece) = self.get_piece_at(from, is_white) {
             *piece = (*piece ^ from) | to;
             self.update_pieces();
+
+            let from_idx = from.trailing_zeros() as usize;
+            self.mailbox[to.trailing_zeros() as usize] = self.mailbox[from_idx];
+            self.mailbox[from_idx] = None;
+            self.debug_assert_mailbox();
         }
     }
 
@@ -313,9 +337,39 @@ impl Board {
         if let Some(piece) = self.get_piece_at(position, is_white) {
             *piece = *piece ^ position;
             self.update_pieces();
+
+            // captured piece may already be overwritten by the capturing piece
+            let idx = position.trailing_zeros() as usize;
+            if matches!(self.mailbox[idx], Some((_, color)) if color == is_white) {
+                self.mailbox[idx] = None;
+            }
+            self.debug_assert_mailbox();
         }
     }
 
+    /// puts a piece on an empty square without any checking
+    pub fn add_piece(&mut self, position: u64, is_white: bool, piece: Piece) {
+        let pieces = match (piece, is_white) {
+            (Piece::Pawn, true) => &mut self.white_pawns,
+            (Piece::Knight, true) => &mut self.white_knights,
+            (Piece::Rook, true) => &mut self.white_rooks,
+            (Piece::Bishop, true) => &mut self.white_bishops,
+            (Piece::Queen, true) => &mut self.white_queens,
+            (Piece::King, true) => &mut self.white_king,
+            (Piece::Pawn, false) => &mut self.black_pawns,
+            (Piece::Knight, false) => &mut self.black_knights,
+            (Piece::Rook, false) => &mut self.black_rooks,
+            (Piece::Bishop, false) => &mut self.black_bishops,
+            (Piece::Queen, false) => &mut self.black_queens,
+            (Piece::King, false) => &mut self.black_king,
+            (Piece::Castling, _) => return,
+        };
+        *pieces |= position;
+        self.update_pieces();
+        self.mailbox[position.trailing_zeros() as usize] = Some((piece, is_white));
+        self.debug_assert_mailbox();
+    }
+
     /// used for promotion. only perform promotion if pawn exists at the position
     pub fn replace_pawn(&mut self, position: u64, is_white: bool, new_piece: Piece) {
         let pawns = if is_white {
@@ -363,94 +417,75 @@ impl Board {
             } else {
                 self.black_pawns ^= position;
             }
+            self.mailbox[position.trailing_zeros() as usize] = Some((new_piece, is_white));
+            self.debug_assert_mailbox();
         }
     }
 
-    pub fn pieces_array(&self, unicode: bool) -> [[char; 8]; 8] {
-        let mut board_representation = [[' '; 8]; 8];
-        // Combine all pieces into a single representation
-        for rank in 0..8 {
-            for file in 0..8 {
-                let square_index = rank * 8 + file;
-
-                let piece = if (self.white_pawns >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♟'
-                    } else {
-                        'P'
-                    }
-                } else if (self.black_pawns >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♙'
-                    } else {
-                        'p'
-                    }
-                } else if (self.white_rooks >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♜'
-                    } else {
-                        'R'
-                    }
-                } else if (self.black_rooks >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♖'
-                    } else {
-                        'r'
-                    }
-                } else if (self.white_knights >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♞'
-                    } else {
-                        'N'
-                    }
-                } else if (self.black_knights >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♘'
-                    } else {
-                        'n'
-                    }
-                } else if (self.white_bishops >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♝'
-                    } else {
-                        'B'
-                    }
-                } else if (self.black_bishops >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♗'
-                    } else {
-                        'b'
-                    }
-                } else if (self.white_queens >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♛'
-                    } else {
-                        'Q'
-                    }
-                } else if (self.black_queens >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♕'
-                    } else {
-                        'q'
-                    }
-                } else if (self.white_king >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♚'
-                    } else {
-                        'K'
+    fn compute_mailbox(&self) -> [Option<(Piece, bool)>; 64] {
+        let mut mailbox = [None; 64];
+        for is_white in [true, false] {
+            for piece in PIECES {
+                let mut pieces = self.get_pieces(piece, is_white);
+                while pieces != 0 {
+                    mailbox[pieces.trailing_zeros() as usize] = Some((piece, is_white));
+                    pieces &= pieces - 1;
+                }
+            }
+        }
+        mailbox
+    }
+
+    /// mailbox must always match the bitboards. Skipped while a capture is in
+    /// progress (capturing piece moved, captured piece not yet removed)
+    fn debug_assert_mailbox(&self) {
+        if cfg!(debug_assertions) && self.white_pieces & self.black_pieces == 0 {
+            debug_assert_eq!(self.compute_mailbox(), self.mailbox, "mailbox out of sync");
+        }
+    }
+
+    /// iterates all pieces on the board as (position, piece type, is white)
+    pub fn pieces(&self) -> impl Iterator<Item = (u64, Piece, bool)> + '_ {
+        self.mailbox
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, square)| square.map(|(piece, is_white)| (1u64 << idx, piece, is_white)))
+    }
+
+    /// piece placement part of FEN (e.g. `rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR`)
+    pub fn to_fen(self) -> String {
+        let ranks: Vec<String> = self
+            .pieces_array(false)
+            .iter()
+            .rev()
+            .map(|row| {
+                let mut rank = String::new();
+                let mut empty = 0;
+                for &piece in row {
+                    if piece == '.' {
+                        empty += 1;
+                        continue;
                     }
-                } else if (self.black_king >> square_index) & 1 != 0 {
-                    if unicode {
-                        '♔'
-                    } else {
-                        'k'
+                    if empty > 0 {
+                        rank.push_str(&empty.to_string());
+                        empty = 0;
                     }
-                } else {
-                    '.' // Empty square
-                };
+                    rank.push(piece);
+                }
+                if empty > 0 {
+                    rank.push_str(&empty.to_string());
+                }
+                rank
+            })
+            .collect();
+        ranks.join("/")
+    }
 
-                board_representation[rank as usize][file as usize] = piece;
-            }
+    pub fn pieces_array(&self, unicode: bool) -> [[char; 8]; 8] {
+        let mut board_representation = [['.'; 8]; 8];
+        for (square, piece, is_white) in self.pieces() {
+            let idx = square.trailing_zeros() as usize;
+            board_representation[idx / 8][idx % 8] = piece_char(piece, is_white, unicode);
         }
         board_representation
     }
@@ -470,45 +505,178 @@ impl Board {
         println!("    a  b  c  d  e  f  g  h");
     }
 
-    /// Helper function to return the piece type based on position
-    /// returns optional piece type and boolean flag to indicate if it's white or black
-    pub fn get_piece_type_at(&self, position: u64) -> Option<(Piece, bool)> {
-        if self.white_pieces & position != 0 {
-            if self.white_pawns & position != 0 {
-                Some((Piece::Pawn, true))
-            } else if self.white_bishops & position != 0 {
-                Some((Piece::Bishop, true))
-            } else if self.white_rooks & position != 0 {
-                Some((Piece::Rook, true))
-            } else if self.white_knights & position != 0 {
-                Some((Piece::Knight, true))
-            } else if self.white_queens & position != 0 {
-                Some((Piece::Queen, true))
-            } else if self.white_king & position != 0 {
-                Some((Piece::King, true))
+    /// returns bitboard for the given piece type and color
+    pub fn get_pieces(&self, piece_type: Piece, is_white: bool) -> u64 {
+        match (piece_type, is_white) {
+            (Piece::Pawn, true) => self.white_pawns,
+            (Piece::Pawn, false) => self.black_pawns,
+            (Piece::Knight, true) => self.white_knights,
+            (Piece::Knight, false) => self.black_knights,
+            (Piece::Rook, true) => self.white_rooks,
+            (Piece::Rook, false) => self.black_rooks,
+            (Piece::Bishop, true) => self.white_bishops,
+            (Piece::Bishop, false) => self.black_bishops,
+            (Piece::Queen, true) => self.white_queens,
+            (Piece::Queen, false) => self.black_queens,
+            (Piece::King | Piece::Castling, true) => self.white_king,
+            (Piece::King | Piece::Castling, false) => self.black_king,
+        }
+    }
+
+    /// number of pieces of the given type and color
+    pub fn count(&self, piece_type: Piece, is_white: bool) -> u32 {
+        self.get_pieces(piece_type, is_white).count_ones()
+    }
+
+    /// board flipped upside down (rank 1 becomes rank 8) with the colors swapped, a
+    /// position and its mirror are the same position for the other side
+    pub fn mirror_vertical(&self) -> Board {
+        Board::new(
+            self.black_pawns.swap_bytes(),
+            self.black_knights.swap_bytes(),
+            self.black_rooks.swap_bytes(),
+            self.black_bishops.swap_bytes(),
+            self.black_queens.swap_bytes(),
+            self.black_king.swap_bytes(),
+            self.white_pawns.swap_bytes(),
+            self.white_knights.swap_bytes(),
+            self.white_rooks.swap_bytes(),
+            self.white_bishops.swap_bytes(),
+            self.white_queens.swap_bytes(),
+            self.white_king.swap_bytes(),
+        )
+    }
+
+    /// known drawn endgame: king, bishop and rook pawn(s) against a bare king, where the
+    /// bishop can't control the promotion square and the defending king holds the corner.
+    /// It's not a dead position by the rules (the defender can still blunder) so the
+    /// game isn't ended, it's meant for evaluation
+    pub fn is_wrong_bishop_draw(&self) -> bool {
+        for is_white in [true, false] {
+            let pawns = self.get_pieces(Piece::Pawn, is_white);
+            let bishops = self.get_pieces(Piece::Bishop, is_white);
+            let king = self.get_pieces(Piece::King, is_white);
+            let (own_pieces, opponent_pieces) = if is_white {
+                (self.white_pieces, self.black_pieces)
             } else {
-                None
+                (self.black_pieces, self.white_pieces)
+            };
+            let defending_king = self.get_pieces(Piece::King, !is_white);
+
+            if pawns == 0
+                || bishops.count_ones() != 1
+                || own_pieces != pawns | bishops | king
+                || opponent_pieces != defending_king
+            {
+                continue;
             }
-        } else if self.black_pieces & position != 0 {
-            if self.black_pawns & position != 0 {
-                Some((Piece::Pawn, false))
-            } else if self.black_bishops & position != 0 {
-                Some((Piece::Bishop, false))
-            } else if self.black_rooks & position != 0 {
-                Some((Piece::Rook, false))
-            } else if self.black_knights & position != 0 {
-                Some((Piece::Knight, false))
-            } else if self.black_queens & position != 0 {
-                Some((Piece::Queen, false))
-            } else if self.black_king & position != 0 {
-                Some((Piece::King, false))
+
+            let file = if pawns & !MASK_FILE_A == 0 {
+                MASK_FILE_A
+            } else if pawns & !MASK_FILE_H == 0 {
+                MASK_FILE_H
             } else {
-                None
+                continue;
+            };
+            let promotion = file & if is_white { MASK_RANK_8 } else { MASK_RANK_1 };
+            let wrong_bishop = (bishops & LIGHT_SQUARES == 0) != (promotion & LIGHT_SQUARES == 0);
+            let corner = promotion | KING_MOVES[promotion.trailing_zeros() as usize];
+
+            if wrong_bishop && defending_king & corner != 0 {
+                return true;
             }
-        } else {
-            None
         }
+        false
+    }
+
+    /// total material value of one side using standard piece values
+    /// (pawn 1, knight 3, bishop 3, rook 5, queen 9). King is not counted
+    pub fn material(&self, is_white: bool) -> i32 {
+        [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+            .into_iter()
+            .map(|piece| self.count(piece, is_white) as i32 * piece_value(piece))
+            .sum()
     }
+
+    /// Helper function to return the piece type based on position
+    /// returns optional piece type and boolean flag to indicate if it's white or black
+    pub fn get_piece_type_at(&self, position: u64) -> Option<(Piece, bool)> {
+        if position.count_ones() != 1 {
+            return None;
+        }
+        self.mailbox[position.trailing_zeros() as usize]
+    }
+
+    /// moves the piece on `from` to `to` (removing the opponent piece on `to`) without
+    /// any checking. The computed moves are not updated, use [`Board::attackers_to`]
+    /// until [`Board::unmake`] restores the position with the returned token
+    pub fn apply_move_unchecked(&mut self, from: u64, to: u64, is_white: bool) -> MoveUndo {
+        let captured = match self.get_piece_type_at(to) {
+            Some((piece, piece_is_white)) if piece_is_white != is_white => {
+                self.remove_piece(to, !is_white);
+                Some(piece)
+            }
+            _ => None,
+        };
+        self.move_piece(from, to, is_white);
+        MoveUndo { from, to, is_white, captured }
+    }
+
+    /// takes back a move made with [`Board::apply_move_unchecked`]
+    pub fn unmake(&mut self, undo: MoveUndo) {
+        self.move_piece(undo.to, undo.from, undo.is_white);
+        if let Some(piece) = undo.captured {
+            self.add_piece(undo.to, !undo.is_white, piece);
+        }
+    }
+
+    /// pieces of the given color attacking `square`, looked up from the occupancy so
+    /// it doesn't depend on the computed moves
+    pub fn attackers_to(&self, square: u64, by_white: bool) -> u64 {
+        let idx = square.trailing_zeros() as usize;
+        let (pawns, knights, bishops, rooks, queens, king) = if by_white {
+            (
+                self.white_pawns,
+                self.white_knights,
+                self.white_bishops,
+                self.white_rooks,
+                self.white_queens,
+                self.white_king,
+            )
+        } else {
+            (
+                self.black_pawns,
+                self.black_knights,
+                self.black_bishops,
+                self.black_rooks,
+                self.black_queens,
+                self.black_king,
+            )
+        };
+        // squares a pawn attacks `square` from
+        let pawn_squares = if by_white {
+            ((square & !MASK_FILE_H) >> 7) | ((square & !MASK_FILE_A) >> 9)
+        } else {
+            ((square & !MASK_FILE_H) << 9) | ((square & !MASK_FILE_A) << 7)
+        };
+        let straight = rook_attacks(idx, self.occupied);
+        let diagonal = bishop_attacks(idx, self.occupied);
+
+        (pawn_squares & pawns)
+            | (KNIGHT_MOVES[idx] & knights)
+            | (KING_MOVES[idx] & king)
+            | (straight & (rooks | queens))
+            | (diagonal & (bishops | queens))
+    }
+}
+
+/// what [`Board::apply_move_unchecked`] changed, restored by [`Board::unmake`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveUndo {
+    from: u64,
+    to: u64,
+    is_white: bool,
+    captured: Option<Piece>,
 }
 
 impl Default for Board {
@@ -517,6 +685,35 @@ impl Default for Board {
     }
 }
 
+/// FEN letter of a piece (uppercase for white) or its unicode chess symbol
+pub fn piece_char(piece: Piece, is_white: bool, unicode: bool) -> char {
+    let (fen, white_symbol, black_symbol) = match piece {
+        Piece::Pawn => ('p', '♟', '♙'),
+        Piece::Knight => ('n', '♞', '♘'),
+        Piece::Bishop => ('b', '♝', '♗'),
+        Piece::Rook => ('r', '♜', '♖'),
+        Piece::Queen => ('q', '♛', '♕'),
+        Piece::King | Piece::Castling => ('k', '♚', '♔'),
+    };
+    match (unicode, is_white) {
+        (true, true) => white_symbol,
+        (true, false) => black_symbol,
+        (false, true) => fen.to_ascii_uppercase(),
+        (false, false) => fen,
+    }
+}
+
+/// standard material value of a piece, king has no material value
+pub fn piece_value(piece: Piece) -> i32 {
+    match piece {
+        Piece::Pawn => 1,
+        Piece::Knight | Piece::Bishop => 3,
+        Piece::Rook => 5,
+        Piece::Queen => 9,
+        Piece::King | Piece::Castling => 0,
+    }
+}
+
 /// Helper function to render single bitboard for debugging
 pub fn render_bitboard(bitboard: &u64, render: char) {
     println!("  +------------------------+");
@@ -555,6 +752,26 @@ pub fn bitboard_single(file: char, rank: u64) -> Option<u64> {
     }
 }
 
+/// Helper to return the algebraic name (e.g. `e4`) of a single bit bitboard
+pub fn square_name(bitboard: u64) -> Option<String> {
+    if bitboard.count_ones() != 1 {
+        return None;
+    }
+    let idx = bitboard.trailing_zeros() as u8;
+    Some(format!("{}{}", (b'a' + idx % 8) as char, idx / 8 + 1))
+}
+
+/// Helper to parse algebraic square name (e.g. `e4`) into single bit bitboard
+pub fn parse_square(square: &str) -> Option<u64> {
+    let mut chars = square.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(file), Some(rank @ '1'..='8'), None) => {
+            bitboard_single(file, rank.to_digit(10)? as u64)
+        }
+        _ => None,
+    }
+}
+
 /// Checjk
 pub fn is_rank(bitboard: u64, rank: u64) -> bool {
     let mask = match rank {
@@ -618,6 +835,8 @@ impl PositionBuilder {
     }
 }
 
+pub const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+
 pub const MASK_RANK_1: u64 =
     0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_11111111;
 pub const MASK_RANK_2: u64 =
@@ -755,6 +974,12 @@ pub mod tests {
         // confirm c4 is empty
         assert_eq!(0, board.white_pieces & bitboard_single('c', 4).unwrap());
         assert_eq!(0, board.black_pieces & bitboard_single('c', 4).unwrap());
+
+        // other FEN fields are ignored
+        assert_eq!(
+            board,
+            Board::from_fen("1k5q/p5Pr/pq6/8/8/5NB1/1P6/4K3 w - - 0 1")
+        );
     }
 
     #[test]
@@ -1015,6 +1240,104 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_to_fen() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+            "4k1bn/6nn/R6K/2q5/3P2N1/6Q1/3P4/7B",
+            "r3k2r/8/8/8/8/8/8/R3K2R",
+        ] {
+            assert_eq!(fen, Board::from_fen(fen).to_fen());
+        }
+    }
+
+    #[test]
+    fn test_pieces_array() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/3P4/R3K3");
+        let pieces = board.pieces_array(false);
+        assert_eq!(['R', '.', '.', '.', 'K', '.', '.', '.'], pieces[0]);
+        assert_eq!(['.', '.', '.', 'P', '.', '.', '.', '.'], pieces[1]);
+        assert_eq!(['.', '.', '.', '.', 'k', '.', '.', '.'], pieces[7]);
+        assert_eq!('♚', board.pieces_array(true)[0][4]);
+        assert_eq!('♔', board.pieces_array(true)[7][4]);
+    }
+
+    #[test]
+    fn test_square_name() {
+        assert_eq!(Some("a1".to_string()), square_name(bitboard_single('a', 1).unwrap()));
+        assert_eq!(Some("e3".to_string()), square_name(bitboard_single('e', 3).unwrap()));
+        assert_eq!(Some("h8".to_string()), square_name(bitboard_single('h', 8).unwrap()));
+        assert_eq!(None, square_name(0));
+        assert_eq!(None, square_name(MASK_RANK_1));
+
+        assert_eq!(bitboard_single('e', 3), parse_square("e3"));
+        assert_eq!(bitboard_single('h', 8), parse_square("h8"));
+        assert_eq!(None, parse_square("e9"));
+        assert_eq!(None, parse_square("i1"));
+        assert_eq!(None, parse_square("e"));
+        assert_eq!(None, parse_square("e33"));
+    }
+
+    #[test]
+    fn test_mirror_vertical() {
+        assert_eq!(Board::default(), Board::default().mirror_vertical());
+
+        for fen in [
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R",
+            "4k3/8/8/3q4/8/8/PP6/R3K3",
+            "r1b1k2r/ppq2ppp/2n5/8/1b1N4/2N5/PPP2PPP/R2QKB1R",
+        ] {
+            let board = Board::from_fen(fen);
+            let mirror = board.mirror_vertical();
+            assert_eq!(board, mirror.mirror_vertical());
+            assert_eq!(
+                board.material(true) - board.material(false),
+                -(mirror.material(true) - mirror.material(false))
+            );
+            assert_eq!(board.white_attack_moves.swap_bytes(), mirror.black_attack_moves);
+            assert_eq!(board.black_attack_moves.swap_bytes(), mirror.white_attack_moves);
+        }
+
+        let board = Board::from_fen("4k3/8/8/3q4/8/8/PP6/R3K3").mirror_vertical();
+        assert_eq!("r3k3/pp6/8/8/3Q4/8/8/4K3", board.to_fen());
+    }
+
+    #[test]
+    fn test_is_wrong_bishop_draw() {
+        // light squared bishop can't cover h8, black king holds the corner
+        assert!(Board::from_fen("7k/8/5K2/7P/8/8/8/3B4").is_wrong_bishop_draw());
+        assert!(Board::from_fen("8/8/8/8/p7/1b6/8/K6k").is_wrong_bishop_draw());
+        // doubled rook pawns don't help either
+        assert!(Board::from_fen("6k1/8/5K2/7P/7P/8/8/3B4").is_wrong_bishop_draw());
+
+        // dark squared bishop controls h8
+        assert!(!Board::from_fen("7k/8/5K2/7P/8/8/8/2B5").is_wrong_bishop_draw());
+        // defending king is too far from the corner
+        assert!(!Board::from_fen("8/8/5K2/7P/8/8/8/k2B4").is_wrong_bishop_draw());
+        // not a rook pawn
+        assert!(!Board::from_fen("7k/8/5K2/6P1/8/8/8/3B4").is_wrong_bishop_draw());
+        // defender has material
+        assert!(!Board::from_fen("7k/8/5K2/7P/8/8/p7/3B4").is_wrong_bishop_draw());
+    }
+
+    #[test]
+    fn test_count_and_material() {
+        let board = Board::default();
+        assert_eq!(8, board.count(Piece::Pawn, true));
+        assert_eq!(2, board.count(Piece::Knight, false));
+        assert_eq!(1, board.count(Piece::Queen, false));
+        assert_eq!(1, board.count(Piece::King, true));
+        // king excluded: 8 + 2 * 3 + 2 * 3 + 2 * 5 + 9
+        assert_eq!(39, board.material(true));
+        assert_eq!(39, board.material(false));
+
+        let board = Board::from_fen("1k5q/p5Pr/pq6/8/8/5NB1/1P6/4K3");
+        assert_eq!(2, board.count(Piece::Queen, false));
+        assert_eq!(0, board.count(Piece::Rook, true));
+        assert_eq!(8, board.material(true));
+        assert_eq!(25, board.material(false));
+    }
+
     #[test]
     fn test() {
         let board = Board::default();
@@ -0,0 +1,174 @@
+use crate::engine::game::{FenError, Game, Move, MoveError};
+use crate::engine::rng::Rng;
+use std::collections::HashMap;
+
+/// opening book read from a plain text file (`--book`), one line of moves per line:
+///
+/// ```text
+/// # comment line, blank lines are skipped as well
+/// e4 e5 Nf3 Nc6 Bb5
+/// e2e4 c7c5 g1f3
+/// rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 1; Nf6 c4 e6
+/// ```
+///
+/// Moves are SAN or UCI coordinates. A line starts from the standard position, or
+/// from the FEN before the `;`. Every position along a line gets the line's next
+/// move, a move found in more lines is picked more often
+#[derive(Debug, Default, Clone)]
+pub struct Book {
+    // book moves and their weight by position (see [`Game::hash`])
+    positions: HashMap<u64, Vec<(Move, u32)>>,
+}
+
+/// line of the book file (starting at 1) that can't be read
+#[derive(Debug, PartialEq, Clone)]
+pub enum BookError {
+    InvalidFen { line: usize, error: FenError },
+    IllegalMove { line: usize, san: String, error: MoveError },
+}
+
+impl Book {
+    /// reads the book, every move is checked by playing it
+    pub fn parse(text: &str) -> Result<Book, BookError> {
+        let mut book = Book::default();
+        for (idx, line) in text.lines().enumerate() {
+            let line_number = idx + 1;
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            let (fen, moves) = match line.split_once(';') {
+                Some((fen, moves)) => (Some(fen.trim()), moves),
+                None => (None, line),
+            };
+            let mut game = match fen.filter(|fen| !fen.is_empty()) {
+                Some(fen) => Game::from_fen(fen).map_err(|error| BookError::InvalidFen { line: line_number, error })?,
+                None => Game::default(),
+            };
+            for san in moves.split_whitespace() {
+                let illegal = |error| BookError::IllegalMove { line: line_number, san: san.to_string(), error };
+                let (mv, next) = book_move(&game, san).map_err(illegal)?;
+                book.add(game.hash(), mv);
+                game = next;
+            }
+        }
+        Ok(book)
+    }
+
+    fn add(&mut self, hash: u64, mv: Move) {
+        let moves = self.positions.entry(hash).or_default();
+        match moves.iter_mut().find(|(book_move, _)| *book_move == mv) {
+            Some((_, weight)) => *weight += 1,
+            None => moves.push((mv, 1)),
+        }
+    }
+
+    /// book moves of the position with their weight, only legal ones in case another
+    /// position has the same hash
+    pub fn moves(&self, game: &Game) -> Vec<(Move, u32)> {
+        let legal = game.legal_moves();
+        self.positions
+            .get(&game.hash())
+            .map_or_else(Vec::new, |moves| moves.iter().filter(|(mv, _)| legal.contains(mv)).copied().collect())
+    }
+
+    /// weighted random book move, `None` once the game is out of book. The same seed
+    /// always picks the same move
+    pub fn pick(&self, game: &Game, seed: u64) -> Option<Move> {
+        let moves = self.moves(game);
+        let total: u32 = moves.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = Rng::new(seed).next_range(total as usize) as u32;
+        moves.into_iter().find_map(|(mv, weight)| {
+            if roll < weight {
+                Some(mv)
+            } else {
+                roll -= weight;
+                None
+            }
+        })
+    }
+}
+
+/// plays the SAN or UCI move and returns it as one of the legal moves, with the
+/// game after it
+fn book_move(game: &Game, san: &str) -> Result<(Move, Game), MoveError> {
+    let mut next = game.clone();
+    next.process_move(san)?;
+    let mv = game
+        .legal_moves()
+        .iter()
+        .copied()
+        .find(|mv| {
+            let mut played = game.clone();
+            played.apply_move(*mv).is_ok() && played.hash() == next.hash()
+        })
+        .ok_or(MoveError::ParseError)?;
+    Ok((mv, next))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::engine::board::parse_square;
+
+    #[test]
+    fn test_parse_book() {
+        let book = Book::parse(
+            "# main lines\n\
+             e4 e5 Nf3 Nc6 Bb5\n\
+             e2e4 c7c5\n\
+             \n\
+             e4 e5 Nf3 Nf6\n\
+             rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 1; Nf6 c4\n",
+        )
+        .unwrap();
+        let san = |game: &Game, moves: Vec<(Move, u32)>| {
+            let mut moves: Vec<(String, u32)> = moves.into_iter().map(|(mv, weight)| (game.san(mv).unwrap(), weight)).collect();
+            moves.sort();
+            moves
+        };
+        let game = Game::default();
+        assert_eq!(vec![("e4".to_string(), 3)], san(&game, book.moves(&game)));
+        let game = Game::from_uci_moves(None, &["e2e4"]).unwrap();
+        assert_eq!(vec![("c5".to_string(), 1), ("e5".to_string(), 2)], san(&game, book.moves(&game)));
+        // the FEN line is found by position, whatever the move order
+        let game = Game::from_uci_moves(None, &["d2d4"]).unwrap();
+        assert_eq!(vec![("Nf6".to_string(), 1)], san(&game, book.moves(&game)));
+        let game = Game::from_uci_moves(None, &["d2d4", "g8f6"]).unwrap();
+        assert_eq!(vec![("c4".to_string(), 1)], san(&game, book.moves(&game)));
+        // out of book
+        let game = Game::from_uci_moves(None, &["a2a3"]).unwrap();
+        assert!(book.moves(&game).is_empty());
+        assert_eq!(None, book.pick(&game, 1));
+    }
+
+    #[test]
+    fn test_parse_book_errors() {
+        assert!(matches!(Book::parse("e4 e5\ne4 e5 Nf6"), Err(BookError::IllegalMove { line: 2, san, .. }) if san == "Nf6"));
+        assert!(matches!(Book::parse("e4 e5 e4"), Err(BookError::IllegalMove { line: 1, .. })));
+        // mates are not comments
+        assert!(Book::parse("f3 e5 g4 Qh4#").is_ok());
+        assert!(matches!(Book::parse("e4\nbad fen; e4"), Err(BookError::InvalidFen { line: 2, .. })));
+        assert!(Book::parse("# empty\n\n").unwrap().moves(&Game::default()).is_empty());
+    }
+
+    #[test]
+    fn test_pick() {
+        let book = Book::parse("e4\ne4\ne4\nd4").unwrap();
+        let game = Game::default();
+        let e4 = Move { from: parse_square("e2").unwrap(), to: parse_square("e4").unwrap(), promotion: None };
+        let picks: Vec<Move> = (0..400).filter_map(|seed| book.pick(&game, seed)).collect();
+        assert_eq!(400, picks.len());
+        let e4_picks = picks.iter().filter(|mv| **mv == e4).count();
+        assert!((250..350).contains(&e4_picks), "{}", e4_picks);
+        assert_eq!(book.pick(&game, 7), book.pick(&game, 7));
+
+        // promotions keep their piece
+        let book = Book::parse("7k/P7/8/8/8/8/8/K7 w - - 0 1; a8=N").unwrap();
+        let game = Game::from_fen("7k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!("a8=N", game.san(book.pick(&game, 0).unwrap()).unwrap());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::engine::game::Game;
+
+/// number of Chess960 starting positions
+pub const POSITIONS: u16 = 960;
+
+/// index of the standard starting position
+pub const STANDARD_POSITION: u16 = 518;
+
+/// white pieces of the back rank (a to h) of a Chess960 starting position, numbered
+/// 0 to 959 as in Scharnagl's scheme (e.g. `RNBQKBNR` for 518). Bishops are on
+/// opposite colors and the king between the rooks
+pub fn back_rank(index: u16) -> String {
+    let mut rank = [' '; 8];
+    let mut n = (index % POSITIONS) as usize;
+    // light-squared bishop on b, d, f or h, then the dark-squared one on a, c, e or g
+    rank[n % 4 * 2 + 1] = 'B';
+    n /= 4;
+    rank[n % 4 * 2] = 'B';
+    n /= 4;
+    // queen, knights, then rook king rook on the remaining free squares
+    place(&mut rank, n % 6, 'Q');
+    n /= 6;
+    let (first, second) = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)][n];
+    place(&mut rank, second, 'N');
+    place(&mut rank, first, 'N');
+    for piece in ['R', 'K', 'R'] {
+        place(&mut rank, 0, piece);
+    }
+    rank.iter().collect()
+}
+
+/// puts the piece on the `nth` free square (counting from the a-file)
+fn place(rank: &mut [char; 8], nth: usize, piece: char) {
+    if let Some(square) = rank.iter_mut().filter(|square| **square == ' ').nth(nth) {
+        *square = piece;
+    }
+}
+
+impl Game {
+    /// game from the Chess960 starting position `index` (see [`back_rank`]) with
+    /// Chess960 castling, black mirrors white's back rank
+    pub fn chess960(index: u16) -> Game {
+        let white = back_rank(index);
+        // Shredder-FEN castling field, the rook files (e.g. `HAha`)
+        let rook_files: String = white
+            .char_indices()
+            .filter(|(_, piece)| *piece == 'R')
+            .map(|(file, _)| (b'A' + file as u8) as char)
+            .rev()
+            .collect();
+        let fen = format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{} - 0 1",
+            white.to_lowercase(),
+            white,
+            rook_files,
+            rook_files.to_lowercase()
+        );
+        Game::from_fen(&fen).expect("valid Chess960 position")
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::engine::board::parse_square;
+    use crate::engine::game::{Move, Status};
+
+    #[test]
+    fn test_back_rank() {
+        assert_eq!("RNBQKBNR", back_rank(STANDARD_POSITION));
+        assert_eq!("BBQNNRKR", back_rank(0));
+        assert_eq!("RKRNNQBB", back_rank(959));
+        for index in 0..POSITIONS {
+            let rank = back_rank(index);
+            let file = |piece| rank.find(piece).unwrap();
+            assert_eq!(2, rank.matches('B').count());
+            assert_eq!(1, (file('B') + rank.rfind('B').unwrap()) % 2, "{}", rank);
+            assert!(file('R') < file('K') && file('K') < rank.rfind('R').unwrap(), "{}", rank);
+        }
+    }
+
+    #[test]
+    fn test_chess960_game() {
+        let game = Game::chess960(STANDARD_POSITION);
+        assert!(game.chess960);
+        assert_eq!("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1", game.to_fen());
+        assert_eq!(20, game.legal_moves().len());
+        assert_eq!(Status::Ongoing, Game::chess960(0).status());
+        assert_eq!(Game::chess960(0).to_fen(), Game::from_fen(&Game::chess960(0).to_fen()).unwrap().to_fen());
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        let square = |name| parse_square(name).unwrap();
+        // king on b1 with the rooks on a1 and h1, queenside castling leaves the king on c1
+        let mut game = Game::from_fen("1k5r/8/8/8/8/8/8/RK5R w HA - 0 1").unwrap();
+        assert!(game.chess960);
+        let castling = Move { from: square("b1"), to: square("a1"), promotion: None };
+        assert!(game.legal_moves().contains(&castling));
+        assert_eq!("O-O-O", game.san(castling).unwrap());
+        game.process_move("O-O-O").unwrap();
+        assert_eq!("1k5r/8/8/8/8/8/8/2KR3R b - - 1 1", game.to_fen());
+        game.undo().unwrap();
+        assert_eq!("1k5r/8/8/8/8/8/8/RK5R w HA - 0 1", game.to_fen());
+
+        // king on g1 stays, the rook jumps over nothing from h1 to f1
+        let mut game = Game::from_fen("6k1/8/8/8/8/8/8/R5KR w HA - 0 1").unwrap();
+        game.apply_uci("g1h1").unwrap();
+        assert_eq!("6k1/8/8/8/8/8/8/R4RK1 b - - 1 1", game.to_fen());
+        assert_eq!("O-O", game.history()[0].san);
+        game.undo().unwrap();
+
+        // the king passes f1, e1 and d1 to c1, all have to be free and not attacked
+        game.process_move("O-O-O").unwrap();
+        assert_eq!("6k1/8/8/8/8/8/8/2KR3R b - - 1 1", game.to_fen());
+        let game = Game::from_fen("3r2k1/8/8/8/8/8/8/R5KR w HA - 0 1").unwrap();
+        assert!(game.san(Move { from: square("g1"), to: square("a1"), promotion: None }).is_err());
+
+        // the castling rook doesn't shield the king from a rook behind it
+        let game = Game::from_fen("6k1/8/8/8/8/8/8/rRK4R w HB - 0 1").unwrap();
+        assert!(game.san(Move { from: square("c1"), to: square("b1"), promotion: None }).is_err());
+        assert_eq!("O-O", game.san(Move { from: square("c1"), to: square("h1"), promotion: None }).unwrap());
+
+        // moving a castling rook only drops its own right
+        let mut game = Game::from_fen("6k1/8/8/8/8/8/8/1R2K1R1 w GB - 0 1").unwrap();
+        game.process_move("Rg2").unwrap();
+        assert_eq!("6k1/8/8/8/8/8/6R1/1R2K3 b B - 1 1", game.to_fen());
+
+        // once the rook has left, the king steps onto its square like any other move
+        let mut game = Game::from_fen("1k6/8/8/8/8/8/8/RK5R w HA - 0 1").unwrap();
+        game.process_move("Ra5").unwrap();
+        game.process_move("Kc8").unwrap();
+        let king_move = Move { from: square("b1"), to: square("a1"), promotion: None };
+        assert!(game.legal_moves().contains(&king_move));
+        assert_eq!("Ka1", game.san(king_move).unwrap());
+        assert_eq!("Ka1", game.clone().apply_move(king_move).unwrap().san);
+        game.process_move("Ka1").unwrap();
+        assert_eq!("Ka1", game.history()[2].san);
+        assert_eq!("2k5/8/8/R7/8/8/8/K6R b - - 3 2", game.to_fen());
+        assert_eq!(game.to_fen(), Game::from_save_file(&game.to_save_file()).unwrap().to_fen());
+        assert_eq!(game.to_fen(), Game::from_pgn(&game.to_pgn()).unwrap().to_fen());
+    }
+
+    #[test]
+    fn test_chess960_perft() {
+        let game = Game::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        assert_eq!(21, game.perft(1));
+        assert_eq!(528, game.perft(2));
+        assert_eq!(12189, game.perft(3));
+        let game = Game::from_fen("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9").unwrap();
+        assert_eq!(21, game.perft(1));
+        assert_eq!(807, game.perft(2));
+        assert_eq!(18002, game.perft(3));
+    }
+}
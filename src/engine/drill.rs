@@ -0,0 +1,125 @@
+use crate::engine::game::Game;
+use crate::engine::openings;
+
+/// opening drill, the user plays one side of a book line and the other side is
+/// answered from the book. Moves leaving the line are rejected with the book move
+#[derive(Debug, Clone)]
+pub struct Drill {
+    pub name: String,
+    pub user_is_white: bool,
+    line: Vec<String>,
+    // number of book moves played so far
+    played: usize,
+}
+
+impl Drill {
+    /// drill for a SAN line (e.g. `e4 e5 Nf3`) from the start position
+    pub fn new(name: &str, line: &str, user_is_white: bool) -> Drill {
+        Drill {
+            name: name.to_string(),
+            user_is_white,
+            line: line.split_whitespace().map(|mv| mv.to_string()).collect(),
+            played: 0,
+        }
+    }
+
+    /// drill for one of the known openings (see [`openings::line`])
+    pub fn from_opening(name: &str, user_is_white: bool) -> Option<Drill> {
+        openings::line(name).map(|line| Drill::new(name.trim(), line, user_is_white))
+    }
+
+    /// next book move, `None` once the line is complete
+    pub fn next_move(&self) -> Option<&str> {
+        self.line.get(self.played).map(|mv| mv.as_str())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.played >= self.line.len()
+    }
+
+    /// whether the next book move is played by the book (not the user)
+    pub fn is_book_turn(&self) -> bool {
+        !self.is_finished() && (self.played % 2 == 0) != self.user_is_white
+    }
+
+    pub fn advance(&mut self) {
+        self.played += 1;
+    }
+
+    pub fn restart(&mut self) {
+        self.played = 0;
+    }
+
+    /// compares the move against the next book move by the position it leads to, so
+    /// other ways of writing the same move (e.g. `Ngf3` for `Nf3`) are accepted.
+    /// Returns the book move if `cmd` leaves the line. Illegal moves and moves after
+    /// the end of the line are not checked here
+    pub fn verify(&self, game: &Game, cmd: &str) -> Result<(), String> {
+        let expected = match self.next_move() {
+            Some(expected) => expected,
+            None => return Ok(()),
+        };
+
+        let mut played = game.clone();
+        if played.process_move(cmd).is_err() {
+            return Ok(());
+        }
+        let mut book = game.clone();
+        match book.process_move(expected) {
+            Ok(_) if book.to_fen() == played.to_fen() => Ok(()),
+            _ => Err(expected.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let mut drill = Drill::from_opening("Ruy Lopez", true).unwrap();
+        let mut game = Game::default();
+        assert!(!drill.is_book_turn());
+
+        assert_eq!(Ok(()), drill.verify(&game, "e4"));
+        assert_eq!(Err("e4".to_string()), drill.verify(&game, "d4"));
+        // illegal moves are left to the game to report
+        assert_eq!(Ok(()), drill.verify(&game, "e5"));
+
+        game.process_move("e4").unwrap();
+        drill.advance();
+        assert!(drill.is_book_turn());
+        assert_eq!(Some("e5"), drill.next_move());
+        game.process_move("e5").unwrap();
+        drill.advance();
+
+        // same move written differently
+        assert_eq!(Ok(()), drill.verify(&game, "Ngf3"));
+        assert_eq!(Err("Nf3".to_string()), drill.verify(&game, "Nc3"));
+
+        for mv in ["Nf3", "Nc6", "Bb5"] {
+            game.process_move(mv).unwrap();
+            drill.advance();
+        }
+        assert!(drill.is_finished());
+        assert!(!drill.is_book_turn());
+        assert_eq!(None, drill.next_move());
+        assert_eq!(Ok(()), drill.verify(&game, "a6"));
+
+        drill.restart();
+        assert_eq!(Some("e4"), drill.next_move());
+    }
+
+    #[test]
+    fn test_book_turn() {
+        let mut drill = Drill::new("Sicilian", "e4 c5", false);
+        assert!(drill.is_book_turn());
+        drill.advance();
+        assert!(!drill.is_book_turn());
+        drill.advance();
+        assert!(!drill.is_book_turn());
+
+        assert!(Drill::from_opening("Unknown Opening", true).is_none());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::engine::game::{FenError, Game, Move};
+use std::collections::HashMap;
+
+impl Game {
+    /// reads an EPD record: the first four FEN fields followed by `;` terminated
+    /// operations (e.g. `bm Qd6; id "WAC.001";`). The clocks come from the `hmvc` and
+    /// `fmvn` operations if present. Operations map the opcode to its operands
+    /// joined by a space, quotes removed
+    pub fn from_epd(line: &str) -> Result<(Game, HashMap<String, String>), FenError> {
+        let line = line.trim();
+        let mut fields = Vec::new();
+        let mut rest = line;
+        while fields.len() < 4 {
+            let field_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if field_end == 0 {
+                return Err(FenError::FieldCount);
+            }
+            fields.push(&rest[..field_end]);
+            rest = rest[field_end..].trim_start();
+        }
+
+        let operations = parse_operations(rest);
+        let clock = |opcode: &str, default: &'static str| operations.get(opcode).map_or(default, String::as_str);
+        let fen = format!("{} {} {}", fields.join(" "), clock("hmvc", "0"), clock("fmvn", "1"));
+        let game = Game::from_fen(&fen)?;
+        Ok((game, operations))
+    }
+}
+
+/// operations of an EPD record, operands in double quotes may hold spaces and `;`
+fn parse_operations(text: &str) -> HashMap<String, String> {
+    let mut operations = HashMap::new();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => token.extend(chars.next()),
+            ';' if !quoted => {
+                end_token(&mut tokens, &mut token);
+                if let Some((opcode, operands)) = tokens.split_first() {
+                    operations.insert(opcode.clone(), operands.join(" "));
+                }
+                tokens.clear();
+            }
+            c if c.is_whitespace() && !quoted => end_token(&mut tokens, &mut token),
+            c => token.push(c),
+        }
+    }
+    operations
+}
+
+fn end_token(tokens: &mut Vec<String>, token: &mut String) {
+    if !token.is_empty() {
+        tokens.push(std::mem::take(token));
+    }
+}
+
+/// whether the move solves an EPD test position: it is one of the `bm` (best move)
+/// moves and none of the `am` (avoid move) moves, either can be missing. The moves
+/// are SAN, check and annotation marks are ignored
+pub fn solves(game: &Game, operations: &HashMap<String, String>, mv: Move) -> bool {
+    let Ok(san) = game.san(mv) else {
+        return false;
+    };
+    let strip = |san: &str| san.trim_end_matches(['+', '#', '!', '?']).to_string();
+    let san = strip(&san);
+    let listed = |opcode: &str| operations.get(opcode).map(|moves| moves.split_whitespace().any(|listed| strip(listed) == san));
+    listed("bm").unwrap_or(true) && !listed("am").unwrap_or(false)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::engine::board::parse_square;
+
+    #[test]
+    fn test_parse_operations() {
+        let operations = parse_operations("bm Qd6 Qe5+; id \"WAC.001\"; c0 \"a; \\\"quoted\\\" comment\"; noop;");
+        assert_eq!("Qd6 Qe5+", operations["bm"]);
+        assert_eq!("WAC.001", operations["id"]);
+        assert_eq!("a; \"quoted\" comment", operations["c0"]);
+        assert_eq!("", operations["noop"]);
+        assert_eq!(4, operations.len());
+        assert!(parse_operations("").is_empty());
+    }
+
+    #[test]
+    fn test_from_epd() {
+        let (game, operations) =
+            Game::from_epd("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";").unwrap();
+        assert_eq!("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1", game.to_fen());
+        assert_eq!("WAC.001", operations["id"]);
+
+        let (game, operations) = Game::from_epd("4k3/8/8/8/8/8/4P3/4K3 b - - hmvc 12; fmvn 40;").unwrap();
+        assert_eq!("4k3/8/8/8/8/8/4P3/4K3 b - - 12 40", game.to_fen());
+        assert_eq!(2, operations.len());
+
+        // no operations at all
+        assert!(Game::from_epd("8/8/8/8/8/8/8/K6k w - -").unwrap().1.is_empty());
+        assert_eq!(Err(FenError::FieldCount), Game::from_epd("8/8/8/8/8/8/8/K6k w").map(|_| ()));
+        assert_eq!(Err(FenError::SideToMove), Game::from_epd("8/8/8/8/8/8/8/K6k x - -").map(|_| ()));
+    }
+
+    #[test]
+    fn test_solves() {
+        let square = |name| parse_square(name).unwrap();
+        // back rank mate
+        let (game, operations) = Game::from_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; am Ra7;").unwrap();
+        let mate = Move { from: square("a1"), to: square("a8"), promotion: None };
+        assert!(solves(&game, &operations, mate));
+        assert!(!solves(&game, &operations, Move { from: square("a1"), to: square("a7"), promotion: None }));
+        assert!(!solves(&game, &operations, Move { from: square("g1"), to: square("f1"), promotion: None }));
+        assert!(!solves(&game, &operations, Move { from: square("a1"), to: square("h8"), promotion: None }));
+        let (best, _) = game.best_move(2).unwrap();
+        assert!(solves(&game, &operations, best));
+
+        // only moves to avoid
+        let (game, operations) = Game::from_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - - am Ra7;").unwrap();
+        assert!(solves(&game, &operations, mate));
+        assert!(!solves(&game, &operations, Move { from: square("a1"), to: square("a7"), promotion: None }));
+    }
+}
@@ -1,27 +1,36 @@
-use crate::engine::board::{is_file, is_rank, Board, MASK_FILE_A, MASK_FILE_B, MASK_FILE_C, MASK_FILE_D, MASK_FILE_F, MASK_FILE_G, MASK_FILE_H, MASK_RANK_1, MASK_RANK_8};
-use crate::engine::moves::{find_blocker_mask, resolve_bishop_source, resolve_king_source, resolve_knight_source, resolve_pawn_source, resolve_queen_source, resolve_rook_source, BISHOP_RAYS_DIRECTIONS, BLACK_PAWN_MOVES, KING_MOVES, KNIGHT_MOVES, QUEEN_RAYS, QUEEN_RAYS_DIRECTIONS, ROOK_RAYS_DIRECTIONS, WHITE_PAWN_MOVES};
-use crate::engine::parser::{parse_move, ParsedMove, Piece, SpecialMove};
-
-const MASK_CASTLING_PATH_KINGSIDE: u64 = (MASK_FILE_F | MASK_FILE_G) & (MASK_RANK_1 | MASK_RANK_8);
-const MASK_CASTLING_PATH_QUEENSIDE: u64 =
-    (MASK_FILE_B | MASK_FILE_C | MASK_FILE_D) & (MASK_RANK_1 | MASK_RANK_8);
-
-const MASK_CASTLING_KINGSIDE_PIECE: u64 = MASK_FILE_H & (MASK_RANK_1 | MASK_RANK_8);
-const MASK_CASTLING_QUEENSIDE_PIECE: u64 = MASK_FILE_A & (MASK_RANK_1 | MASK_RANK_8);
+use crate::engine::board::{is_file, is_rank, parse_square, piece_value, square_name, Board, LIGHT_SQUARES, MASK_FILE_A, MASK_FILE_B, MASK_FILE_C, MASK_FILE_D, MASK_FILE_E, MASK_FILE_F, MASK_FILE_G, MASK_FILE_H, MASK_RANK_1, MASK_RANK_8};
+use crate::engine::moves::{compute_piece_moves, find_blocker_mask, resolve_bishop_source, resolve_king_source, resolve_knight_source, resolve_pawn_source, resolve_queen_source, resolve_rook_source, BETWEEN, BISHOP_RAYS, BISHOP_RAYS_DIRECTIONS, BLACK_PAWN_MOVES, KING_MOVES, KNIGHT_MOVES, QUEEN_RAYS, QUEEN_RAYS_DIRECTIONS, ROOK_RAYS, ROOK_RAYS_DIRECTIONS, WHITE_PAWN_MOVES};
+use crate::engine::openings;
+use crate::engine::zobrist;
+use crate::engine::parser::{parse_move, parse_uci, split_suffix, CheckSuffix, ParsedMove, Piece, SpecialMove};
+use std::cell::OnceCell;
+use std::fmt;
 
 /// Game struct responsible for all game logics (pin, check, valid captures, etc)
+#[derive(Clone)]
 pub struct Game {
     pub board: Board,
-    pub turn: u8,
+    pub turn: u16,
 
     // castling
     pub white_can_castle_kingside: bool,
     pub white_can_castle_queenside: bool,
     pub black_can_castle_kingside: bool,
     pub black_can_castle_queenside: bool,
+    // file (0 for a) of the rook each castling right castles with, same order as
+    // the rights (white kingside, white queenside, black kingside, black queenside)
+    castling_rook_files: [u8; 4],
+    // Chess960 castling: the king moves onto its rook (e.g. `g1h1`) and FEN uses the
+    // rook files (e.g. `HAha`). The king and rook still end on the g/f or c/d files
+    pub chess960: bool,
 
     // check
     pub check: bool,
+    // number of opponent pieces giving check, 2 for a double check (see [`Game::checkers`])
+    pub checker_count: u8,
+    checkers: u64,
+    // squares from the king to the checkers, see [`CheckInfo::lines`]
+    check_lines: u64,
 
     // pin
     pub pinned_white: u64,
@@ -30,8 +39,138 @@ pub struct Game {
     // en passant target square (not piece)
     pub en_passant_target: u64,
 
-    // end game (checkmate, draw)
-    pub status: Status,
+    // number of halfmoves since the last pawn move or capture
+    pub halfmove_clock: u32,
+
+    // pawn moves to the last rank without a promotion piece are rejected instead
+    // of promoting to a queen
+    pub require_explicit_promotion: bool,
+
+    // end of the game with the winner and the reason, see [`Game::result`]
+    result: GameResult,
+
+    // legal moves of the side to move, generated at most once per position on the
+    // first request and cleared when the position changes (see [`Game::legal_moves`])
+    legal_moves: OnceCell<Vec<Move>>,
+
+    // Zobrist hash of the current position, updated incrementally with every move
+    hash: u64,
+
+    // hash of every position reached so far (including the current one), used
+    // for repetition detection
+    position_history: Vec<u64>,
+
+    // every move played so far
+    history: Vec<MoveInfo>,
+
+    // state to take back each played move, see [`Game::undo`]
+    undo_history: Vec<UndoRecord>,
+
+    // live game saved while exploring in analysis mode
+    analysis_base: Option<Box<Game>>,
+
+    // number of status updates that had to look at non king moves
+    #[cfg(test)]
+    non_king_move_checks: u32,
+}
+
+/// legal move of the side to move, see [`Game::legal_moves`]. Castling is the
+/// king's move (e.g. `e1g1`), in Chess960 the king moves onto its rook (e.g. `e1h1`)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Move {
+    pub from: u64,
+    pub to: u64,
+    pub promotion: Option<Piece>,
+}
+
+impl Move {
+    /// UCI coordinate notation (e.g. `e2e4`, `e7e8q`)
+    pub fn uci(&self) -> String {
+        let promotion = match self.promotion {
+            Some(Piece::Queen) => "q",
+            Some(Piece::Rook) => "r",
+            Some(Piece::Bishop) => "b",
+            Some(Piece::Knight) => "n",
+            _ => "",
+        };
+        format!(
+            "{}{}{}",
+            square_name(self.from).unwrap_or_default(),
+            square_name(self.to).unwrap_or_default(),
+            promotion
+        )
+    }
+}
+
+/// why the side to move is in check, see [`Game::check_info`]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CheckInfo {
+    /// opponent pieces giving check (two for a double check)
+    pub checkers: u64,
+    /// squares between the king and every checker plus the checkers themselves, for
+    /// knight and pawn checks (no line) only the checker squares
+    pub lines: u64,
+}
+
+/// information about a move that has been played
+#[derive(Debug, PartialEq, Clone)]
+pub struct MoveInfo {
+    /// move in SAN (minimal disambiguation) with `+`/`#` for check/checkmate
+    pub san: String,
+    pub is_white: bool,
+    pub piece: Piece,
+    /// source and target square, for castling this is the king's move
+    pub from: u64,
+    pub to: u64,
+    pub is_capture: bool,
+    pub is_check: bool,
+}
+
+/// state before a move that can't be derived from the position after it, see
+/// [`Game::undo`]
+#[derive(Clone)]
+struct UndoRecord {
+    is_white: bool,
+    /// castling as in [`Game::legal_moves`], promotion piece if the pawn promoted
+    mv: Move,
+    /// captured piece and its square (behind the target square for en passant)
+    captured: Option<(Piece, u64)>,
+    castling: Option<CastlingMove>,
+    castling_rights: (bool, bool, bool, bool),
+    en_passant_target: u64,
+    halfmove_clock: u32,
+    turn: u16,
+    hash: u64,
+    pinned_white: u64,
+    pinned_black: u64,
+    result: GameResult,
+    /// position history before an irreversible move cleared it
+    position_history: Option<Vec<u64>>,
+}
+
+/// king and rook squares before and after castling, the king or the rook may stay
+/// on its square in Chess960
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct CastlingMove {
+    king_from: u64,
+    king_to: u64,
+    rook_from: u64,
+    rook_to: u64,
+}
+
+/// per side statistics, see [`Game::statistics`]
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub struct SideStats {
+    pub moves: u32,
+    pub captures: u32,
+    pub checks: u32,
+    pub castled: bool,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub struct GameStats {
+    pub white: SideStats,
+    pub black: SideStats,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -50,24 +189,76 @@ pub enum InvalidMoveReason {
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum MoveError {
-    AmbiguousSource,
+    /// more than one piece can make the move, holds their squares
+    AmbiguousSource(u64),
     InvalidMove(InvalidMoveReason),
     Pinned,
     Checked,
     ParseError,
     GameOver,
+    NothingToUndo,
+    /// the move is legal but its `+`/`#` marker doesn't match the resulting position
+    WrongCheckSuffix,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum FenError {
+    FieldCount,
+    Placement,
+    SideToMove,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber,
 }
 
-#[derive(Debug, PartialEq)]
+/// error replaying a saved game
+#[derive(Debug, PartialEq, Clone)]
+pub enum LoadError {
+    InvalidFen(FenError),
+    /// the move at `move_number` (fullmove number, e.g. `12` for `12. Nf3` or
+    /// `12... Nf6`) could not be played
+    IllegalMove { move_number: u16, san: String, error: MoveError },
+}
+
+/// end of the game without the winner, see [`Game::status`]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Status {
     Ongoing,
     Draw,
     Checkmate,
+    /// the side to move resigned
+    Resigned,
+}
+
+/// outcome of the game with the reason it ended, [`Status`] is derived from it
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GameResult {
+    Ongoing,
+    Win { white: bool, reason: WinReason },
+    Draw { reason: DrawReason },
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WinReason {
+    Checkmate,
+    Resignation,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DrawReason {
+    Stalemate,
+    /// neither side can checkmate anymore (including blocked pawn walls)
+    InsufficientMaterial,
+    FiftyMoveRule,
+    Repetition,
+    /// draw offered by one player and accepted by the other
+    Agreement,
 }
 
 impl Game {
     pub fn new(board: Board) -> Game {
-        Game {
+        let mut game = Game {
             board,
             turn: 1,
 
@@ -75,67 +266,301 @@ impl Game {
             white_can_castle_queenside: true,
             black_can_castle_kingside: true,
             black_can_castle_queenside: true,
+            castling_rook_files: [7, 0, 7, 0],
+            chess960: false,
 
             check: false,
+            checker_count: 0,
+            checkers: 0,
+            check_lines: 0,
             pinned_white: 0,
             pinned_black: 0,
             en_passant_target: 0,
+            halfmove_clock: 0,
+            require_explicit_promotion: false,
+
+            result: GameResult::Ongoing,
+            legal_moves: OnceCell::new(),
+
+            hash: 0,
+            position_history: Vec::new(),
+            history: Vec::new(),
+            undo_history: Vec::new(),
+            analysis_base: None,
 
-            status: Status::Ongoing,
+            #[cfg(test)]
+            non_king_move_checks: 0,
+        };
+        game.hash = game.compute_hash();
+        game.position_history.push(game.hash);
+        game
+    }
+
+    /// same as [`Game::new`] but with the given side to move (e.g. black to move
+    /// from a custom position)
+    pub fn with_side_to_move(board: Board, is_white: bool) -> Game {
+        let mut game = Self::new(board);
+        if !is_white {
+            game.turn = 2;
+            game.hash = game.compute_hash();
+            game.position_history = vec![game.hash];
+            game.legal_moves.take();
         }
+        game
     }
 
-    fn is_white(&self) -> bool {
-        self.turn & 1 == 1
+    /// back to the standard starting position with an empty history, as
+    /// [`Game::default`] but keeping the options (`require_explicit_promotion`,
+    /// `chess960`) and the memory of the histories for the next game
+    pub fn reset(&mut self) {
+        self.board = Board::default();
+        self.turn = 1;
+
+        self.white_can_castle_kingside = true;
+        self.white_can_castle_queenside = true;
+        self.black_can_castle_kingside = true;
+        self.black_can_castle_queenside = true;
+        self.castling_rook_files = [7, 0, 7, 0];
+
+        self.check = false;
+        self.checker_count = 0;
+        self.checkers = 0;
+        self.check_lines = 0;
+        self.pinned_white = 0;
+        self.pinned_black = 0;
+        self.en_passant_target = 0;
+        self.halfmove_clock = 0;
+
+        self.result = GameResult::Ongoing;
+        self.legal_moves.take();
+
+        self.history.clear();
+        self.undo_history.clear();
+        self.analysis_base = None;
+        self.hash = self.compute_hash();
+        self.position_history.clear();
+        self.position_history.push(self.hash);
     }
 
-    fn get_pieces(board: &Board, piece_type: Piece, is_white: bool) -> u64 {
-        match piece_type {
-            Piece::Pawn => {
-                if is_white {
-                    board.white_pawns
-                } else {
-                    board.black_pawns
-                }
-            }
-            Piece::Knight => {
-                if is_white {
-                    board.white_knights
-                } else {
-                    board.black_knights
-                }
-            }
-            Piece::Rook => {
-                if is_white {
-                    board.white_rooks
-                } else {
-                    board.black_rooks
-                }
+    /// creates game from a full FEN string. Halfmove clock and fullmove number are
+    /// optional and default to `0 1`
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        let mut game = Game::default();
+        game.set_position(fen)?;
+        Ok(game)
+    }
+
+    /// resets the whole game state (board, turn, castling rights, en passant,
+    /// clocks, history and status) from a FEN string, reusing the allocations.
+    /// The game is left untouched if the FEN is invalid
+    pub fn set_position(&mut self, fen: &str) -> Result<(), FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 4 && fields.len() != 6 {
+            return Err(FenError::FieldCount);
+        }
+
+        Self::validate_fen_placement(fields[0])?;
+        let is_white = match fields[1] {
+            "w" => true,
+            "b" => false,
+            _ => return Err(FenError::SideToMove),
+        };
+
+        let castling = fields[2];
+        if castling != "-"
+            && (castling.is_empty() || !castling.chars().all(|c| "KQkqABCDEFGHabcdefgh".contains(c)))
+        {
+            return Err(FenError::Castling);
+        }
+        let board = Board::from_fen(fields[0]);
+        let (castling_rights, castling_rook_files) = Self::parse_castling(&board, castling)?;
+
+        let en_passant_target = match fields[3] {
+            "-" => 0,
+            square => match parse_square(square) {
+                Some(target) if is_rank(target, if is_white { 6 } else { 3 }) => target,
+                _ => return Err(FenError::EnPassant),
+            },
+        };
+
+        let (halfmove_clock, fullmove) = if fields.len() == 6 {
+            let halfmove_clock: u32 = fields[4]
+                .parse()
+                .map_err(|_| FenError::HalfmoveClock)?;
+            let fullmove: u16 = fields[5]
+                .parse()
+                .map_err(|_| FenError::FullmoveNumber)?;
+            if fullmove == 0 || fullmove > u16::MAX / 2 {
+                return Err(FenError::FullmoveNumber);
             }
-            Piece::Bishop => {
-                if is_white {
-                    board.white_bishops
-                } else {
-                    board.black_bishops
-                }
+            (halfmove_clock, fullmove)
+        } else {
+            (0, 1)
+        };
+
+        self.board = board;
+        self.turn = (fullmove - 1) * 2 + if is_white { 1 } else { 2 };
+        [
+            self.white_can_castle_kingside,
+            self.white_can_castle_queenside,
+            self.black_can_castle_kingside,
+            self.black_can_castle_queenside,
+        ] = castling_rights;
+        self.castling_rook_files = castling_rook_files;
+        // rook files in the FEN, or castling rights standard chess can't have
+        let king_on_e_file = |is_white: bool| {
+            let rank = if is_white { MASK_RANK_1 } else { MASK_RANK_8 };
+            Self::get_pieces(&self.board, Piece::King, is_white) & rank & MASK_FILE_E != 0
+        };
+        self.chess960 = castling.chars().any(|c| !"KQkq-".contains(c))
+            || (0..4).any(|i| {
+                castling_rights[i] && (castling_rook_files[i] != [7, 0, 7, 0][i] || !king_on_e_file(i < 2))
+            });
+        self.en_passant_target = en_passant_target;
+        self.halfmove_clock = halfmove_clock;
+        self.history.clear();
+        self.undo_history.clear();
+        self.analysis_base = None;
+
+        self.update_pinned_state();
+        self.update_check_state();
+        self.hash = self.compute_hash();
+        self.position_history.clear();
+        self.position_history.push(self.hash);
+        self.update_game_status();
+        Ok(())
+    }
+
+    fn validate_fen_placement(placement: &str) -> Result<(), FenError> {
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::Placement);
+        }
+        for rank in ranks {
+            let mut files = 0;
+            for c in rank.chars() {
+                files += match c {
+                    '1'..='8' => c.to_digit(10).unwrap(),
+                    'P' | 'N' | 'B' | 'R' | 'Q' | 'K' | 'p' | 'n' | 'b' | 'r' | 'q' | 'k' => 1,
+                    _ => return Err(FenError::Placement),
+                };
             }
-            Piece::Queen => {
-                if is_white {
-                    board.white_queens
-                } else {
-                    board.black_queens
-                }
+            if files != 8 {
+                return Err(FenError::Placement);
             }
-            Piece::King | Piece::Castling => {
-                if is_white {
-                    board.white_king
-                } else {
-                    board.black_king
-                }
+        }
+        if placement.matches('K').count() != 1 || placement.matches('k').count() != 1 {
+            return Err(FenError::Placement);
+        }
+        Ok(())
+    }
+
+    /// full FEN of the current position: placement, side to move, castling
+    /// rights, en passant target, halfmove clock and fullmove number
+    pub fn to_fen(&self) -> String {
+        format!(
+            "{} {} {} {} {} {}",
+            self.board.to_fen(),
+            if self.is_white() { 'w' } else { 'b' },
+            self.castling_fen(),
+            self.en_passant_fen(),
+            self.halfmove_clock,
+            self.turn.div_ceil(2),
+        )
+    }
+
+    /// castling rights and rook files (see [`Game::castling_rook_files`]) of a FEN
+    /// castling field. `K`/`Q` castle with the outermost rook on that side of the
+    /// king, a file letter (Shredder-FEN, e.g. `HAha`) with the rook on that file
+    fn parse_castling(board: &Board, castling: &str) -> Result<([bool; 4], [u8; 4]), FenError> {
+        let mut rights = [false; 4];
+        let mut rook_files = [7, 0, 7, 0];
+        for c in castling.chars().filter(|c| *c != '-') {
+            let is_white = c.is_ascii_uppercase();
+            let rank = if is_white { MASK_RANK_1 } else { MASK_RANK_8 };
+            let king = Self::get_pieces(board, Piece::King, is_white) & rank;
+            let king_file = if king == 0 { 4 } else { (king.trailing_zeros() % 8) as u8 };
+            let rook_files_on_rank = (0..8u8).filter(|file| Self::get_pieces(board, Piece::Rook, is_white) & rank & MASK_FILE_A << file != 0);
+            let file = match c.to_ascii_uppercase() {
+                'K' => rook_files_on_rank.filter(|file| *file > king_file).max().unwrap_or(7),
+                'Q' => rook_files_on_rank.filter(|file| *file < king_file).min().unwrap_or(0),
+                file => file as u8 - b'A',
+            };
+            if file == king_file {
+                return Err(FenError::Castling);
             }
+            let idx = Self::castling_index(file > king_file, is_white);
+            rights[idx] = true;
+            rook_files[idx] = file;
+        }
+        Ok((rights, rook_files))
+    }
+
+    /// castling rights in FEN notation (e.g. `KQkq`, or the rook files like `HAha`
+    /// in Chess960), `-` if nobody can castle
+    fn castling_fen(&self) -> String {
+        let [white_kingside, white_queenside, black_kingside, black_queenside] = self.castling_rook_files;
+        let castling: String = [
+            (self.white_can_castle_kingside, 'K', (b'A' + white_kingside) as char),
+            (self.white_can_castle_queenside, 'Q', (b'A' + white_queenside) as char),
+            (self.black_can_castle_kingside, 'k', (b'a' + black_kingside) as char),
+            (self.black_can_castle_queenside, 'q', (b'a' + black_queenside) as char),
+        ]
+        .iter()
+        .filter(|(can_castle, _, _)| *can_castle)
+        .map(|(_, c, file)| if self.chess960 { *file } else { *c })
+        .collect();
+
+        if castling.is_empty() { "-".to_string() } else { castling }
+    }
+
+    /// en passant target square (e.g. `e3`), `-` if there is none
+    fn en_passant_fen(&self) -> String {
+        square_name(self.en_passant_target).unwrap_or_else(|| "-".to_string())
+    }
+
+    /// pieces used to find the source/target of a move, king only for castling
+    /// (rook moves as well)
+    fn own_pieces(&self, piece: Piece, is_white: bool) -> u64 {
+        match (piece, is_white) {
+            (Piece::Castling, _) => Self::get_pieces(&self.board, Piece::King, is_white),
+            (_, true) => self.board.white_pieces,
+            (_, false) => self.board.black_pieces,
         }
     }
 
+    /// name of the opening played so far, `None` once the game leaves the known
+    /// lines (see [`openings::identify`])
+    pub fn opening(&self) -> Option<&'static str> {
+        let moves: Vec<&str> = self.history.iter().map(|mv| mv.san.as_str()).collect();
+        openings::identify(&moves)
+    }
+
+    /// every move played so far (since the start or the last `set_position`), oldest first
+    pub fn history(&self) -> &[MoveInfo] {
+        &self.history
+    }
+
+    /// piece and its color (`true` for white) on the square, `None` for an empty
+    /// square or if `square` is not a single square (0 or more than one bit set)
+    pub fn piece_at(&self, square: u64) -> Option<(Piece, bool)> {
+        self.board.get_piece_type_at(square)
+    }
+
+    /// last move played, if any
+    pub fn last_move(&self) -> Option<&MoveInfo> {
+        self.history.last()
+    }
+
+    /// whether white is the side to move
+    pub fn is_white(&self) -> bool {
+        self.turn & 1 == 1
+    }
+
+    fn get_pieces(board: &Board, piece_type: Piece, is_white: bool) -> u64 {
+        board.get_pieces(piece_type, is_white)
+    }
+
     fn get_computed_pseudolegal_moves(&self, piece_type: Piece, is_white: bool) -> u64 {
         match piece_type {
             Piece::Pawn => {
@@ -224,152 +649,753 @@ impl Game {
         }
     }
 
-    pub fn process_move(&mut self, cmd: &str) -> Result<(), MoveError> {
-        if let Ok(parsed_move) = parse_move(cmd) {
-            if self.status != Status::Ongoing {
-                return Err(MoveError::GameOver);
+    /// plays the move for the side to move and returns the executed move (SAN with
+    /// `+`/`#`). Both SAN and UCI coordinate moves (`e2e4`, `e7e8q`) are accepted.
+    /// In analysis mode turn order is not enforced and a move for the other
+    /// color is played as well
+    pub fn process_move(&mut self, cmd: &str) -> Result<MoveInfo, MoveError> {
+        match self.play_san(cmd) {
+            Err(err) if self.is_analysis() && err != MoveError::ParseError && err != MoveError::GameOver => {
+                let mut other_side = self.clone();
+                other_side.pass_turn();
+                match other_side.play_san(cmd) {
+                    Ok(move_info) => {
+                        *self = other_side;
+                        Ok(move_info)
+                    }
+                    Err(_) => Err(err),
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// plays a move given by its source and target square, skipping the parsing and
+    /// source resolution of [`Game::process_move`]. Only moves in [`Game::legal_moves`]
+    /// are played, castling is the king's move (e.g. e1g1)
+    pub fn apply_move(&mut self, mv: Move) -> Result<MoveInfo, MoveError> {
+        if self.status() != Status::Ongoing {
+            return Err(MoveError::GameOver);
+        }
+        let is_white = self.is_white();
+        let piece = match self.board.get_piece_type_at(mv.from) {
+            Some((piece, piece_is_white)) if piece_is_white == is_white => piece,
+            _ => return Err(MoveError::InvalidMove(InvalidMoveReason::NoSourceOrTarget)),
+        };
+        if !self.legal_moves().contains(&mv) {
+            // right squares with a missing, needless or wrong promotion piece
+            return Err(MoveError::InvalidMove(if self.legal_destinations(mv.from) & mv.to != 0 {
+                InvalidMoveReason::PawnInvalidPromotion
+            } else {
+                InvalidMoveReason::InvalidSourceOrTarget
+            }));
+        }
+
+        let parsed_move = if let Some(is_kingside) = self.castling_side(&mv) {
+            ParsedMove {
+                piece: Piece::Castling,
+                from_file: None,
+                from_rank: None,
+                to: 0,
+                is_capture: false,
+                special_move: Some(if is_kingside {
+                    SpecialMove::CastlingKing
+                } else {
+                    SpecialMove::CastlingQueen
+                }),
+            }
+        } else {
+            let is_en_passant = piece == Piece::Pawn && mv.to == self.en_passant_target && mv.to != 0;
+            ParsedMove {
+                piece,
+                from_file: None,
+                from_rank: None,
+                to: mv.to,
+                is_capture: self.board.is_capture(mv.to, is_white) || is_en_passant,
+                special_move: mv.promotion.map(SpecialMove::Promotion),
             }
+        };
 
-            let is_white = self.is_white();
-            let pieces = Self::get_pieces(&self.board, parsed_move.piece, is_white);
-            let pseudolegal_moves =
-                self.get_computed_pseudolegal_moves(parsed_move.piece, is_white);
+        let san = self.move_to_san(&mv);
+        self.play_parsed(parsed_move, Some(mv.from), |_| san)
+    }
 
-            let pinned_pieces = if is_white {
-                self.pinned_white
-            } else {
-                self.pinned_black
+    /// SAN for a move in the current position, with `+`/`#` suffix for
+    /// check/checkmate. Fails if the move isn't legal
+    pub fn san(&self, mv: Move) -> Result<String, MoveError> {
+        let mut game = self.clone();
+        game.apply_move(mv).map(|move_info| move_info.san)
+    }
+
+    /// SAN of the move `cmd` played by each of the `sources` pieces, with the file or
+    /// rank needed to tell them apart (e.g. `Nbd7` and `Nfd7` for `Nd7`). Sources that
+    /// can't legally make the move are left out
+    pub fn disambiguate(&self, cmd: &str, sources: u64) -> Vec<String> {
+        let Ok(parsed_move) = parse_move(cmd.trim()) else {
+            return Vec::new();
+        };
+        let mut moves = Vec::new();
+        let mut sources = sources;
+        while sources != 0 {
+            let from = 1u64 << sources.trailing_zeros();
+            sources &= sources - 1;
+            let promotion = match parsed_move.special_move {
+                Some(SpecialMove::Promotion(piece)) => Some(piece),
+                _ => None,
             };
+            let mv = Move { from, to: parsed_move.to, promotion };
+            if let Ok(san) = self.san(mv) {
+                moves.push(san);
+            }
+        }
+        moves
+    }
 
-            let clear_en_passant = parsed_move.piece != Piece::Pawn;
-
-            match parsed_move.piece {
-                Piece::Pawn => {
-                    // special case for pawns
-                    self.process_pawn(
-                        parsed_move,
-                        pieces,
-                        is_white,
-                        pseudolegal_moves,
-                        pinned_pieces,
-                        self.check,
-                    )?
-                }
-                Piece::Knight => self.process_knight(
-                    parsed_move,
-                    pieces,
-                    is_white,
-                    pseudolegal_moves,
-                    pinned_pieces,
-                    self.check,
-                )?,
-                Piece::Bishop => self.process_bishop(
-                    parsed_move,
-                    pieces,
-                    is_white,
-                    pseudolegal_moves,
-                    pinned_pieces,
-                    self.check,
-                )?,
-                Piece::Queen => self.process_queen(
-                    parsed_move,
-                    pieces,
-                    is_white,
-                    pseudolegal_moves,
-                    pinned_pieces,
-                    self.check,
-                )?,
-                Piece::Rook => self.process_rook(
-                    parsed_move,
-                    pieces,
-                    is_white,
-                    pseudolegal_moves,
-                    pinned_pieces,
-                    self.check,
-                )?,
-                Piece::King => self.process_king(
+    /// whether the game is in analysis mode (see [`Game::enter_analysis`])
+    pub fn is_analysis(&self) -> bool {
+        self.analysis_base.is_some()
+    }
+
+    /// starts analysis mode, moves can be played freely for both colors until
+    /// [`Game::exit_analysis`] restores the current game
+    pub fn enter_analysis(&mut self) {
+        if self.analysis_base.is_none() {
+            self.analysis_base = Some(Box::new(self.clone()));
+        }
+    }
+
+    /// leaves analysis mode and restores the game as it was when analysis started
+    pub fn exit_analysis(&mut self) {
+        if let Some(live) = self.analysis_base.take() {
+            *self = *live;
+        }
+    }
+
+    /// gives the move to the other color without moving (analysis mode only)
+    fn pass_turn(&mut self) {
+        self.turn += 1;
+        self.en_passant_target = 0;
+        self.hash = self.compute_hash();
+        self.update_pinned_state();
+        self.update_check_state();
+        self.legal_moves.take();
+    }
+
+    /// parses and plays a SAN (or UCI coordinate) move for the side to move
+    fn play_san(&mut self, cmd: &str) -> Result<MoveInfo, MoveError> {
+        if parse_uci(cmd.trim()).is_ok() {
+            return self.apply_uci(cmd);
+        }
+        let parsed_move = parse_move(cmd).map_err(|_| MoveError::ParseError)?;
+        // the input may be over-disambiguated, SAN is generated from the position
+        // before the move once the source square is known
+        let before = self.clone();
+        let move_info = self.play_parsed(parsed_move, None, |mv| before.move_to_san(mv))?;
+
+        // a check marker is optional but has to be right when given, `+` is
+        // accepted for checkmate too
+        let wrong_suffix = match split_suffix(cmd.trim()).1 {
+            Some(CheckSuffix::Check) => !self.check,
+            Some(CheckSuffix::Checkmate) => self.status() != Status::Checkmate,
+            None => false,
+        };
+        if wrong_suffix {
+            *self = before;
+            return Err(MoveError::WrongCheckSuffix);
+        }
+        Ok(move_info)
+    }
+
+    /// plays a parsed move, the source square is resolved from the move when it
+    /// isn't given. `san` gives the SAN (without suffix) of the played move
+    fn play_parsed(
+        &mut self,
+        parsed_move: ParsedMove,
+        source: Option<u64>,
+        san: impl FnOnce(&Move) -> String,
+    ) -> Result<MoveInfo, MoveError> {
+        if self.status() != Status::Ongoing {
+            return Err(MoveError::GameOver);
+        }
+
+        // no block or capture stops both checks of a double check, only the king can move
+        if self.checker_count >= 2 && parsed_move.piece != Piece::King {
+            return Err(MoveError::Checked);
+        }
+
+        let is_white = self.is_white();
+        let from = match source {
+            Some(from) => from,
+            None => self.resolve_source(&parsed_move, is_white),
+        };
+        let pieces = Self::get_pieces(&self.board, parsed_move.piece, is_white);
+        let pseudolegal_moves =
+            self.get_computed_pseudolegal_moves(parsed_move.piece, is_white);
+
+        let pinned_pieces = if is_white {
+            self.pinned_white
+        } else {
+            self.pinned_black
+        };
+
+        let clear_en_passant = parsed_move.piece != Piece::Pawn;
+
+        let castling_rights = self.castling_rights();
+
+        // compare opponent pieces after the move to detect captures (including en passant)
+        let piece = parsed_move.piece;
+        let opponent_count = if is_white {
+            self.board.black_pieces.count_ones()
+        } else {
+            self.board.white_pieces.count_ones()
+        };
+        let own_pieces_before = self.own_pieces(piece, is_white);
+        let board_before = self.board;
+        let mut undo = UndoRecord {
+            is_white,
+            mv: Move { from: 0, to: 0, promotion: None },
+            captured: None,
+            castling: None,
+            castling_rights,
+            en_passant_target: self.en_passant_target,
+            halfmove_clock: self.halfmove_clock,
+            turn: self.turn,
+            hash: self.hash,
+            pinned_white: self.pinned_white,
+            pinned_black: self.pinned_black,
+            result: self.result,
+            position_history: None,
+        };
+
+        match parsed_move.piece {
+            Piece::Pawn => {
+                // special case for pawns
+                self.process_pawn(
                     parsed_move,
+                    from,
                     pieces,
                     is_white,
                     pseudolegal_moves,
                     pinned_pieces,
-                    self.check,
-                )?,
-                Piece::Castling => self.process_castling(parsed_move, is_white)?,
-            }
-            // move successful, increment turn
-            self.turn += 1;
-
-            // remove en-passant for non pawn move
-            if clear_en_passant {
-                self.en_passant_target = 0;
+                )?
             }
+            Piece::Knight => self.process_knight(
+                parsed_move,
+                from,
+                pieces,
+                is_white,
+                pseudolegal_moves,
+                pinned_pieces,
+            )?,
+            Piece::Bishop => self.process_bishop(
+                parsed_move,
+                from,
+                pieces,
+                is_white,
+                pseudolegal_moves,
+                pinned_pieces,
+            )?,
+            Piece::Queen => self.process_queen(
+                parsed_move,
+                from,
+                pieces,
+                is_white,
+                pseudolegal_moves,
+                pinned_pieces,
+            )?,
+            Piece::Rook => self.process_rook(
+                parsed_move,
+                from,
+                pieces,
+                is_white,
+                pseudolegal_moves,
+                pinned_pieces,
+            )?,
+            Piece::King => self.process_king(
+                parsed_move,
+                from,
+                pieces,
+                is_white,
+                pseudolegal_moves,
+                pinned_pieces,
+            )?,
+            Piece::Castling => undo.castling = Some(self.process_castling(parsed_move, is_white)?),
+        }
+        // move successful, increment turn
+        self.turn += 1;
 
-            self.board.update_compute_moves();
-            self.update_pinned_state();
-            self.update_check_state();
+        let is_capture = opponent_count
+            != if is_white {
+                self.board.black_pieces.count_ones()
+            } else {
+                self.board.white_pieces.count_ones()
+            };
 
-            // final step is to update game status
-            self.update_game_status();
-            Ok(())
+        // pawn moves, captures and castling rights changes can never be undone,
+        // no position before them can repeat
+        let is_irreversible = piece == Piece::Pawn || is_capture;
+        if is_irreversible {
+            self.halfmove_clock = 0;
         } else {
-            Err(MoveError::ParseError)
+            self.halfmove_clock += 1;
         }
-    }
 
-    fn process_pawn(
-        &mut self,
-        mv: ParsedMove,
-        pawns: u64,
-        is_white: bool,
-        pseudolegal_moves: u64,
-        pinned_pieces: u64,
-        check: bool,
-    ) -> Result<(), MoveError> {
-        let to = mv.to;
-        let from = resolve_pawn_source(&self.board, &mv, self.is_white());
+        // remove en-passant for non pawn move
+        if clear_en_passant {
+            self.en_passant_target = 0;
+        }
 
-        self.validate_pawn_move(from, to, &mv, self.is_white())?;
-        Self::validate_move_piece(
-            &self.board,
-            Piece::Pawn,
+        self.board.update_compute_moves();
+        self.update_pinned_state();
+        self.update_check_state();
+
+        // only the squares that changed, the castling rights and en passant target
+        // before and after, and the side to move are hashed again
+        self.hash ^= zobrist::board_delta(&board_before, &self.board)
+            ^ zobrist::castling_key(castling_rights)
+            ^ zobrist::castling_key(self.castling_rights())
+            ^ zobrist::en_passant_key(undo.en_passant_target)
+            ^ zobrist::en_passant_key(self.en_passant_target)
+            ^ zobrist::KEYS.black_to_move;
+        debug_assert_eq!(self.compute_hash(), self.hash, "incremental hash out of sync");
+
+        if is_irreversible || castling_rights != self.castling_rights() {
+            undo.position_history = Some(std::mem::take(&mut self.position_history));
+        }
+        self.position_history.push(self.hash);
+
+        // final step is to update game status
+        self.update_game_status();
+
+        let own_pieces_after = self.own_pieces(piece, is_white);
+        let (from, to) = match undo.castling {
+            // the king may stay on its square in Chess960
+            Some(castling) => (castling.king_from, castling.king_to),
+            None => (own_pieces_before & !own_pieces_after, own_pieces_after & !own_pieces_before),
+        };
+        undo.mv = Move {
             from,
-            to,
-            pawns,
+            to: match undo.castling {
+                Some(castling) if self.chess960 => castling.rook_from,
+                _ => to,
+            },
+            promotion: self
+                .board
+                .get_piece_type_at(to)
+                .map(|(piece, _)| piece)
+                .filter(|promoted| piece == Piece::Pawn && *promoted != Piece::Pawn),
+        };
+        let mut san = san(&undo.mv);
+        if self.status() == Status::Checkmate {
+            san.push('#');
+        } else if self.check {
+            san.push('+');
+        }
+        let move_info = MoveInfo {
+            san,
             is_white,
-            mv.is_capture,
-            pseudolegal_moves,
-            pinned_pieces,
-            check,
-            self.en_passant_target,
-        )?;
-        self.move_piece(from, to, is_white, mv.is_capture)?;
+            piece,
+            from,
+            to,
+            is_capture,
+            is_check: self.check,
+        };
+        let opponent_pieces = |board: &Board| if is_white { board.black_pieces } else { board.white_pieces };
+        let captured = opponent_pieces(&board_before) & !opponent_pieces(&self.board);
+        undo.captured = board_before.get_piece_type_at(captured).map(|(piece, _)| (piece, captured));
+        self.undo_history.push(undo);
 
-        if let Some(SpecialMove::Promotion(piece)) = mv.special_move {
-            self.board.replace_pawn(to, is_white, piece);
-        }
+        self.history.push(move_info.clone());
+        Ok(move_info)
+    }
 
-        // flag for en passant for double move
-        if (is_white && from << 16 == to) || (!is_white && from >> 16 == to) {
-            self.en_passant_target = if is_white { from << 8 } else { from >> 8 };
+    /// takes back the last move, restoring the position and the game state
+    /// (castling rights, en passant, clocks and status) before it
+    pub fn undo(&mut self) -> Result<(), MoveError> {
+        let undo = self.undo_history.pop().ok_or(MoveError::NothingToUndo)?;
+        let is_white = undo.is_white;
+        let Move { from, to, promotion } = undo.mv;
+
+        if let Some(castling) = undo.castling {
+            self.board.remove_piece(castling.king_to, is_white);
+            self.board.remove_piece(castling.rook_to, is_white);
+            self.board.add_piece(castling.king_from, is_white, Piece::King);
+            self.board.add_piece(castling.rook_from, is_white, Piece::Rook);
+        } else if promotion.is_some() {
+            self.board.remove_piece(to, is_white);
+            self.board.add_piece(from, is_white, Piece::Pawn);
         } else {
-            self.en_passant_target = 0;
+            self.board.move_piece(to, from, is_white);
         }
-
+        if let Some((piece, square)) = undo.captured {
+            self.board.add_piece(square, !is_white, piece);
+        }
+        self.board.update_compute_moves();
+
+        (
+            self.white_can_castle_kingside,
+            self.white_can_castle_queenside,
+            self.black_can_castle_kingside,
+            self.black_can_castle_queenside,
+        ) = undo.castling_rights;
+        self.en_passant_target = undo.en_passant_target;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.turn = undo.turn;
+        self.hash = undo.hash;
+        self.pinned_white = undo.pinned_white;
+        self.pinned_black = undo.pinned_black;
+        self.update_check_state();
+        self.result = undo.result;
+        self.legal_moves.take();
+
+        self.position_history.pop();
+        if let Some(position_history) = undo.position_history {
+            self.position_history = position_history;
+        }
+        self.history.pop();
+        Ok(())
+    }
+
+    /// source square of a parsed move, 0 (or several squares if ambiguous) when no
+    /// piece matches. Castling has no source, see [`Game::process_castling`]
+    fn resolve_source(&self, mv: &ParsedMove, is_white: bool) -> u64 {
+        match mv.piece {
+            Piece::Pawn => resolve_pawn_source(&self.board, mv, is_white),
+            Piece::Knight => resolve_knight_source(&self.board, mv, is_white),
+            Piece::Bishop => resolve_bishop_source(&self.board, mv, is_white),
+            Piece::Rook => resolve_rook_source(&self.board, mv, is_white),
+            Piece::Queen => resolve_queen_source(&self.board, mv, is_white),
+            Piece::King => resolve_king_source(&self.board, mv, is_white),
+            Piece::Castling => 0,
+        }
+    }
+
+    /// human explanation of why `cmd` can't be played in the current position, naming
+    /// the pieces and squares involved (e.g. the pinning piece or the blocking piece).
+    /// Returns `None` if the move is legal
+    pub fn explain_illegal(&self, cmd: &str) -> Option<String> {
+        let err = self.clone().process_move(cmd).err()?;
+        let parsed_move = match parse_move(cmd.trim()) {
+            Ok(parsed_move) => parsed_move,
+            Err(_) => return Some(format!("\"{}\" is not a valid move", cmd.trim())),
+        };
+
+        let is_white = self.is_white();
+        let piece = parsed_move.piece;
+        let to = parsed_move.to;
+        let target = square_name(to).unwrap_or_default();
+
+        // own pieces matching the move, narrowed down by the source file/rank if given
+        let mut sources = Vec::new();
+        if piece != Piece::Castling {
+            let mut pieces = Self::get_pieces(&self.board, piece, is_white);
+            while pieces != 0 {
+                let from = 1u64 << pieces.trailing_zeros();
+                pieces &= pieces - 1;
+                let from_square = square_name(from).unwrap();
+                if parsed_move.from_file.is_some_and(|file| !from_square.starts_with(file))
+                    || parsed_move.from_rank.is_some_and(|rank| !from_square.ends_with(&rank.to_string()))
+                {
+                    continue;
+                }
+                sources.push(from);
+            }
+        }
+
+        let explanation = match err {
+            MoveError::GameOver => "The game is over".to_string(),
+            MoveError::NothingToUndo => "There is no move to take back".to_string(),
+            MoveError::WrongCheckSuffix => match split_suffix(cmd.trim()).1 {
+                Some(CheckSuffix::Checkmate) => format!("{} is not checkmate", cmd.trim()),
+                _ => format!("{} does not give check", cmd.trim()),
+            },
+            MoveError::ParseError => format!("\"{}\" is not a valid move", cmd.trim()),
+            MoveError::AmbiguousSource(sources) => format!(
+                "More than one {} can move to {}, did you mean {}?",
+                piece_name(piece),
+                target,
+                or_list(&self.disambiguate(cmd, sources))
+            ),
+            MoveError::Pinned | MoveError::Checked => {
+                let attacker = sources
+                    .iter()
+                    .filter(|from| compute_piece_moves(&self.board, piece, **from, is_white) & to != 0)
+                    .find_map(|from| self.attacker_after_move(*from, to).map(|attacker| (*from, attacker)));
+                match (err, attacker) {
+                    (MoveError::Checked, _) if self.checker_count >= 2 && piece != Piece::King => {
+                        "Your king is in double check, only the king can move".to_string()
+                    }
+                    (MoveError::Pinned, Some((from, (attacker, square)))) => format!(
+                        "The {} on {} is pinned, moving it would put your king in check from the {} on {}",
+                        piece_name(piece),
+                        square_name(from).unwrap(),
+                        piece_name(attacker),
+                        square_name(square).unwrap()
+                    ),
+                    (_, Some((_, (attacker, square)))) => format!(
+                        "Your king would be in check from the {} on {}",
+                        piece_name(attacker),
+                        square_name(square).unwrap()
+                    ),
+                    (_, None) => "Your king would be in check".to_string(),
+                }
+            }
+            MoveError::InvalidMove(reason) => {
+                let blocked = sources
+                    .iter()
+                    .find_map(|from| self.blocker_between(piece, *from, to).map(|blocker| (*from, blocker)));
+                match blocked {
+                    Some((from, (blocker, blocker_is_white, square))) => format!(
+                        "The {} {} is blocked by {} {} on {}",
+                        square_name(from).unwrap(),
+                        piece_name(piece),
+                        if blocker_is_white == is_white { "your" } else { "the opponent's" },
+                        piece_name(blocker),
+                        square_name(square).unwrap()
+                    ),
+                    None => match reason {
+                        InvalidMoveReason::InvalidCaptureTarget => format!("There is nothing to capture on {}", target),
+                        InvalidMoveReason::KingCaptureMove => "The king can't be captured".to_string(),
+                        InvalidMoveReason::PawnNonDiagonalCapture => "Pawns can only capture diagonally".to_string(),
+                        InvalidMoveReason::PawnInvalidPromotion => {
+                            "A pawn reaching the last rank must promote to a knight, bishop, rook or queen".to_string()
+                        }
+                        InvalidMoveReason::NoCastlingRight => "You can no longer castle on that side".to_string(),
+                        InvalidMoveReason::CastlingPathBlocked => {
+                            "The squares between king and rook must be empty and not attacked".to_string()
+                        }
+                        InvalidMoveReason::NoCastlingRook => "There is no rook to castle with".to_string(),
+                        InvalidMoveReason::NoSourceOrTarget
+                        | InvalidMoveReason::InvalidSourceOrTarget
+                        | InvalidMoveReason::MultipleTargets => {
+                            format!("No {} can move to {}", piece_name(piece), target)
+                        }
+                    },
+                }
+            }
+        };
+        Some(explanation)
+    }
+
+    /// opponent piece (and its square) giving check after moving `from` to `to`
+    fn attacker_after_move(&self, from: u64, to: u64) -> Option<(Piece, u64)> {
+        let is_white = self.is_white();
+        let mut board = self.board;
+        if board.is_capture(to, is_white) {
+            board.remove_piece(to, !is_white);
+        }
+        board.move_piece(from, to, is_white);
+
+        let checkers = Self::king_attackers(&board, is_white);
+        let square = 1u64.checked_shl(checkers.trailing_zeros())?;
+        board.get_piece_type_at(square).map(|(piece, _)| (piece, square))
+    }
+
+    /// opponent pieces currently giving check to the side to move (two bits for a
+    /// double check)
+    pub fn checkers(&self) -> u64 {
+        self.checkers
+    }
+
+    /// checkers and their lines of attack to the king, `None` when not in check
+    pub fn check_info(&self) -> Option<CheckInfo> {
+        self.check.then_some(CheckInfo {
+            checkers: self.checkers,
+            lines: self.check_lines,
+        })
+    }
+
+    fn king_attackers(board: &Board, is_white: bool) -> u64 {
+        let king = Self::get_pieces(board, Piece::King, is_white);
+        board
+            .pieces()
+            .filter(|(square, piece, piece_is_white)| {
+                *piece_is_white != is_white && compute_piece_moves(board, *piece, *square, !is_white) & king != 0
+            })
+            .fold(0, |attackers, (square, _, _)| attackers | square)
+    }
+
+    /// first piece standing between a sliding piece on `from` and `to`, `None` if
+    /// `to` is not on one of the piece's lines or nothing is in between
+    fn blocker_between(&self, piece: Piece, from: u64, to: u64) -> Option<(Piece, bool, u64)> {
+        let (from_idx, to_idx) = (from.trailing_zeros() as i32, to.trailing_zeros() as i32);
+        let file_delta = to_idx % 8 - from_idx % 8;
+        let rank_delta = to_idx / 8 - from_idx / 8;
+        let straight = file_delta == 0 || rank_delta == 0;
+        let diagonal = file_delta.abs() == rank_delta.abs();
+        let on_line = match piece {
+            Piece::Rook => straight,
+            Piece::Bishop => diagonal,
+            Piece::Queen => straight || diagonal,
+            _ => false,
+        };
+        if !on_line || from == to || to == 0 {
+            return None;
+        }
+
+        let step = rank_delta.signum() * 8 + file_delta.signum();
+        let mut idx = from_idx + step;
+        while idx != to_idx {
+            if let Some((blocker, blocker_is_white)) = self.board.get_piece_type_at(1 << idx) {
+                return Some((blocker, blocker_is_white, 1 << idx));
+            }
+            idx += step;
+        }
+        None
+    }
+
+    /// creates game from the start position (or FEN) followed by UCI coordinate moves,
+    /// same as the UCI `position [startpos|fen ...] moves ...` command
+    pub fn from_uci_moves(start: Option<&str>, moves: &[&str]) -> Result<Game, MoveError> {
+        let mut game = match start {
+            Some(fen) => Game::from_fen(fen).map_err(|_| MoveError::ParseError)?,
+            None => Game::default(),
+        };
+        for mv in moves {
+            game.apply_uci(mv)?;
+        }
+        Ok(game)
+    }
+
+    /// plays a UCI coordinate move (e.g. `e2e4`, `e7e8q`, `e1g1`), only legal moves
+    /// are accepted. See [`Game::apply_move`]
+    pub fn apply_uci(&mut self, cmd: &str) -> Result<MoveInfo, MoveError> {
+        let uci = parse_uci(cmd.trim()).map_err(|_| MoveError::ParseError)?;
+        if self.status() != Status::Ongoing {
+            return Err(MoveError::GameOver);
+        }
+        if self.legal_destinations(uci.from) & uci.to == 0 {
+            return Err(MoveError::InvalidMove(InvalidMoveReason::InvalidSourceOrTarget));
+        }
+        self.apply_move(Move {
+            from: uci.from,
+            to: uci.to,
+            promotion: uci.promotion,
+        })
+    }
+
+    /// SAN (without check suffix) for a legal move, disambiguated only when
+    /// another piece of the same type can reach the target square
+    fn move_to_san(&self, mv: &Move) -> String {
+        let is_white = self.is_white();
+        let (piece, _) = self.board.get_piece_type_at(mv.from).unwrap();
+        let from = square_name(mv.from).unwrap();
+        let to = square_name(mv.to).unwrap();
+        let file_distance = (mv.from.trailing_zeros() % 8).abs_diff(mv.to.trailing_zeros() % 8);
+        if let Some(is_kingside) = self.castling_side(mv) {
+            return if is_kingside { "O-O" } else { "O-O-O" }.to_string();
+        }
+
+        let letter = match piece {
+            Piece::Knight => 'N',
+            Piece::Bishop => 'B',
+            Piece::Rook => 'R',
+            Piece::Queen => 'Q',
+            Piece::King => 'K',
+            Piece::Pawn | Piece::Castling => ' ',
+        };
+
+        match piece {
+            Piece::Pawn => {
+                let mut san = if file_distance != 0 {
+                    format!("{}x{}", &from[..1], to)
+                } else {
+                    to
+                };
+                if let Some(promotion) = mv.promotion {
+                    let promotion = match promotion {
+                        Piece::Knight => 'N',
+                        Piece::Bishop => 'B',
+                        Piece::Rook => 'R',
+                        _ => 'Q',
+                    };
+                    san.push('=');
+                    san.push(promotion);
+                }
+                san
+            }
+            _ => {
+                let mut others = Self::get_pieces(&self.board, piece, is_white) & !mv.from;
+                let mut same_file = false;
+                let mut same_rank = false;
+                let mut ambiguous = false;
+                while others != 0 {
+                    let other = 1u64 << others.trailing_zeros();
+                    others &= others - 1;
+                    if self.legal_destinations(other) & mv.to != 0 {
+                        ambiguous = true;
+                        same_file |= other.trailing_zeros() % 8 == mv.from.trailing_zeros() % 8;
+                        same_rank |= other.trailing_zeros() / 8 == mv.from.trailing_zeros() / 8;
+                    }
+                }
+                let disambiguation = match (ambiguous, same_file, same_rank) {
+                    (false, _, _) => "",
+                    (true, false, _) => &from[..1],
+                    (true, true, false) => &from[1..],
+                    (true, true, true) => &from,
+                };
+                let capture = if self.board.is_capture(mv.to, is_white) { "x" } else { "" };
+                format!("{}{}{}{}", letter, disambiguation, capture, to)
+            }
+        }
+    }
+
+    fn process_pawn(
+        &mut self,
+        mv: ParsedMove,
+        from: u64,
+        pawns: u64,
+        is_white: bool,
+        pseudolegal_moves: u64,
+        pinned_pieces: u64,
+    ) -> Result<(), MoveError> {
+        let to = mv.to;
+
+        self.validate_pawn_move(from, to, &mv, self.is_white())?;
+        Self::validate_move_piece(
+            &self.board,
+            Piece::Pawn,
+            from,
+            to,
+            pawns,
+            is_white,
+            mv.is_capture,
+            pseudolegal_moves,
+            pinned_pieces,
+            self.check,
+            self.en_passant_target,
+        )?;
+        self.move_piece(from, to, is_white, mv.is_capture)?;
+
+        // without an explicit piece the pawn is promoted to a queen
+        // (validate_pawn_move rejects it if explicit promotion is required)
+        let last_rank = if is_white { is_rank(to, 8) } else { is_rank(to, 1) };
+        match mv.special_move {
+            Some(SpecialMove::Promotion(piece)) => self.board.replace_pawn(to, is_white, piece),
+            _ if last_rank => self.board.replace_pawn(to, is_white, Piece::Queen),
+            _ => {}
+        }
+
+        // flag for en passant for double move
+        if (is_white && from << 16 == to) || (!is_white && from >> 16 == to) {
+            self.en_passant_target = if is_white { from << 8 } else { from >> 8 };
+        } else {
+            self.en_passant_target = 0;
+        }
+
         Ok(())
     }
 
     fn process_king(
         &mut self,
         mv: ParsedMove,
+        from: u64,
         king: u64,
         is_white: bool,
         pseudolegal_moves: u64,
         pinned_pieces: u64,
-        check: bool,
     ) -> Result<(), MoveError> {
         let to = mv.to;
-        let from = resolve_king_source(&self.board, &mv, self.is_white());
 
         self.validate_king_move(from, to, self.is_white())?;
         Self::validate_move_piece(
@@ -382,7 +1408,7 @@ impl Game {
             mv.is_capture,
             pseudolegal_moves,
             pinned_pieces,
-            check,
+            self.check,
             0,
         )?;
         self.move_piece(from, to, is_white, mv.is_capture)?;
@@ -395,14 +1421,13 @@ impl Game {
     fn process_bishop(
         &mut self,
         mv: ParsedMove,
+        from: u64,
         rook: u64,
         is_white: bool,
         pseudolegal_moves: u64,
         pinned_pieces: u64,
-        check: bool,
     ) -> Result<(), MoveError> {
         let to = mv.to;
-        let from = resolve_bishop_source(&self.board, &mv, self.is_white());
 
         self.validate_bishop_move(from, to)?;
         Self::validate_move_piece(
@@ -415,7 +1440,7 @@ impl Game {
             mv.is_capture,
             pseudolegal_moves,
             pinned_pieces,
-            check,
+            self.check,
             0,
         )?;
         self.move_piece(from, to, is_white, mv.is_capture)
@@ -425,14 +1450,13 @@ impl Game {
     fn process_queen(
         &mut self,
         mv: ParsedMove,
+        from: u64,
         rook: u64,
         is_white: bool,
         pseudolegal_moves: u64,
         pinned_pieces: u64,
-        check: bool,
     ) -> Result<(), MoveError> {
         let to = mv.to;
-        let from = resolve_queen_source(&self.board, &mv, self.is_white());
 
         self.validate_queen_move(from, to)?;
         Self::validate_move_piece(
@@ -445,7 +1469,7 @@ impl Game {
             mv.is_capture,
             pseudolegal_moves,
             pinned_pieces,
-            check,
+            self.check,
             0,
         )?;
         self.move_piece(from, to, is_white, mv.is_capture)
@@ -454,14 +1478,13 @@ impl Game {
     fn process_knight(
         &mut self,
         mv: ParsedMove,
+        from: u64,
         rook: u64,
         is_white: bool,
         pseudolegal_moves: u64,
         pinned_pieces: u64,
-        check: bool,
     ) -> Result<(), MoveError> {
         let to = mv.to;
-        let from = resolve_knight_source(&self.board, &mv, self.is_white());
 
         self.validate_knight_move(from, to)?;
         Self::validate_move_piece(
@@ -474,7 +1497,7 @@ impl Game {
             mv.is_capture,
             pseudolegal_moves,
             pinned_pieces,
-            check,
+            self.check,
             0,
         )?;
         self.move_piece(from, to, is_white, mv.is_capture)
@@ -484,14 +1507,13 @@ impl Game {
     fn process_rook(
         &mut self,
         mv: ParsedMove,
+        from: u64,
         rook: u64,
         is_white: bool,
         pseudolegal_moves: u64,
         pinned_pieces: u64,
-        check: bool,
     ) -> Result<(), MoveError> {
         let to = mv.to;
-        let from = resolve_rook_source(&self.board, &mv, self.is_white());
 
         self.validate_rook_move(from, to)?;
         Self::validate_move_piece(
@@ -504,21 +1526,46 @@ impl Game {
             mv.is_capture,
             pseudolegal_moves,
             pinned_pieces,
-            check,
+            self.check,
             0,
         )?;
         self.move_piece(from, to, is_white, mv.is_capture)?;
 
         // remove castling right
-        if is_file(from, 'a') {
-            self.remove_castling_right(false, is_white);
-        } else if is_file(from, 'h') {
-            self.remove_castling_right(true, is_white);
+        for is_kingside in [true, false] {
+            if from == self.castling_rook_square(is_kingside, is_white) {
+                self.remove_castling_right(is_kingside, is_white);
+            }
         }
 
         Ok(())
     }
 
+    fn castling_rights(&self) -> (bool, bool, bool, bool) {
+        (
+            self.white_can_castle_kingside,
+            self.white_can_castle_queenside,
+            self.black_can_castle_kingside,
+            self.black_can_castle_queenside,
+        )
+    }
+
+    /// remaining castling rights of one side, `K` for kingside and `Q` for
+    /// queenside (e.g. "K Q"), or "-" if the side can no longer castle
+    pub fn castling_rights_label(&self, is_white: bool) -> String {
+        let (kingside, queenside) = if is_white {
+            (self.white_can_castle_kingside, self.white_can_castle_queenside)
+        } else {
+            (self.black_can_castle_kingside, self.black_can_castle_queenside)
+        };
+        match (kingside, queenside) {
+            (true, true) => "K Q".to_string(),
+            (true, false) => "K".to_string(),
+            (false, true) => "Q".to_string(),
+            (false, false) => "-".to_string(),
+        }
+    }
+
     fn remove_castling_right(&mut self, is_kingside: bool, is_white: bool) {
         if is_white {
             if is_kingside {
@@ -535,37 +1582,103 @@ impl Game {
         }
     }
 
-    fn process_castling(&mut self, mv: ParsedMove, is_white: bool) -> Result<(), MoveError> {
-        if let Some(special_move) = mv.special_move {
-            let king = Self::get_pieces(&self.board, Piece::King, is_white);
-            let rooks = Self::get_pieces(&self.board, Piece::Rook, is_white);
+    /// index of the castling right in [`Game::castling_rights`] order
+    fn castling_index(is_kingside: bool, is_white: bool) -> usize {
+        match (is_white, is_kingside) {
+            (true, true) => 0,
+            (true, false) => 1,
+            (false, true) => 2,
+            (false, false) => 3,
+        }
+    }
+
+    /// starting square of the rook castling on the given side
+    fn castling_rook_square(&self, is_kingside: bool, is_white: bool) -> u64 {
+        let rank = if is_white { MASK_RANK_1 } else { MASK_RANK_8 };
+        rank & MASK_FILE_A << self.castling_rook_files[Self::castling_index(is_kingside, is_white)]
+    }
+
+    /// king and rook squares of castling on the given side, the king lands on the g
+    /// or c file and the rook next to it. `None` without the king or the rook on the
+    /// back rank
+    fn castling_move(&self, is_kingside: bool, is_white: bool) -> Option<CastlingMove> {
+        let rank = if is_white { MASK_RANK_1 } else { MASK_RANK_8 };
+        let king_from = Self::get_pieces(&self.board, Piece::King, is_white) & rank;
+        let rook_from = self.castling_rook_square(is_kingside, is_white);
+        if king_from == 0 || Self::get_pieces(&self.board, Piece::Rook, is_white) & rook_from == 0 {
+            return None;
+        }
+        let (king_to, rook_to) = if is_kingside {
+            (MASK_FILE_G, MASK_FILE_F)
+        } else {
+            (MASK_FILE_C, MASK_FILE_D)
+        };
+        Some(CastlingMove {
+            king_from,
+            king_to: rank & king_to,
+            rook_from,
+            rook_to: rank & rook_to,
+        })
+    }
 
+    /// target square of castling in [`Game::legal_moves`]: the king's square after
+    /// castling, or the castling rook in Chess960
+    fn castling_target(&self, is_kingside: bool, is_white: bool) -> u64 {
+        if self.chess960 {
+            self.castling_rook_square(is_kingside, is_white)
+        } else {
             let rank = if is_white { MASK_RANK_1 } else { MASK_RANK_8 };
+            rank & if is_kingside { MASK_FILE_G } else { MASK_FILE_C }
+        }
+    }
 
-            let (is_kingside, rook_mask, king_target, rook_target) = match special_move {
-                SpecialMove::CastlingKing => (true, MASK_FILE_H, MASK_FILE_G, MASK_FILE_F),
-                SpecialMove::CastlingQueen => (false, MASK_FILE_A, MASK_FILE_C, MASK_FILE_D),
-                _ => {
-                    return Err(MoveError::InvalidMove(
-                        InvalidMoveReason::InvalidSourceOrTarget,
-                    ))
-                }
-            };
+    /// side of castling (`true` for kingside) if the move castles, see
+    /// [`Game::castling_target`]. Only while the side has the right and its rook,
+    /// otherwise a Chess960 king stepping onto the rook's square is a plain move
+    fn castling_side(&self, mv: &Move) -> Option<bool> {
+        let is_white = self.is_white();
+        if self.board.get_piece_type_at(mv.from) != Some((Piece::King, is_white)) {
+            return None;
+        }
+        let file_distance = (mv.from.trailing_zeros() % 8).abs_diff(mv.to.trailing_zeros() % 8);
+        if !self.chess960 && file_distance != 2 {
+            return None;
+        }
+        let rights = self.castling_rights();
+        let rights = [rights.0, rights.1, rights.2, rights.3];
+        [true, false].into_iter().find(|is_kingside| {
+            mv.to == self.castling_target(*is_kingside, is_white)
+                && rights[Self::castling_index(*is_kingside, is_white)]
+                && self.castling_move(*is_kingside, is_white).is_some()
+        })
+    }
+
+    fn process_castling(&mut self, mv: ParsedMove, is_white: bool) -> Result<CastlingMove, MoveError> {
+        let is_kingside = match mv.special_move {
+            Some(SpecialMove::CastlingKing) => true,
+            Some(SpecialMove::CastlingQueen) => false,
+            _ => {
+                return Err(MoveError::InvalidMove(
+                    InvalidMoveReason::InvalidSourceOrTarget,
+                ))
+            }
+        };
 
-            self.validate_castling(is_kingside, is_white)?;
+        self.validate_castling(is_kingside, is_white)?;
+        let castling = self
+            .castling_move(is_kingside, is_white)
+            .ok_or(MoveError::InvalidMove(InvalidMoveReason::NoCastlingRook))?;
 
-            let rook = rooks & rook_mask;
-            self.move_piece(king, rank & king_target, is_white, false)?;
-            self.move_piece(rook, rank & rook_target, is_white, false)?;
+        // the king may land on the rook's square (or the other way around) in Chess960
+        self.board.remove_piece(castling.king_from, is_white);
+        self.board.remove_piece(castling.rook_from, is_white);
+        self.board.add_piece(castling.king_to, is_white, Piece::King);
+        self.board.add_piece(castling.rook_to, is_white, Piece::Rook);
 
-            // remove castling rights
-            self.remove_castling_right(true, is_white);
-            self.remove_castling_right(false, is_white);
-            return Ok(());
-        }
-        Err(MoveError::InvalidMove(
-            InvalidMoveReason::InvalidSourceOrTarget,
-        ))
+        // remove castling rights
+        self.remove_castling_right(true, is_white);
+        self.remove_castling_right(false, is_white);
+        Ok(castling)
     }
 
     // pawn specific move validation (diagonal capture, promotion, etc)
@@ -601,19 +1714,24 @@ impl Game {
             return Err(MoveError::InvalidMove(InvalidMoveReason::InvalidSourceOrTarget));
         }
 
-        if let Some(SpecialMove::Promotion(_)) = mv.special_move {
-            // promotion only allowed on rank 8 for white and rank 1 for black
-            let correct_rank = if is_white {
-                is_rank(to, 8)
-            } else {
-                is_rank(to, 1)
-            };
-
-            if !correct_rank {
+        // promotion only allowed on rank 8 for white and rank 1 for black
+        let correct_rank = if is_white {
+            is_rank(to, 8)
+        } else {
+            is_rank(to, 1)
+        };
+        match mv.special_move {
+            Some(SpecialMove::Promotion(_)) if !correct_rank => {
+                return Err(MoveError::InvalidMove(
+                    InvalidMoveReason::PawnInvalidPromotion,
+                ));
+            }
+            None if correct_rank && self.require_explicit_promotion => {
                 return Err(MoveError::InvalidMove(
                     InvalidMoveReason::PawnInvalidPromotion,
                 ));
             }
+            _ => {}
         }
         Ok(())
     }
@@ -663,13 +1781,11 @@ impl Game {
         if from == 0 {
             return Ok(())
         }
-        let opponent_attacks = Self::get_attack_moves(&self.board, is_white);
-
         let from_idx = from.trailing_zeros() as usize;
         if to & KING_MOVES[from_idx] == 0 {
             return Err(MoveError::InvalidMove(InvalidMoveReason::InvalidSourceOrTarget));
         }
-        if to & opponent_attacks != 0 {
+        if self.is_square_attacked(to, !is_white) {
             Err(MoveError::Checked)
         } else {
             Ok(())
@@ -681,63 +1797,35 @@ impl Game {
             return Err(MoveError::Checked);
         }
 
-        // Define the structs to hold color-specific data
-        struct CastlingData {
-            can_castle_kingside: bool,
-            can_castle_queenside: bool,
-            rooks: u64,
-            rank_mask: u64,
-            attack_moves: u64,
-        }
-
-        // Get the appropriate data based on color
-        let data = if is_white {
-            CastlingData {
-                can_castle_kingside: self.white_can_castle_kingside,
-                can_castle_queenside: self.white_can_castle_queenside,
-                rooks: self.board.white_rooks,
-                rank_mask: MASK_RANK_1,
-                attack_moves: self.board.black_attack_moves,
-            }
-        } else {
-            CastlingData {
-                can_castle_kingside: self.black_can_castle_kingside,
-                can_castle_queenside: self.black_can_castle_queenside,
-                rooks: self.board.black_rooks,
-                rank_mask: MASK_RANK_8,
-                attack_moves: self.board.white_attack_moves,
-            }
-        };
-
         // Check castling rights
-        let can_castle = if is_kingside {
-            data.can_castle_kingside
-        } else {
-            data.can_castle_queenside
-        };
+        let rights = self.castling_rights();
+        let can_castle = [rights.0, rights.1, rights.2, rights.3][Self::castling_index(is_kingside, is_white)];
         if !can_castle {
             return Err(MoveError::InvalidMove(InvalidMoveReason::NoCastlingRight));
         }
 
         // Check if rook is present
-        let rook_mask = if is_kingside {
-            MASK_CASTLING_KINGSIDE_PIECE
-        } else {
-            MASK_CASTLING_QUEENSIDE_PIECE
-        };
-        if data.rooks & rook_mask & data.rank_mask == 0 {
+        let Some(castling) = self.castling_move(is_kingside, is_white) else {
             return Err(MoveError::InvalidMove(InvalidMoveReason::NoCastlingRook));
+        };
+        let CastlingMove { king_from, king_to, rook_from, rook_to } = castling;
+        let (king_idx, rook_idx) = (king_from.trailing_zeros() as usize, rook_from.trailing_zeros() as usize);
+        let king_path = BETWEEN[king_idx][king_to.trailing_zeros() as usize] | king_to;
+        let rook_path = BETWEEN[rook_idx][rook_to.trailing_zeros() as usize] | rook_to;
+
+        // Check if castling path is clear, apart from the castling king and rook. The
+        // king must not pass or land on an attacked square, the castling rook doesn't
+        // shield it since it moves away
+        let mut board = self.board;
+        board.remove_piece(king_from, is_white);
+        board.remove_piece(rook_from, is_white);
+        let mut attacked = false;
+        let mut squares = king_path;
+        while squares != 0 && !attacked {
+            attacked = board.attackers_to(1 << squares.trailing_zeros(), !is_white) != 0;
+            squares &= squares - 1;
         }
-
-        // Check if castling path is clear
-        let path_mask = if is_kingside {
-            MASK_CASTLING_PATH_KINGSIDE
-        } else {
-            MASK_CASTLING_PATH_QUEENSIDE
-        } & data.rank_mask;
-
-        let path_clear = (path_mask & self.board.free & !data.attack_moves) == path_mask;
-        if !path_clear {
+        if (king_path | rook_path) & board.occupied != 0 || attacked {
             return Err(MoveError::InvalidMove(
                 InvalidMoveReason::CastlingPathBlocked,
             ));
@@ -758,47 +1846,26 @@ impl Game {
             return true; // no pin, all good
         }
 
-        let king_idx = king.trailing_zeros() as usize;
-        for direction in QUEEN_RAYS_DIRECTIONS {
-            let ray = QUEEN_RAYS[king_idx][direction];
-
-            // check only if from and to within the same ray
-            if ray & from != 0 && ray & to != 0 {
-                return true;
-            }
-        }
-        false
+        // the pinned piece stays between the king and the pinner, or captures it
+        let (king_idx, from_idx, to_idx) =
+            (king.trailing_zeros() as usize, from.trailing_zeros() as usize, to.trailing_zeros() as usize);
+        BETWEEN[king_idx][to_idx] & from != 0 || BETWEEN[king_idx][from_idx] & to != 0
     }
 
-    fn validate_move_check(board: &Board, from: u64, to: u64, is_white: bool) -> bool {
-        let mut simulated_board = board.clone();
-        let opponent_king;
-        let opponent_pieces;
-        if is_white {
-            opponent_king = simulated_board.black_king;
-            opponent_pieces = simulated_board.black_pieces ^ opponent_king;
-        } else {
-            opponent_king = simulated_board.white_king;
-            opponent_pieces = simulated_board.white_pieces ^ opponent_king;
-        }
-
+    /// whether the move leaves the own king in check. The move is made and unmade on
+    /// `board`, only the attackers of the king are looked up instead of recomputing
+    /// every move
+    fn validate_move_check(board: &mut Board, from: u64, to: u64, is_white: bool) -> bool {
         // do not allow capturing king
-        if to == opponent_king {
+        if to == Self::get_pieces(board, Piece::King, !is_white) {
             return false;
         }
 
-        simulated_board.move_piece(from, to, is_white);
-        if opponent_pieces & to != 0 {
-            // simulate capture
-            simulated_board.remove_piece(to, !is_white);
-        }
-
-        // update the whole moves for simplicity, this helps with capture and
-        // blocking move
-        simulated_board.update_compute_moves();
-
-        // if attack_moves & to
-        Self::is_in_check(&simulated_board, is_white)
+        let undo = board.apply_move_unchecked(from, to, is_white);
+        let king = Self::get_pieces(board, Piece::King, is_white);
+        let in_check = board.attackers_to(king, !is_white) != 0;
+        board.unmake(undo);
+        in_check
     }
 
     fn validate_move_piece(
@@ -829,7 +1896,7 @@ impl Game {
         }
 
         if from.count_ones() > 1 {
-            return Err(MoveError::AmbiguousSource);
+            return Err(MoveError::AmbiguousSource(from));
         }
 
         if (from & pieces) == 0 {
@@ -870,11 +1937,20 @@ impl Game {
             }
         }
 
+        // en passant also takes the captured pawn off the king's rank, the pin detection
+        // doesn't see a rook behind both pawns (e.g. `K2pP2r`), so it is always simulated
+        if is_en_passant_capture {
+            let mut simulated_board = *board;
+            let captured = if is_white { to >> 8 } else { to << 8 };
+            simulated_board.remove_piece(captured, !is_white);
+            if Self::validate_move_check(&mut simulated_board, from, to, is_white) {
+                return Err(MoveError::Checked);
+            }
         // validate_move_check is expensive, only use it if currently in check
         // OR when king perform a capture since captured piece may be protected
-        if is_check || is_capture && piece_type == Piece::King {
-            // if Self::is_in_check(board, is_white) {
-            if Self::validate_move_check(board, from, to, is_white) {
+        } else if is_check || is_capture && piece_type == Piece::King {
+            let mut simulated_board = *board;
+            if Self::validate_move_check(&mut simulated_board, from, to, is_white) {
                 return Err(MoveError::Checked);
             }
         }
@@ -889,10 +1965,12 @@ impl Game {
         is_white: bool,
         is_capture: bool,
     ) -> Result<(), MoveError> {
-        if is_capture {
-            self.board.move_piece(from, to, is_white);
-            self.board.remove_piece(to, !is_white);
-        } else if is_capture && to == self.en_passant_target {
+        // en passant captures onto an empty square, the captured pawn is behind it
+        let is_en_passant = is_capture
+            && to == self.en_passant_target
+            && self.board.get_piece_type_at(to).is_none();
+
+        if is_en_passant {
             let en_passant_piece = if is_white {
                 to >> 8 // black 1 box down
             } else {
@@ -900,6 +1978,9 @@ impl Game {
             };
             self.board.move_piece(from, to, is_white);
             self.board.remove_piece(en_passant_piece, !is_white);
+        } else if is_capture {
+            self.board.move_piece(from, to, is_white);
+            self.board.remove_piece(to, !is_white);
         } else {
             // Normal move
             self.board.move_piece(from, to, is_white);
@@ -924,68 +2005,65 @@ impl Game {
             self.board.black_pieces ^ king
         };
 
-        let opponent_sliding_moves = self.get_computed_pseudolegal_moves(Piece::Rook, !is_white)
-            | self.get_computed_pseudolegal_moves(Piece::Bishop, !is_white)
-            | self.get_computed_pseudolegal_moves(Piece::Queen, !is_white);
-
-        let opponent_sliding_pieces = Self::get_pieces(&self.board, Piece::Rook, !is_white)
-            | Self::get_pieces(&self.board, Piece::Bishop, !is_white)
-            | Self::get_pieces(&self.board, Piece::Queen, !is_white);
-
-        let mut pinned_pieces: u64 = 0;
-        // pin only happened through sliding pieces, check all sliding directions
+        let opponent_queens = Self::get_pieces(&self.board, Piece::Queen, !is_white);
+        let opponent_straight_pieces = Self::get_pieces(&self.board, Piece::Rook, !is_white) | opponent_queens;
+        let opponent_diagonal_pieces = Self::get_pieces(&self.board, Piece::Bishop, !is_white) | opponent_queens;
 
-        for direction_from_king in QUEEN_RAYS_DIRECTIONS {
-            // opposite direction of the ray (add by 4 and modulo 8)
-            let direction_to_king = (direction_from_king + 4) % 8;
-            let ray = QUEEN_RAYS[king_idx][direction_from_king];
+        let occupied = self.board.white_pieces | self.board.black_pieces;
 
-            let blockers = ray & own_pieces;
+        // pin only happened through sliding pieces lined up with the king, bishops only
+        // pin diagonally, rooks only pin along ranks and files
+        let rays = |rays: &[u64]| rays.iter().fold(0, |all, ray| all | ray);
+        let mut pinners = (rays(&ROOK_RAYS[king_idx]) & opponent_straight_pieces)
+            | (rays(&BISHOP_RAYS[king_idx]) & opponent_diagonal_pieces);
 
-            // pin only happens there is only 1 piece blocking a ray
-            if blockers.count_ones() != 1 {
-                continue;
+        let mut pinned_pieces: u64 = 0;
+        while pinners != 0 {
+            let pinner_idx = pinners.trailing_zeros() as usize;
+            // pin only happens when the only piece in between is an own piece
+            let blockers = BETWEEN[king_idx][pinner_idx] & occupied;
+            if blockers.count_ones() == 1 && blockers & own_pieces != 0 {
+                pinned_pieces |= blockers;
             }
 
-            let blocker_idx = blockers.trailing_zeros() as usize;
-            let blocker_bit = 1u64 << blocker_idx;
-
-            // found potential pin that can be attacked
-            if opponent_sliding_moves & blocker_bit != 0 {
-                // only filter the sliding pieces if the ray can reach the king (FROM KING outwards)
-                let candidate_pinners = opponent_sliding_pieces & QUEEN_RAYS[king_idx][direction_from_king];
-                let mut pieces = candidate_pinners;
-                while pieces != 0 {
-                    let piece_idx = pieces.trailing_zeros() as usize;
-
-                    // the ray direction is TOWARDS the king
-                    let opponent_ray = QUEEN_RAYS[piece_idx][direction_to_king];
-                    let (_, blocker_mask) = find_blocker_mask(opponent_ray, own_pieces, direction_to_king);
-
-                    // the ray will hit the king
-                    if blocker_mask & king != 0 {
-                        // get the opponent ray to the pinned piece
-                        let opponent_ray_to_blocker = opponent_ray & !blocker_mask;
-
-                        // pin only if actual opponent sliding move can hit the blocker and it is the
-                        // same as the opponent ray to blocker (e.g. no other friendly piece blocking
-                        // the opponent ray)
-                        if opponent_sliding_moves & opponent_ray_to_blocker == opponent_ray_to_blocker {
-                            pinned_pieces |= blocker_bit;
-                            break;
-                        }
-                    }
-
-                    // Remove the processed piece (use lsb approach)
-                    pieces &= pieces - 1;
-                }
-            }
+            // Remove the processed piece (use lsb approach)
+            pinners &= pinners - 1;
         }
         pinned_pieces
     }
 
     fn update_check_state(&mut self) {
         self.check = Self::is_in_check(&self.board, self.is_white());
+        self.checkers = if self.check {
+            Self::king_attackers(&self.board, self.is_white())
+        } else {
+            0
+        };
+        self.checker_count = self.checkers.count_ones() as u8;
+
+        let king_idx = Self::get_pieces(&self.board, Piece::King, self.is_white()).trailing_zeros() as usize;
+        let mut checkers = self.checkers;
+        self.check_lines = 0;
+        while checkers != 0 {
+            let checker_idx = checkers.trailing_zeros() as usize;
+            // not aligned with the king (knight) or next to it, nothing in between
+            self.check_lines |= BETWEEN[king_idx][checker_idx] | 1 << checker_idx;
+            checkers &= checkers - 1;
+        }
+    }
+
+    /// whether pieces of the given color attack the square (any of them if several
+    /// bits are set), using the attack maps cached on the board. Pawns count with
+    /// their diagonal attack bitboard, never their push squares. The maps hold move
+    /// targets, so a square occupied by one of the attacker's own pieces is never
+    /// reported as attacked, this can't tell whether a piece is defended
+    pub fn is_square_attacked(&self, square: u64, by_white: bool) -> bool {
+        let attacks = if by_white {
+            self.board.white_attack_moves
+        } else {
+            self.board.black_attack_moves
+        };
+        square & attacks != 0
     }
 
     fn get_attack_moves(board: &Board, is_white: bool) -> u64 {
@@ -1003,200 +2081,632 @@ impl Game {
         king & opponent_attacks != 0
     }
 
-    fn has_valid_move(
-        &self,
-        piece: Piece,
-        mut pseudolegal_moves: u64,
-        is_white: bool,
-        opponent_pieces: u64,
-    ) -> bool {
-        let pinned = if is_white {
-            self.pinned_white
-        } else {
-            self.pinned_black
+    /// fully legal target squares (pins and checks applied) for the piece on `from`.
+    /// Returns 0 if there is no piece of the side to move on `from` or the game is over.
+    /// Castling is included as the king's target square
+    pub fn legal_destinations(&self, from: u64) -> u64 {
+        self.legal_moves()
+            .iter()
+            .filter(|mv| mv.from == from)
+            .fold(0, |destinations, mv| destinations | mv.to)
+    }
+
+    /// [`Game::legal_destinations`] computed from the position, whatever the status
+    fn generate_destinations(&self, from: u64) -> u64 {
+        if from.count_ones() != 1 {
+            return 0;
+        }
+
+        let is_white = self.is_white();
+        let piece = match self.board.get_piece_type_at(from) {
+            Some((piece, piece_is_white)) if piece_is_white == is_white => piece,
+            _ => return 0,
         };
+        if self.checker_count >= 2 && piece != Piece::King {
+            return 0;
+        }
 
-        let mut pieces = Self::get_pieces(&self.board, piece, is_white);
-        while pieces != 0 {
-            let piece_idx = pieces.trailing_zeros() as u64;
-            let piece_pos = 1 << piece_idx;
-
-            while pseudolegal_moves != 0 {
-                let move_idx = pseudolegal_moves.trailing_zeros() as u64;
-                let single_move = 1 << move_idx;
-
-                let mut is_capture = single_move & opponent_pieces != 0;
-
-                // remove processed move
-                pseudolegal_moves &= pseudolegal_moves - 1;
-
-                match piece {
-                    Piece::Pawn => {
-                        let is_capture = if is_white {
-                            // diagonal upward
-                            piece_pos << 7 & single_move != 0 || piece_pos << 9 & single_move != 0
-                        } else {
-                            // diagonal downward
-                            piece_pos >> 7 & single_move != 0 || piece_pos >> 9 & single_move != 0
-                        };
-                        if self
-                            .validate_pawn_move(
-                                piece_pos,
-                                single_move,
-                                &ParsedMove {
-                                    piece,
-                                    from_file: None,
-                                    from_rank: None,
-                                    to: 0,
-                                    is_capture,
-                                    special_move: None,
-                                },
-                                is_white,
-                            )
-                            .is_err()
-                        {
-                            // skip if
-                            continue;
-                        }
-                    }
-                    Piece::Knight => {
-                        if self.validate_knight_move(piece_pos, single_move).is_err() {
-                            continue;
-                        }
-                    }
-                    Piece::Rook => {
-                        if self.validate_rook_move(piece_pos, single_move).is_err() {
-                            continue;
-                        }
-                    }
-                    Piece::Bishop => {
-                        if self.validate_bishop_move(piece_pos, single_move).is_err() {
-                            continue;
-                        }
-                    }
-                    Piece::Queen => {
-                        if self.validate_queen_move(piece_pos, single_move).is_err() {
-                            continue;
-                        }
-                    }
-                    Piece::King => {
-                        if self.validate_king_move(piece_pos, single_move, is_white).is_err() {
-                            // skip if king goes into the bad place
-                            continue;
-                        }
-                    }
-                    _ => {}
-                }
+        let opponent_king = Self::get_pieces(&self.board, Piece::King, !is_white);
+        let mut candidates = compute_piece_moves(&self.board, piece, from, is_white) & !opponent_king;
 
-                if Self::validate_move_piece(
-                    &self.board,
-                    piece,
-                    piece_pos,
-                    single_move,
-                    piece_pos,
-                    is_white,
-                    is_capture,
-                    single_move,
-                    pinned,
-                    self.check,
-                    0,
-                )
-                .is_ok()
-                {
-                    return true;
+        // en passant capture, captured pawn needs to be removed before simulating the move
+        let mut en_passant_board = None;
+        if piece == Piece::Pawn && self.en_passant_target != 0 {
+            let pawn_attacks = if is_white {
+                WHITE_PAWN_MOVES[from.trailing_zeros() as usize][1]
+            } else {
+                BLACK_PAWN_MOVES[from.trailing_zeros() as usize][1]
+            };
+            if pawn_attacks & self.en_passant_target != 0 {
+                let captured = if is_white {
+                    self.en_passant_target >> 8
+                } else {
+                    self.en_passant_target << 8
+                };
+                let mut board = self.board;
+                board.remove_piece(captured, !is_white);
+                en_passant_board = Some(board);
+                candidates |= self.en_passant_target;
+            }
+        }
+
+        let mut board = self.board;
+        let mut destinations = 0u64;
+        while candidates != 0 {
+            let to = 1u64 << candidates.trailing_zeros();
+            candidates &= candidates - 1;
+
+            let board = match en_passant_board {
+                Some(ref mut board) if to == self.en_passant_target => board,
+                _ => &mut board,
+            };
+            if !Self::validate_move_check(board, from, to, is_white) {
+                destinations |= to;
+            }
+        }
+
+        if piece == Piece::King {
+            for is_kingside in [true, false] {
+                if self.validate_castling(is_kingside, is_white).is_ok() {
+                    destinations |= self.castling_target(is_kingside, is_white);
                 }
             }
-            // remove the processed piece
-            pieces &= pieces - 1;
         }
-        false
+
+        destinations
     }
 
-    fn has_sufficient_materials(board: &Board) -> bool {
-        // if pawn/rook/queen still around return true
-        if board.white_pawns > 0
-            || board.black_pawns > 0
-            || board.white_queens > 0
-            || board.black_queens > 0
-            || board.white_rooks > 0
-            || board.black_rooks > 0
-        {
-            return true;
+    /// squares that get the side to move out of check: king escape squares, squares
+    /// blocking the check and the checking piece. Only king squares in double check,
+    /// 0 when not in check
+    pub fn check_resolution_squares(&self) -> u64 {
+        if !self.check {
+            return 0;
         }
 
-        let white_knights = board.white_knights.count_ones();
-        let black_knights = board.black_knights.count_ones();
-        let white_bishops = board.white_bishops.count_ones();
-        let black_bishops = board.black_bishops.count_ones();
-
-        let insufficient = matches!(
-            (white_knights, black_knights, white_bishops, black_bishops),
-            (0, 0, 0, 0)
-                | (1, 0, 0, 0)
-                | (0, 1, 0, 0)
+        let mut pieces = if self.is_white() {
+            self.board.white_pieces
+        } else {
+            self.board.black_pieces
+        };
+        let mut squares = 0u64;
+        while pieces != 0 {
+            let from = 1u64 << pieces.trailing_zeros();
+            pieces &= pieces - 1;
+            squares |= self.legal_destinations(from);
+        }
+        squares
+    }
+
+    /// every legal move of the side to move (pins, checks, castling and en passant
+    /// applied), promotions are listed once per promotion piece. Empty when the game
+    /// is over
+    pub fn legal_moves(&self) -> &[Move] {
+        if self.status() != Status::Ongoing {
+            return &[];
+        }
+        self.legal_moves.get_or_init(|| self.generate_legal_moves())
+    }
+
+    /// legal moves generated from the position whatever the status, cached by
+    /// [`Game::legal_moves`]
+    fn generate_legal_moves(&self) -> Vec<Move> {
+        let is_white = self.is_white();
+        let mut moves = Vec::new();
+        let mut pieces = self.movable_pieces();
+        while pieces != 0 {
+            let from = 1u64 << pieces.trailing_zeros();
+            pieces &= pieces - 1;
+
+            let is_pawn = self.board.get_piece_type_at(from) == Some((Piece::Pawn, is_white));
+            let mut destinations = self.generate_destinations(from);
+            while destinations != 0 {
+                let to = 1u64 << destinations.trailing_zeros();
+                destinations &= destinations - 1;
+
+                if is_pawn && (is_rank(to, 1) || is_rank(to, 8)) {
+                    for promotion in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+                        moves.push(Move { from, to, promotion: Some(promotion) });
+                    }
+                } else {
+                    moves.push(Move { from, to, promotion: None });
+                }
+            }
+        }
+        moves
+    }
+
+    /// pieces of the side to move that may have legal moves, only the king in double
+    /// check since no block or capture can stop both checks
+    fn movable_pieces(&self) -> u64 {
+        let is_white = self.is_white();
+        if self.checker_count >= 2 {
+            Self::get_pieces(&self.board, Piece::King, is_white)
+        } else if is_white {
+            self.board.white_pieces
+        } else {
+            self.board.black_pieces
+        }
+    }
+
+    /// number of leaf nodes of the legal move tree `depth` plies deep, compared with
+    /// known counts to verify move generation
+    pub fn perft(&self, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves
+            .iter()
+            .map(|mv| {
+                let mut next = self.clone();
+                next.apply_move(*mv).expect("legal move");
+                next.perft(depth - 1)
+            })
+            .sum()
+    }
+
+    /// [`Game::perft`] split by root move (in UCI notation, sorted) for comparing
+    /// with other engines when a count is off
+    pub fn perft_divide(&self, depth: u8) -> Vec<(String, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        let mut divide: Vec<(String, u64)> = self
+            .legal_moves()
+            .iter()
+            .map(|mv| {
+                let mut next = self.clone();
+                next.apply_move(*mv).expect("legal move");
+                (mv.uci(), next.perft(depth - 1))
+            })
+            .collect();
+        divide.sort();
+        divide
+    }
+
+    /// [`Game::legal_moves`] in UCI coordinate notation (e.g. `e2e4`, `e7e8q`)
+    pub fn legal_uci_moves(&self) -> Vec<String> {
+        self.legal_moves().iter().map(Move::uci).collect()
+    }
+
+    fn has_sufficient_materials(board: &Board) -> bool {
+        // if pawn/rook/queen still around return true
+        for piece in [Piece::Pawn, Piece::Queen, Piece::Rook] {
+            if board.count(piece, true) > 0 || board.count(piece, false) > 0 {
+                return true;
+            }
+        }
+
+        let white_knights = board.count(Piece::Knight, true);
+        let black_knights = board.count(Piece::Knight, false);
+        let white_bishops = board.count(Piece::Bishop, true);
+        let black_bishops = board.count(Piece::Bishop, false);
+
+        let insufficient = matches!(
+            (white_knights, black_knights, white_bishops, black_bishops),
+            (0, 0, 0, 0)
+                | (1, 0, 0, 0)
+                | (0, 1, 0, 0)
                 | (0, 0, 1, 0)
                 | (0, 0, 0, 1)
                 | (1, 1, 0, 0)
-                | (0, 0, 1, 1)
                 | (1, 0, 0, 1)
                 | (0, 1, 1, 0)
                 | (0, 2, 0, 0)
                 | (2, 0, 0, 0)
         );
 
-        !insufficient
+        // bishops (of either side) all on one square color can never cover the
+        // king's escape squares of the other color, opposite colored bishops can mate
+        let bishops = board.white_bishops | board.black_bishops;
+        let same_colored_bishops =
+            white_knights + black_knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0);
+
+        !(insufficient || same_colored_bishops)
     }
 
-    fn update_game_status(&mut self) {
-        // check for sufficient material
-        if !Self::has_sufficient_materials(&self.board) {
-            self.status = Status::Draw;
-            return;
+    /// dead position with only kings and pawns where every pawn is blocked by an
+    /// opponent pawn, no pawn can capture and neither king can reach an undefended
+    /// opponent pawn. Nothing can ever change so the game can't be won by either side
+    fn is_blocked_pawn_wall(board: &Board) -> bool {
+        for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+            if board.count(piece, true) > 0 || board.count(piece, false) > 0 {
+                return false;
+            }
         }
 
-        let is_white = self.is_white();
-
-        let knights_moves = self.get_computed_pseudolegal_moves(Piece::Knight, is_white);
-        let rooks_moves = self.get_computed_pseudolegal_moves(Piece::Rook, is_white);
-        let bishops_moves = self.get_computed_pseudolegal_moves(Piece::Bishop, is_white);
-        let queens_moves = self.get_computed_pseudolegal_moves(Piece::Queen, is_white);
-        let pawns_moves = self.get_computed_pseudolegal_moves(Piece::Pawn, is_white);
-        let king_moves = self.get_computed_pseudolegal_moves(Piece::King, is_white);
+        let white_pawns = Self::get_pieces(board, Piece::Pawn, true);
+        let black_pawns = Self::get_pieces(board, Piece::Pawn, false);
+        if white_pawns == 0 || white_pawns << 8 != black_pawns {
+            // every white pawn needs a black pawn right in front of it (and vice versa)
+            return false;
+        }
 
-        let opponent_pieces = if is_white {
-            self.board.black_pieces
-        } else {
-            self.board.white_pieces
+        let pawn_attacks = |pawns: u64, is_white: bool| {
+            let mut attacks = 0u64;
+            let mut pawns = pawns;
+            while pawns != 0 {
+                let idx = pawns.trailing_zeros() as usize;
+                pawns &= pawns - 1;
+                attacks |= if is_white {
+                    WHITE_PAWN_MOVES[idx][1]
+                } else {
+                    BLACK_PAWN_MOVES[idx][1]
+                };
+            }
+            attacks
         };
+        let white_attacks = pawn_attacks(white_pawns, true);
+        let black_attacks = pawn_attacks(black_pawns, false);
+        if white_attacks & black_pawns != 0 || black_attacks & white_pawns != 0 {
+            return false;
+        }
+
+        // flood fill every square the king can walk to and check whether it can capture
+        // an undefended pawn from there
+        for (is_white, opponent_pawns, opponent_attacks) in [
+            (true, black_pawns, black_attacks),
+            (false, white_pawns, white_attacks),
+        ] {
+            let walkable = !(white_pawns | black_pawns) & !opponent_attacks;
+            let mut reachable = Self::get_pieces(board, Piece::King, is_white);
+            loop {
+                let mut next = reachable;
+                let mut squares = reachable;
+                while squares != 0 {
+                    next |= KING_MOVES[squares.trailing_zeros() as usize] & walkable;
+                    squares &= squares - 1;
+                }
+                if next == reachable {
+                    break;
+                }
+                reachable = next;
+            }
+
+            let mut squares = reachable;
+            while squares != 0 {
+                let capturable = KING_MOVES[squares.trailing_zeros() as usize] & opponent_pawns & !opponent_attacks;
+                if capturable != 0 {
+                    return false;
+                }
+                squares &= squares - 1;
+            }
+        }
+        true
+    }
+
+    /// Zobrist hash of the position (piece placement, side to move, castling rights
+    /// and en passant file). The same position reached by different move orders has
+    /// the same hash
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// hash computed from scratch, see [`Game::hash`] for the incremental one
+    fn compute_hash(&self) -> u64 {
+        let side = if self.is_white() { 0 } else { zobrist::KEYS.black_to_move };
+        zobrist::hash_board(&self.board)
+            ^ side
+            ^ zobrist::castling_key(self.castling_rights())
+            ^ zobrist::en_passant_key(self.en_passant_target)
+    }
+
+    /// number of times the current position has appeared in the game
+    /// (including the current occurrence). History only goes back to the last
+    /// irreversible move
+    pub fn repetition_count(&self) -> u8 {
+        match self.position_history.last() {
+            Some(current) => self
+                .position_history
+                .iter()
+                .filter(|&key| key == current)
+                .count() as u8,
+            None => 0,
+        }
+    }
+
+    /// move count, captures, checks given and castling for each side, computed
+    /// from the move history
+    pub fn statistics(&self) -> GameStats {
+        let mut stats = GameStats::default();
+        for mv in &self.history {
+            let side = if mv.is_white { &mut stats.white } else { &mut stats.black };
+            side.moves += 1;
+            side.captures += mv.is_capture as u32;
+            side.checks += mv.is_check as u32;
+            side.castled |= mv.piece == Piece::Castling;
+        }
+        stats
+    }
+
+    /// opponent pieces captured by the given side in this game, most valuable first.
+    /// A captured promoted piece counts as the piece it promoted to
+    pub fn captured_pieces(&self, by_white: bool) -> Vec<Piece> {
+        let mut pieces: Vec<Piece> = self
+            .undo_history
+            .iter()
+            .filter(|undo| undo.is_white == by_white)
+            .filter_map(|undo| undo.captured.map(|(piece, _)| piece))
+            .collect();
+        pieces.sort_by_key(|piece| std::cmp::Reverse(piece_value(*piece)));
+        pieces
+    }
+
+    /// PGN of the game: the seven tag roster (unknown values as `?`), `SetUp`/`FEN`
+    /// tags when the game didn't start from the standard position and the movetext
+    /// wrapped at 80 columns. Like the save file it holds the live game, not the
+    /// analysis moves
+    pub fn to_pgn(&self) -> String {
+        if let Some(live) = self.analysis_base.as_deref() {
+            return live.to_pgn();
+        }
+        let result = self.pgn_result();
+        let mut tags = vec![
+            ("Event", "Casual game".to_string()),
+            ("Site", "chessterm".to_string()),
+            ("Date", "????.??.??".to_string()),
+            ("Round", "-".to_string()),
+            ("White", "?".to_string()),
+            ("Black", "?".to_string()),
+            ("Result", result.to_string()),
+        ];
+        if self.chess960 {
+            tags.push(("Variant", "Chess960".to_string()));
+        }
+        let start_fen = self.start_fen();
+        if start_fen != Game::default().to_fen() {
+            tags.push(("SetUp", "1".to_string()));
+            tags.push(("FEN", start_fen));
+        }
+
+        let mut pgn: String = tags
+            .iter()
+            .map(|(name, value)| format!("[{} \"{}\"]\n", name, value))
+            .collect();
+        pgn.push('\n');
+        let mut line = String::new();
+        for token in self.pgn_movetext().split(' ') {
+            if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+        pgn
+    }
+
+    /// PGN movetext of the game (e.g. `1. e4 e5 2. Nf3 *`) followed by the result,
+    /// games started from a black to move position begin with `n...`
+    pub fn pgn_movetext(&self) -> String {
+        let first_turn = self.turn as usize - self.history.len();
+        let mut tokens = Vec::new();
+        for (i, mv) in self.history.iter().enumerate() {
+            let fullmove = (first_turn + i).div_ceil(2);
+            if mv.is_white {
+                tokens.push(format!("{}.", fullmove));
+            } else if i == 0 {
+                tokens.push(format!("{}...", fullmove));
+            }
+            tokens.push(mv.san.clone());
+        }
+        tokens.push(self.pgn_result().to_string());
+        tokens.join(" ")
+    }
+
+    /// PGN result token (`1-0`, `0-1`, `1/2-1/2` or `*` while ongoing)
+    fn pgn_result(&self) -> &'static str {
+        match self.result {
+            GameResult::Ongoing => "*",
+            GameResult::Win { white: true, .. } => "1-0",
+            GameResult::Win { white: false, .. } => "0-1",
+            GameResult::Draw { .. } => "1/2-1/2",
+        }
+    }
+
+    /// winner (if any) and the reason the game ended
+    pub fn result(&self) -> GameResult {
+        self.result
+    }
+
+    /// whether the game goes on, and how it ended otherwise (without the winner, see
+    /// [`Game::result`])
+    pub fn status(&self) -> Status {
+        match self.result {
+            GameResult::Ongoing => Status::Ongoing,
+            GameResult::Win { reason: WinReason::Checkmate, .. } => Status::Checkmate,
+            GameResult::Win { reason: WinReason::Resignation, .. } => Status::Resigned,
+            GameResult::Draw { .. } => Status::Draw,
+        }
+    }
+
+    /// the side to move resigns and the other side wins
+    pub fn resign(&mut self) -> Result<(), MoveError> {
+        let white = !self.is_white();
+        self.end_by_players(GameResult::Win { white, reason: WinReason::Resignation })
+    }
+
+    /// ends the game as a draw, for a draw offer the other player accepted
+    pub fn agree_draw(&mut self) -> Result<(), MoveError> {
+        self.end_by_players(GameResult::Draw { reason: DrawReason::Agreement })
+    }
+
+    fn end_by_players(&mut self, result: GameResult) -> Result<(), MoveError> {
+        if self.result != GameResult::Ongoing {
+            return Err(MoveError::GameOver);
+        }
+        self.result = result;
+        self.legal_moves.take();
+        Ok(())
+    }
+
+    /// FEN of the position the game started from (before the first move)
+    pub fn start_fen(&self) -> String {
+        let mut start = self.clone();
+        while start.undo().is_ok() {}
+        start.to_fen()
+    }
+
+    /// game after its first `plies` moves, rebuilt by replaying them from the start
+    /// position. `None` past the last move
+    pub fn position_after(&self, plies: usize) -> Option<Game> {
+        let moves = self.history.get(..plies)?;
+        let mut game = self.clone();
+        while game.undo().is_ok() {}
+        for mv in moves {
+            game.process_move(&mv.san).ok()?;
+        }
+        Some(game)
+    }
+
+    /// human-readable save file: the start FEN on the first line followed by one SAN
+    /// move per line. Analysis moves are not saved, the live game is
+    pub fn to_save_file(&self) -> String {
+        let live = self.analysis_base.as_deref().unwrap_or(self);
+        let mut lines = vec![live.start_fen()];
+        lines.extend(live.history.iter().map(|mv| mv.san.clone()));
+        lines.join("\n") + "\n"
+    }
+
+    /// restores a game written by [`Game::to_save_file`] by replaying its moves, so
+    /// every move is validated again
+    pub fn from_save_file(contents: &str) -> Result<Game, LoadError> {
+        let mut lines = contents.lines().map(str::trim).filter(|line| !line.is_empty());
+        let mut game = Game::from_fen(lines.next().unwrap_or_default()).map_err(LoadError::InvalidFen)?;
+        for san in lines {
+            if let Err(error) = game.process_move(san) {
+                return Err(LoadError::IllegalMove {
+                    move_number: game.turn.div_ceil(2),
+                    san: san.to_string(),
+                    error,
+                });
+            }
+        }
+        Ok(game)
+    }
+
+    fn update_game_status(&mut self) {
+        // the position changed, the move list is only generated again when asked for
+        // (a search doesn't need it for the positions it only evaluates)
+        self.legal_moves.take();
+
+        // check for sufficient material
+        if !Self::has_sufficient_materials(&self.board) || Self::is_blocked_pawn_wall(&self.board) {
+            self.result = GameResult::Draw { reason: DrawReason::InsufficientMaterial };
+            return;
+        }
 
-        let found_legal_move =
-            self.has_valid_move(Piece::Knight, knights_moves, is_white, opponent_pieces)
-                || self.has_valid_move(Piece::Rook, rooks_moves, is_white, opponent_pieces)
-                || self.has_valid_move(Piece::Bishop, bishops_moves, is_white, opponent_pieces)
-                || self.has_valid_move(Piece::Queen, queens_moves, is_white, opponent_pieces)
-                || self.has_valid_move(Piece::Pawn, pawns_moves, is_white, opponent_pieces)
-                || self.has_valid_move(Piece::King, king_moves, is_white, opponent_pieces);
+        // only the king can move in double check
+        #[cfg(test)]
+        if self.checker_count < 2 {
+            self.non_king_move_checks += 1;
+        }
+        let mut pieces = self.movable_pieces();
+        let mut found_legal_move = false;
+        while pieces != 0 && !found_legal_move {
+            found_legal_move = self.generate_destinations(1 << pieces.trailing_zeros()) != 0;
+            pieces &= pieces - 1;
+        }
 
         if found_legal_move {
-            self.status = Status::Ongoing
+            // threefold repetition and fifty-move rule end the game as a draw
+            // (no claim needed), checkmate on the last move still takes precedence
+            if self.repetition_count() >= 3 {
+                self.result = GameResult::Draw { reason: DrawReason::Repetition };
+            } else if self.halfmove_clock >= 100 {
+                self.result = GameResult::Draw { reason: DrawReason::FiftyMoveRule };
+            } else {
+                self.result = GameResult::Ongoing;
+            }
         } else {
             if self.check {
-                // check for checkmate
-                self.status = Status::Checkmate;
+                // check for checkmate, the side to move lost
+                let white = !self.is_white();
+                self.result = GameResult::Win { white, reason: WinReason::Checkmate };
             } else {
                 // check for stalemate
-                self.status = Status::Draw;
+                self.result = GameResult::Draw { reason: DrawReason::Stalemate };
             }
         }
     }
 }
 
+/// items joined for a sentence (e.g. `Nbd7, Nfd7 or Nb6d7`)
+pub fn or_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [rest @ .., last] => format!("{} or {}", rest.join(", "), last),
+    }
+}
+
+fn piece_name(piece: Piece) -> &'static str {
+    match piece {
+        Piece::Pawn => "pawn",
+        Piece::Knight => "knight",
+        Piece::Bishop => "bishop",
+        Piece::Rook => "rook",
+        Piece::Queen => "queen",
+        Piece::King => "king",
+        Piece::Castling => "king",
+    }
+}
+
 impl Default for Game {
     fn default() -> Game {
         Self::new(Board::default())
     }
 }
 
+/// state that isn't visible on the board, for diagnosis
+/// (e.g. `white to move, castling KQkq, en passant -`)
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} to move, castling {}, en passant {}",
+            if self.is_white() { "white" } else { "black" },
+            self.castling_fen(),
+            self.en_passant_fen(),
+        )
+    }
+}
+
+/// result for the player (e.g. `White wins by checkmate`, `Draw by agreement`)
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameResult::Ongoing => write!(f, "Game in progress"),
+            GameResult::Win { white, reason } => {
+                let reason = match reason {
+                    WinReason::Checkmate => "checkmate",
+                    WinReason::Resignation => "resignation",
+                };
+                write!(f, "{} wins by {}", if *white { "White" } else { "Black" }, reason)
+            }
+            GameResult::Draw { reason } => {
+                let reason = match reason {
+                    DrawReason::Stalemate => "stalemate",
+                    DrawReason::InsufficientMaterial => "insufficient material",
+                    DrawReason::FiftyMoveRule => "the fifty-move rule",
+                    DrawReason::Repetition => "threefold repetition",
+                    DrawReason::Agreement => "agreement",
+                };
+                write!(f, "Draw by {}", reason)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -1318,6 +2828,8 @@ pub mod tests {
                     "fxg3",
                     MoveError::InvalidMove(InvalidMoveReason::NoSourceOrTarget),
                 ),
+                // capture without the originating file
+                ("xg3", MoveError::ParseError),
             ],
         );
         process_moves(&mut game, &["exd4"]);
@@ -1350,8 +2862,10 @@ pub mod tests {
                 MoveError::InvalidMove(InvalidMoveReason::PawnInvalidPromotion),
             )],
         );
-        game.turn = 4; // switch to black
-                       // promotion doesn't work if not rank 1 for black
+
+        // promotion doesn't work if not rank 1 for black
+        let board = Board::from_fen("1n4n1/p3k2P/8/8/8/3pP1p1/P1p1P1P1/1N2K1N1");
+        let mut game = Game::with_side_to_move(board, false);
         process_moves_error(
             &mut game,
             &[(
@@ -1361,6 +2875,37 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_pawn_auto_promotion() {
+        let fen = "1n6/4P1k1/8/8/8/8/p7/4K3 w - - 0 1";
+        let mut game = Game::from_fen(fen).unwrap();
+        process_moves(&mut game, &["e8"]);
+        assert_eq!(bitboard_single('e', 8).unwrap(), game.board.white_queens);
+        assert_eq!(0, game.board.white_pawns);
+        assert_eq!("e8=Q", game.last_move().unwrap().san);
+
+        // capture
+        let mut game = Game::from_fen("1n6/P3k3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        process_moves(&mut game, &["axb8"]);
+        assert_eq!(bitboard_single('b', 8).unwrap(), game.board.white_queens);
+        assert_eq!("axb8=Q", game.last_move().unwrap().san);
+
+        // underpromotion suffix is still respected
+        let mut game = Game::from_fen(fen).unwrap();
+        process_moves(&mut game, &["e8=N"]);
+        assert_eq!(bitboard_single('e', 8).unwrap(), game.board.white_knights);
+
+        let mut game = Game::from_fen(fen).unwrap();
+        game.require_explicit_promotion = true;
+        process_moves_error(
+            &mut game,
+            &[("e8", MoveError::InvalidMove(InvalidMoveReason::PawnInvalidPromotion))],
+        );
+        assert_eq!(0, game.board.white_queens);
+        process_moves(&mut game, &["e8=Q"]);
+        assert_eq!(bitboard_single('e', 8).unwrap(), game.board.white_queens);
+    }
+
     #[test]
     fn test_knight() {
         let board = Board::from_fen("kn6/8/1n6/8/2P5/4pp2/4P3/K3N1N1");
@@ -1374,7 +2919,7 @@ pub mod tests {
                     "Ne2",
                     MoveError::InvalidMove(InvalidMoveReason::InvalidSourceOrTarget),
                 ),
-                ("Nxf3", MoveError::AmbiguousSource),
+                ("Nxf3", MoveError::AmbiguousSource(bitboard_single('e', 1).unwrap() | bitboard_single('g', 1).unwrap())),
                 // must capture
                 (
                     "Ngf3",
@@ -1467,6 +3012,16 @@ pub mod tests {
         );
 
         assert_eq!(bitboard_single('b', 2).unwrap(), game.detect_pins(false));
+
+        // bishop can't pin along a file and the rook behind it is blocked
+        let board = Board::from_fen("4r1k1/8/4b3/4B3/8/4K3/8/8");
+        let game = Game::new(board);
+        assert_eq!(0, game.detect_pins(true));
+
+        // opponent pawn between the king and the piece
+        let board = Board::from_fen("8/8/8/8/1R3pPk/8/8/K7");
+        let game = Game::new(board);
+        assert_eq!(0, game.detect_pins(false));
     }
 
     #[test]
@@ -1589,8 +3144,7 @@ pub mod tests {
     #[test]
     fn test_check_state() {
         let board = Board::from_fen("4k3/8/4r3/4b3/8/8/3B4/4K3");
-        let mut game = Game::new(board);
-        game.turn = 2; // black's turn
+        let mut game = Game::with_side_to_move(board, false);
         assert!(!Game::is_in_check(&game.board, game.is_white()));
         // discovered check
         process_moves(&mut game, &["Bg3"]);
@@ -1669,7 +3223,7 @@ pub mod tests {
         // black in check but not mate
         assert!(!Game::is_in_check(&game.board, true));
         assert!(Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Ongoing, game.status);
+        assert_eq!(Status::Ongoing, game.status());
         process_moves(&mut game, &["Rg8"]);
 
         // // blocked the check
@@ -1679,7 +3233,7 @@ pub mod tests {
         // black is lost
         assert!(!Game::is_in_check(&game.board, true));
         assert!(Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Checkmate, game.status);
+        assert_eq!(Status::Checkmate, game.status());
     }
 
     #[test]
@@ -1699,7 +3253,7 @@ pub mod tests {
             ],
         );
         process_moves(&mut game, &["Ke5"]);
-        assert_eq!(Status::Ongoing, game.status);
+        assert_eq!(Status::Ongoing, game.status());
     }
 
     #[test]
@@ -1711,12 +3265,12 @@ pub mod tests {
         // neither in check
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Ongoing, game.status);
+        assert_eq!(Status::Ongoing, game.status());
         process_moves(&mut game, &["Kxb2"]);
 
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Draw, game.status);
+        assert_eq!(Status::Draw, game.status());
 
         // knight and bishop
         let board = Board::from_fen("3k4/7b/8/8/8/2r5/8/K2N4");
@@ -1725,12 +3279,12 @@ pub mod tests {
         // neither in check
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Ongoing, game.status);
+        assert_eq!(Status::Ongoing, game.status());
         process_moves(&mut game, &["Nxc3"]);
 
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Draw, game.status);
+        assert_eq!(Status::Draw, game.status());
 
         // 2 knights
         let board = Board::from_fen("3k4/8/8/8/8/8/7q/K2N1N2");
@@ -1739,12 +3293,185 @@ pub mod tests {
         // neither in check
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Ongoing, game.status);
+        assert_eq!(Status::Ongoing, game.status());
         process_moves(&mut game, &["Nxh2"]);
 
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Draw, game.status);
+        assert_eq!(Status::Draw, game.status());
+    }
+
+    #[test]
+    fn test_is_square_attacked() {
+        let square = |name| parse_square(name).unwrap();
+        let game = Game::from_fen("4k3/8/8/3p2p1/8/5N2/4P3/4K2R w - - 0 1").unwrap();
+
+        // pawns attack diagonally, push squares aren't attacked
+        assert!(game.is_square_attacked(square("c4"), false));
+        assert!(game.is_square_attacked(square("e4"), false));
+        assert!(!game.is_square_attacked(square("d4"), false));
+        assert!(game.is_square_attacked(square("d3"), true));
+        assert!(!game.is_square_attacked(square("e3"), true));
+
+        // knight, king and rook (which stops at its own king)
+        assert!(game.is_square_attacked(square("d4"), true));
+        assert!(game.is_square_attacked(square("d1"), true));
+        assert!(game.is_square_attacked(square("h8"), true));
+        assert!(!game.is_square_attacked(square("c1"), true));
+        assert!(game.is_square_attacked(square("d7"), false));
+        assert!(!game.is_square_attacked(square("a1"), false));
+
+        // opponent pieces that can be captured are attacked, own pieces never are
+        assert!(game.is_square_attacked(square("g5"), true));
+        assert!(!game.is_square_attacked(square("e2"), true));
+
+        // any of several squares
+        assert!(game.is_square_attacked(square("a1") | square("c4"), false));
+    }
+
+    #[test]
+    fn test_hash() {
+        // same position through different move orders
+        let mut game1 = Game::default();
+        process_moves(&mut game1, &["e4", "e5", "Nf3", "Nc6", "Bc4"]);
+        let mut game2 = Game::default();
+        process_moves(&mut game2, &["Nf3", "Nc6", "e4", "e5", "Bc4"]);
+        assert_eq!(game1.board, game2.board);
+        assert_eq!(game1.hash(), game2.hash());
+        assert_eq!(Game::from_fen(&game1.to_fen()).unwrap().hash(), game1.hash());
+
+        // side to move, castling rights and en passant are part of the hash
+        let hash = |fen: &str| Game::from_fen(fen).unwrap().hash();
+        let start = Game::default().hash();
+        assert_ne!(start, hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1"));
+        assert_ne!(start, hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 0 1"));
+        assert_ne!(
+            hash("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1"),
+            hash("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1")
+        );
+        // clocks are not
+        assert_eq!(start, hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 10 20"));
+
+        // back to the start position after the knights return
+        let mut game = Game::default();
+        process_moves(&mut game, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+        assert_eq!(start, game.hash());
+    }
+
+    #[test]
+    fn test_captured_pieces() {
+        let mut game = Game::default();
+        assert!(game.captured_pieces(true).is_empty());
+        process_moves(&mut game, &["e4", "d5", "exd5", "Qxd5", "Nc3", "Qxa2", "Rxa2"]);
+        assert_eq!(vec![Piece::Queen, Piece::Pawn], game.captured_pieces(true));
+        assert_eq!(vec![Piece::Pawn, Piece::Pawn], game.captured_pieces(false));
+
+        game.undo().unwrap();
+        assert_eq!(vec![Piece::Pawn], game.captured_pieces(true));
+
+        // en passant and a promoted piece
+        let mut game = Game::from_fen("1r2k3/P7/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        process_moves(&mut game, &["exd6", "Kd7", "axb8=N+", "Kxd6"]);
+        assert_eq!(vec![Piece::Rook, Piece::Pawn], game.captured_pieces(true));
+        assert_eq!(vec![Piece::Pawn], game.captured_pieces(false));
+    }
+
+    #[test]
+    fn test_save_file() {
+        let mut game = Game::default();
+        process_moves(&mut game, &["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"]);
+        let saved = game.to_save_file();
+        assert_eq!(format!("{}\ne4\ne5\nQh5\nNc6\nBc4\nNf6\nQxf7#\n", Game::default().to_fen()), saved);
+        let loaded = Game::from_save_file(&saved).unwrap();
+        assert_eq!(game.to_fen(), loaded.to_fen());
+        assert_eq!(Status::Checkmate, loaded.status());
+        assert_eq!(game.history(), loaded.history());
+
+        // custom start position, analysis moves are not saved
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 3 12";
+        let mut game = Game::from_fen(fen).unwrap();
+        process_moves(&mut game, &["Kd7", "e4"]);
+        assert_eq!(fen, game.start_fen());
+        game.enter_analysis();
+        process_moves(&mut game, &["Kc6"]);
+        let loaded = Game::from_save_file(&game.to_save_file()).unwrap();
+        assert_eq!("8/3k4/8/8/4P3/8/8/4K3 b - e3 0 13", loaded.to_fen());
+
+        let err = Game::from_save_file(&format!("{}\ne4\ne5\nNf3\nKe6\n", Game::default().to_fen())).err();
+        assert!(
+            matches!(&err, Some(LoadError::IllegalMove { move_number: 2, san, .. }) if san == "Ke6"),
+            "{:?}",
+            err
+        );
+        assert_eq!(
+            Some(LoadError::InvalidFen(FenError::FieldCount)),
+            Game::from_save_file("e4\ne5\n").err()
+        );
+    }
+
+    #[test]
+    fn test_piece_at() {
+        let mut game = Game::default();
+        assert_eq!(Some((Piece::King, true)), game.piece_at(parse_square("e1").unwrap()));
+        assert_eq!(Some((Piece::Knight, false)), game.piece_at(parse_square("g8").unwrap()));
+        assert_eq!(None, game.piece_at(parse_square("e4").unwrap()));
+        assert_eq!(None, game.piece_at(0));
+        // more than one square
+        assert_eq!(None, game.piece_at(game.board.white_pawns));
+
+        process_moves(&mut game, &["e4"]);
+        assert_eq!(Some((Piece::Pawn, true)), game.piece_at(parse_square("e4").unwrap()));
+        assert_eq!(None, game.piece_at(parse_square("e2").unwrap()));
+    }
+
+    #[test]
+    fn test_draw_same_colored_bishops() {
+        // both bishops on light squares (b7 and c4) after the rook is taken
+        let board = Board::from_fen("3k4/1b6/8/8/2B5/8/r7/K7");
+        let mut game = Game::new(board);
+        assert_eq!(Status::Ongoing, game.status());
+        process_moves(&mut game, &["Kxa2"]);
+        assert_eq!(Status::Draw, game.status());
+
+        // several bishops, all on dark squares
+        let game = Game::from_fen("3k4/8/3b1b2/8/8/8/1B6/K1B5 w - - 0 1").unwrap();
+        assert_eq!(Status::Draw, game.status());
+
+        // opposite colored bishops can still mate
+        let game = Game::from_fen("3k4/2b5/8/8/2B5/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(Status::Ongoing, game.status());
+
+        // a knight next to same colored bishops can still mate
+        let game = Game::from_fen("3k4/1b6/8/8/2B5/8/8/K1N5 w - - 0 1").unwrap();
+        assert_eq!(Status::Ongoing, game.status());
+    }
+
+    #[test]
+    fn test_draw_blocked_pawn_wall() {
+        // locked pawn chain, neither king can get behind the wall
+        let game = Game::from_fen("8/3k4/8/p1p1p1p1/P1P1P1P1/8/3K4/8 w - - 0 1").unwrap();
+        assert_eq!(Status::Draw, game.status());
+
+        let game = Game::from_fen("8/3k4/8/p1p1p1p1/P1P1P1P1/8/8/3K4 b - - 0 1").unwrap();
+        assert_eq!(Status::Draw, game.status());
+
+        // h-file is open, white king can walk around and win the pawns
+        let game = Game::from_fen("8/3k4/8/p1p1p3/P1P1P3/8/3K4/8 w - - 0 1").unwrap();
+        assert_eq!(Status::Ongoing, game.status());
+
+        // pawns can still capture
+        let game = Game::from_fen("8/3k4/8/p1pp4/P1PP4/8/3K4/8 w - - 0 1").unwrap();
+        assert_eq!(Status::Ongoing, game.status());
+
+        // unblocked pawn
+        let game = Game::from_fen("8/3k4/8/p1p1p1p1/P1P1P1P1/8/3K3P/8 w - - 0 1").unwrap();
+        assert_eq!(Status::Ongoing, game.status());
+
+        // wall is only reached after the last pawn move
+        let mut game = Game::from_fen("8/3k4/8/p1p1p1p1/P1P1P3/6P1/3K4/8 w - - 0 1").unwrap();
+        assert_eq!(Status::Ongoing, game.status());
+        process_moves(&mut game, &["g4"]);
+        assert_eq!(Status::Draw, game.status());
     }
 
     #[test]
@@ -1755,12 +3482,12 @@ pub mod tests {
         // neither in check
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Ongoing, game.status);
+        assert_eq!(Status::Ongoing, game.status());
         process_moves(&mut game, &["Qg5"]);
 
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Draw, game.status);
+        assert_eq!(Status::Draw, game.status());
     }
 
     #[test]
@@ -1771,12 +3498,12 @@ pub mod tests {
         // neither in check
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Ongoing, game.status);
+        assert_eq!(Status::Ongoing, game.status());
         process_moves(&mut game, &["Qg6"]);
 
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Draw, game.status);
+        assert_eq!(Status::Draw, game.status());
     }
 
     #[test]
@@ -1785,12 +3512,12 @@ pub mod tests {
         let mut game = Game::new(board);
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Ongoing, game.status);
+        assert_eq!(Status::Ongoing, game.status());
         process_moves(&mut game, &["h7"]);
 
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Draw, game.status);
+        assert_eq!(Status::Draw, game.status());
     }
 
     #[test]
@@ -1799,12 +3526,12 @@ pub mod tests {
         let mut game = Game::new(board);
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Ongoing, game.status);
+        assert_eq!(Status::Ongoing, game.status());
         process_moves(&mut game, &["Ne6"]);
 
         assert!(!Game::is_in_check(&game.board, true));
         assert!(!Game::is_in_check(&game.board, false));
-        assert_eq!(Status::Draw, game.status);
+        assert_eq!(Status::Draw, game.status());
     }
 
     #[test]
@@ -1949,6 +3676,24 @@ pub mod tests {
         assert_eq!(0, game.en_passant_target);
     }
 
+    #[test]
+    fn test_en_passant_capture_removes_pawn() {
+        let board = Board::from_fen("4k3/3p4/8/4P3/8/8/8/4K3");
+        let mut game = Game::new(board);
+        process_moves(&mut game, &["Kd2", "d5", "exd6"]);
+
+        assert_eq!(0, game.board.black_pawns);
+        assert_eq!(bitboard_single('d', 6).unwrap(), game.board.white_pawns);
+        assert_eq!(0, game.en_passant_target);
+
+        // black captures en passant as well
+        let board = Board::from_fen("4k3/8/8/8/3p4/8/4P3/4K3");
+        let mut game = Game::new(board);
+        process_moves(&mut game, &["e4", "dxe3"]);
+        assert_eq!(0, game.board.white_pawns);
+        assert_eq!(bitboard_single('e', 3).unwrap(), game.board.black_pawns);
+    }
+
     #[test]
     fn test_en_passant() {
         let board = Board::from_fen("7k/p1pp2r1/8/5P2/BP2P3/8/8/4K3");
@@ -1983,55 +3728,1358 @@ pub mod tests {
     }
 
     #[test]
-    fn test_short_game() {
-        let mut game = Game::default();
-        process_moves(&mut game, &["e4", "e5", "Bc4", "Nc6", "Qh5", "Nd4", "Qxf7"]);
-        assert_eq!(Status::Checkmate, game.status);
+    fn test_kings_not_adjacent() {
+        let board = Board::from_fen("8/8/8/4k3/8/4K3/8/8");
+        // the black king attacks its neighbouring squares even though it can't move there
+        let between = PositionBuilder::new().add_piece('d', 4).add_piece('e', 4).add_piece('f', 4).build();
+        assert_eq!(between, board.black_attack_moves & board.white_attack_moves);
+        let game = Game::new(board);
+        let destinations = game.generate_legal_moves().iter().fold(0, |destinations, mv| destinations | mv.to);
+        assert_eq!(5, destinations.count_ones());
+        assert_eq!(0, destinations & between);
+
+        // bare kings are a draw, rooks away from the kings keep the game going
+        let mut game = Game::new(Board::from_fen("7r/8/8/4k3/8/4K3/8/R7"));
+        process_moves_error(&mut game, &[("Ke4", MoveError::Checked), ("Kd4", MoveError::Checked), ("Kf4", MoveError::Checked)]);
+        assert!(game.legal_uci_moves().iter().all(|mv| !mv.starts_with("e3") || !mv.ends_with('4')));
+        process_moves(&mut game, &["Kd3", "Rh7"]);
+        process_moves_error(&mut game, &[("Kd4", MoveError::Checked), ("Ke4", MoveError::Checked)]);
+
+        // nor by capturing a piece next to the other king
+        let board = Board::from_fen("7r/8/8/4k3/3p4/4K3/8/8");
+        let mut game = Game::new(board);
+        process_moves_error(&mut game, &[("Kxd4", MoveError::Checked)]);
     }
 
     #[test]
-    fn test_longer_game() {
-        let mut game = Game::default();
-        // reference: https://www.chess.com/game/computer/214517513
-        process_moves(
-            &mut game,
-            &[
-                "b4", "Nf6", "Bb2", "e6", "a3", "d5", "e3", "Be7", "c4", "dxc4", "Bxc4", "Rf8",
-                "Nf3", "c6", "Nc3", "Bd7", "d4", "Ng4", "h3", "Nf6", "O-O", "h6", "e4", "c5",
-                "dxc5", "Nc6", "e5", "Nh7", "Ne4", "Nxb4", "axb4", "Qc7", "Re1", "b5", "Bb3",
-                "Rc8", "Nd4", "Qxe5", "Qd2", "f5", "Nc6", "Qxe4", "Rxe4", "fxe4", "Bxg7", "Bxc6",
-                "Bxf8", "Nxf8", "Qxh6", "Bd5", // Qh5+
-                "Qh5", "Kd8", "Rd1", "Bxc5", "bxc5", "Rxc5", // Qg5+
-                "Qg5", "Ke8", "f3", "exf3", "Bxd5", "Rxd5", "Rxd5", "exd5", "Qxd5", "fxg2", "Kxg2",
-                "b4", // Qe4+
-                "Qe4", "Kd8", "Qxb4", "a6", // Qa5+
-                "Qa5", "Ke8", "Qxa6", "Nh7", // Qg6+
-                "Qg6", "Ke7", // Qxh7+
-                "Qxh7", "Kf6", "Kg3", "Ke5", "Kg4", "Kd5", // Qf5+
-                "Qf5", "Kd6", "Kg5", "Kc7", "Kf6", "Kd8", "Ke6", "Kc7", // Qe5+
-                "Qe5", "Kc8", // Qc5+
-                "Qc5", "Kb7", "Kd7", "Ka6", "Qd5", "Kb6", "Kd6", "Ka6", "Kc6", "Ka7", "Kc7", "Ka6",
-                // Qc6+
-                "Qc6", "Ka5", // Qc5+
-                "Qc5", "Ka4", // Qc6+
-                "Qc6", "Kb4", "Kb6", "Kb3", "Kb5", "Kb2", "Qc4", "Ka3", "Kc5", "Kb2", "Kb4", "Kb1",
-                "Kb3", "Ka1", "Qc1",
-            ],
-        );
-        assert_eq!(Status::Checkmate, game.status);
-        process_moves_error(&mut game, &[("Ka2", MoveError::GameOver)]);
+    fn test_en_passant_discovered_check() {
+        // both pawns leave the fifth rank, exposing the king to the rook
+        let mut game = Game::from_fen("8/8/8/K2pP2r/8/8/8/k7 w - d6 0 1").unwrap();
+        process_moves_error(&mut game, &[("exd6", MoveError::Checked)]);
+        assert!(game.apply_uci("e5d6").is_err());
+        assert_eq!(0, game.legal_destinations(bitboard_single('e', 5).unwrap()) & game.en_passant_target);
+        process_moves(&mut game, &["e6"]);
+
+        // same for black, and allowed once the rook doesn't see the king
+        let mut game = Game::from_fen("8/8/8/8/k2Pp2R/8/8/7K b - d3 0 1").unwrap();
+        process_moves_error(&mut game, &[("exd3", MoveError::Checked)]);
+        let mut game = Game::from_fen("8/8/8/8/k1nPp2R/8/8/7K b - d3 0 1").unwrap();
+        process_moves(&mut game, &["exd3"]);
+
+        // capturing the checking pawn en passant resolves the check
+        let mut game = Game::from_fen("8/8/8/3pP3/4K3/8/8/k7 w - d6 0 1").unwrap();
+        assert!(game.check);
+        process_moves(&mut game, &["exd6"]);
     }
 
     #[test]
-    fn test_stalemate_game() {
-        let mut game = Game::default();
-        // reference: https://lichess.org/zV6oGNVz
-        process_moves(
-            &mut game,
-            &[
-                "e4", "e5", "Nf3", "h5", "Nxe5", "f6", "Nf3", "Ne7", "Bc4", "d5", "exd5", "Nxd5",
-                "O-O", "Nf4", "d4", "Rh6", "Bxf4", "Bd6", // Bxh2+
-                "Bxh6", "Bxh2", "Nxh2", "Qxd4", "Qxd4", "Nc6", // Qe4+
+    fn test_legal_destinations() {
+        // pinned knight can't move at all
+        let board = Board::from_fen("4k3/8/4n3/8/8/8/4R3/4K3");
+        let game = Game::with_side_to_move(board, false);
+        assert_eq!(0, game.legal_destinations(bitboard_single('e', 6).unwrap()));
+
+        // free queen, blocked by own king and capturing the opponent rook
+        let board = Board::from_fen("3k4/8/r7/8/8/8/8/Q3K3");
+        let game = Game::new(board);
+        let expected = PositionBuilder::new()
+            .add_piece('a', 2)
+            .add_piece('a', 3)
+            .add_piece('a', 4)
+            .add_piece('a', 5)
+            .add_piece('a', 6)
+            .add_piece('b', 2)
+            .add_piece('c', 3)
+            .add_piece('d', 4)
+            .add_piece('e', 5)
+            .add_piece('f', 6)
+            .add_piece('g', 7)
+            .add_piece('h', 8)
+            .add_piece('b', 1)
+            .add_piece('c', 1)
+            .add_piece('d', 1)
+            .build();
+        assert_eq!(expected, game.legal_destinations(bitboard_single('a', 1).unwrap()));
+
+        // not the side to move or empty square
+        assert_eq!(0, game.legal_destinations(bitboard_single('d', 8).unwrap()));
+        assert_eq!(0, game.legal_destinations(bitboard_single('d', 4).unwrap()));
+    }
+
+    #[test]
+    fn test_legal_destinations_check() {
+        // only moves resolving the check (block or king escape) are legal
+        let board = Board::from_fen("4r2k/8/8/8/2B5/8/3P1P2/1N2K3");
+        let mut game = Game::new(board);
+        game.update_check_state();
+        assert_eq!(0, game.legal_destinations(bitboard_single('d', 2).unwrap()));
+        assert_eq!(0, game.legal_destinations(bitboard_single('b', 1).unwrap()));
+        assert_eq!(
+            PositionBuilder::new().add_piece('e', 2).add_piece('e', 6).build(),
+            game.legal_destinations(bitboard_single('c', 4).unwrap())
+        );
+        assert_eq!(
+            PositionBuilder::new().add_piece('d', 1).add_piece('f', 1).build(),
+            game.legal_destinations(bitboard_single('e', 1).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_legal_destinations_special_moves() {
+        // castling both sides
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R");
+        let game = Game::new(board);
+        let king_moves = game.legal_destinations(bitboard_single('e', 1).unwrap());
+        assert_ne!(0, king_moves & bitboard_single('g', 1).unwrap());
+        assert_ne!(0, king_moves & bitboard_single('c', 1).unwrap());
+
+        // pawn pushes, capture and en passant
+        let board = Board::from_fen("4k3/3p4/2n5/4P3/8/8/1P6/4K3");
+        let mut game = Game::new(board);
+        assert_eq!(
+            PositionBuilder::new().add_piece('b', 3).add_piece('b', 4).build(),
+            game.legal_destinations(bitboard_single('b', 2).unwrap())
+        );
+        process_moves(&mut game, &["Kf1", "d5"]);
+        assert_eq!(
+            PositionBuilder::new().add_piece('e', 6).add_piece('d', 6).build(),
+            game.legal_destinations(bitboard_single('e', 5).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_board_mailbox_in_sync() {
+        fn assert_mailbox(board: &Board) {
+            for idx in 0..64 {
+                let position = 1u64 << idx;
+                let mut expected = None;
+                for is_white in [true, false] {
+                    for piece in [Piece::Pawn, Piece::Knight, Piece::Rook, Piece::Bishop, Piece::Queen, Piece::King] {
+                        if board.get_pieces(piece, is_white) & position != 0 {
+                            expected = Some((piece, is_white));
+                        }
+                    }
+                }
+                assert_eq!(expected, board.get_piece_type_at(position), "square {}", idx);
+            }
+            assert_eq!(board.occupied.count_ones() as usize, board.pieces().count());
+        }
+
+        let mut game = Game::default();
+        for mv in ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "O-O", "Nxe4", "Bxf7", "Kxf7", "d4", "exd4", "Ng5", "Kg8", "Qf3", "Qxg5"] {
+            process_moves(&mut game, &[mv]);
+            assert_mailbox(&game.board);
+        }
+
+        // promotions with and without capture
+        let board = Board::from_fen("1n6/P3k3/8/8/8/8/7p/4K1N1");
+        let mut game = Game::new(board);
+        for mv in ["axb8=Q", "hxg1=N", "Qb5", "Nh3"] {
+            process_moves(&mut game, &[mv]);
+            assert_mailbox(&game.board);
+        }
+        assert_eq!(
+            Some((Piece::Knight, false)),
+            game.board.get_piece_type_at(bitboard_single('h', 3).unwrap())
+        );
+        assert_eq!(
+            Some((Piece::Queen, true)),
+            game.board.get_piece_type_at(bitboard_single('b', 5).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_with_side_to_move() {
+        let mut game = Game::with_side_to_move(Board::default(), false);
+        process_moves_error(
+            &mut game,
+            &[("e4", MoveError::InvalidMove(InvalidMoveReason::NoSourceOrTarget))],
+        );
+        process_moves(&mut game, &["e5"]);
+        assert!(game.history[0].san == "e5" && !game.history[0].is_white);
+        process_moves(&mut game, &["e4"]);
+        assert!(game.history[1].is_white);
+
+        // white to move is the same as Game::new
+        let game = Game::with_side_to_move(Board::default(), true);
+        assert_eq!(1, game.turn);
+        assert_eq!(Game::default().hash(), game.hash());
+    }
+
+    #[test]
+    fn test_castling_rights_label() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R");
+        let mut game = Game::new(board);
+        assert_eq!("K Q", game.castling_rights_label(true));
+        assert_eq!("K Q", game.castling_rights_label(false));
+
+        process_moves(&mut game, &["Ke2", "Rh7"]);
+        assert_eq!("-", game.castling_rights_label(true));
+        assert_eq!("Q", game.castling_rights_label(false));
+
+        process_moves(&mut game, &["Ke1", "Rb8"]);
+        assert_eq!("-", game.castling_rights_label(true));
+        assert_eq!("-", game.castling_rights_label(false));
+    }
+
+    #[test]
+    fn test_fen() {
+        let mut game = Game::default();
+        assert_eq!("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", game.to_fen());
+
+        process_moves(&mut game, &["e4"]);
+        assert_eq!("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", game.to_fen());
+
+        process_moves(&mut game, &["c5", "Nf3"]);
+        assert_eq!("rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2", game.to_fen());
+
+        process_moves(&mut game, &["Nc6", "Ke2"]);
+        assert_eq!("r1bqkbnr/pp1ppppp/2n5/2p5/4P3/5N2/PPPPKPPP/RNBQ1B1R b kq - 3 3", game.to_fen());
+    }
+
+    #[test]
+    fn test_fen_round_trip() {
+        let mut game = Game::default();
+        process_moves(&mut game, &["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "O-O", "Nxe4", "Re1", "d5"]);
+
+        let restored = Game::from_fen(&game.to_fen()).unwrap();
+        assert_eq!(game.to_fen(), restored.to_fen());
+        assert_eq!(game.board, restored.board);
+        assert_eq!(game.turn, restored.turn);
+        assert_eq!(game.castling_rights(), restored.castling_rights());
+        assert_eq!(game.en_passant_target, restored.en_passant_target);
+        assert_eq!(game.halfmove_clock, restored.halfmove_clock);
+        assert_eq!(game.check, restored.check);
+        assert_eq!(game.pinned_white, restored.pinned_white);
+        assert_eq!(game.pinned_black, restored.pinned_black);
+        assert_eq!(game.status(), restored.status());
+        assert_eq!(game.hash(), restored.hash());
+
+        // halfmove clock and fullmove number are optional
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 b Q -").unwrap();
+        assert_eq!("4k3/8/8/8/8/8/8/R3K3 b Q - 0 1", game.to_fen());
+    }
+
+    #[test]
+    fn test_fen_round_trip_known_positions() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 b - - 99 250",
+        ] {
+            assert_eq!(fen, Game::from_fen(fen).unwrap().to_fen());
+        }
+    }
+
+    #[test]
+    fn test_from_fen_fields() {
+        let mut game = Game::from_fen("r3k3/8/8/3pP3/8/8/8/4K2R w Kq d6 3 20").unwrap();
+        assert!(game.is_white());
+        assert_eq!(39, game.turn);
+        assert_eq!((true, false, false, true), game.castling_rights());
+        assert_eq!(bitboard_single('d', 6).unwrap(), game.en_passant_target);
+        assert_eq!(3, game.halfmove_clock);
+
+        // en passant target is playable
+        process_moves(&mut game, &["exd6"]);
+        assert_eq!(0, game.board.black_pawns);
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert!(!game.is_white());
+        assert_eq!((false, false, false, false), game.castling_rights());
+        assert_eq!(0, game.en_passant_target);
+    }
+
+    #[test]
+    fn test_from_fen_error() {
+        let errors = [
+            ("", FenError::FieldCount),
+            ("8/8/8/8/8/8/8/8 w - - 0", FenError::FieldCount),
+            ("4k3/8/8/8/8/8/8/4K3/8 w - - 0 1", FenError::Placement),
+            ("4k3/8/8/8/8/8/8/4K2 w - - 0 1", FenError::Placement),
+            ("4k3/8/8/8/8/8/8/4K4 w - - 0 1", FenError::Placement),
+            ("4k3/8/8/8/8/8/8/4X3 w - - 0 1", FenError::Placement),
+            ("8/8/8/8/8/8/8/4K3 w - - 0 1", FenError::Placement),
+            ("4k3/8/8/8/8/8/8/4K3 x - - 0 1", FenError::SideToMove),
+            ("4k3/8/8/8/8/8/8/4K3 w KX - 0 1", FenError::Castling),
+            ("4k3/8/8/8/8/8/8/4K3 w - e4 0 1", FenError::EnPassant),
+            ("4k3/8/8/8/8/8/8/4K3 w - e3 0 1", FenError::EnPassant),
+            ("4k3/8/8/8/8/8/8/4K3 w - - x 1", FenError::HalfmoveClock),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 0", FenError::FullmoveNumber),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 32768", FenError::FullmoveNumber),
+        ];
+        for (fen, error) in errors {
+            assert_eq!(Err(error), Game::from_fen(fen).map(|_| ()), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_draw_threefold_repetition() {
+        let mut game = Game::default();
+        process_moves(&mut game, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+        assert_eq!(2, game.repetition_count());
+        assert_eq!(Status::Ongoing, game.status());
+
+        // third occurrence of the start position
+        process_moves(&mut game, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+        assert_eq!(3, game.repetition_count());
+        assert_eq!(Status::Draw, game.status());
+        process_moves_error(&mut game, &[("Nf3", MoveError::GameOver)]);
+
+        // king shuffle
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        for _ in 0..2 {
+            process_moves(&mut game, &["Kd1", "Kd8", "Ke1", "Ke8"]);
+        }
+        assert_eq!(Status::Draw, game.status());
+
+        // same placement with different castling rights is a different position
+        let mut game = Game::from_fen("r3k3/8/8/8/8/8/8/R3K3 w Qq - 0 1").unwrap();
+        process_moves(&mut game, &["Ra2", "Ra7", "Ra1", "Ra8"]);
+        assert_eq!(1, game.repetition_count());
+        process_moves(&mut game, &["Ra2", "Ra7", "Ra1", "Ra8"]);
+        assert_eq!(2, game.repetition_count());
+        assert_eq!(Status::Ongoing, game.status());
+    }
+
+    #[test]
+    fn test_draw_fifty_move_rule() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 98 90").unwrap();
+        process_moves(&mut game, &["Ra2"]);
+        assert_eq!(Status::Ongoing, game.status());
+        process_moves(&mut game, &["Kd7"]);
+        assert_eq!(100, game.halfmove_clock);
+        assert_eq!(Status::Draw, game.status());
+
+        // pawn move resets the clock
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 90").unwrap();
+        process_moves(&mut game, &["e4"]);
+        assert_eq!(0, game.halfmove_clock);
+        assert_eq!(Status::Ongoing, game.status());
+
+        // checkmate on the 100th halfmove is still checkmate
+        let mut game = Game::from_fen("7k/8/6K1/8/8/8/8/R7 w - - 99 90").unwrap();
+        process_moves(&mut game, &["Ra8"]);
+        assert_eq!(Status::Checkmate, game.status());
+
+        // 50 knight moves each without capture or threefold repetition
+        let mut game = Game::from_fen("rn2k3/8/8/8/8/8/8/RN2K3 w - - 0 1").unwrap();
+        for _ in 0..100 {
+            assert_eq!(Status::Ongoing, game.status());
+            let next = game.legal_uci_moves().iter().find_map(|mv| {
+                let mut next = game.clone();
+                let move_info = next.apply_uci(mv).ok()?;
+                let is_quiet = move_info.piece == Piece::Knight && !move_info.is_capture && !move_info.is_check;
+                (is_quiet && next.repetition_count() < 3).then_some(next)
+            });
+            game = next.expect("quiet knight move");
+        }
+        assert_eq!(100, game.halfmove_clock);
+        assert_eq!(Status::Draw, game.status());
+        assert_eq!(GameResult::Draw { reason: DrawReason::FiftyMoveRule }, game.result());
+    }
+
+    #[test]
+    fn test_game_result() {
+        let mut game = Game::default();
+        assert_eq!(GameResult::Ongoing, game.result());
+        process_moves(&mut game, &["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7"]);
+        assert_eq!(GameResult::Win { white: true, reason: WinReason::Checkmate }, game.result());
+        assert_eq!("White wins by checkmate", game.result().to_string());
+        assert_eq!(Status::Checkmate, game.status());
+        let mut game = Game::default();
+        process_moves(&mut game, &["f3", "e5", "g4", "Qh4"]);
+        assert_eq!(GameResult::Win { white: false, reason: WinReason::Checkmate }, game.result());
+        assert_eq!("0-1", game.pgn_movetext().rsplit(' ').next().unwrap());
+
+        // black to move resigns
+        let mut game = Game::default();
+        process_moves(&mut game, &["e4"]);
+        game.resign().unwrap();
+        assert_eq!(Status::Resigned, game.status());
+        assert_eq!(GameResult::Win { white: true, reason: WinReason::Resignation }, game.result());
+        assert!(game.legal_moves().is_empty());
+        process_moves_error(&mut game, &[("e5", MoveError::GameOver)]);
+        assert_eq!(Err(MoveError::GameOver), game.agree_draw());
+        assert!(game.pgn_movetext().ends_with("1-0"));
+        // taking back the move takes back the resignation as well
+        game.undo().unwrap();
+        assert_eq!(GameResult::Ongoing, game.result());
+        game.resign().unwrap();
+        assert_eq!("Black wins by resignation", game.result().to_string());
+
+        let mut game = Game::default();
+        game.agree_draw().unwrap();
+        assert_eq!(GameResult::Draw { reason: DrawReason::Agreement }, game.result());
+        assert_eq!("1/2-1/2", game.pgn_movetext());
+
+        let game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(GameResult::Draw { reason: DrawReason::Stalemate }, game.result());
+        let game = Game::from_fen("7k/8/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(GameResult::Draw { reason: DrawReason::InsufficientMaterial }, game.result());
+
+        let mut game = Game::default();
+        process_moves(&mut game, &["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8"]);
+        assert_eq!(GameResult::Draw { reason: DrawReason::Repetition }, game.result());
+        assert_eq!("Draw by threefold repetition", game.result().to_string());
+    }
+
+    #[test]
+    fn test_set_position() {
+        let mut game = Game::default();
+        process_moves(&mut game, &["e4", "e5", "Ke2"]);
+        assert_eq!("-", game.castling_rights_label(true));
+
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        game.set_position(fen).unwrap();
+        assert_eq!(fen, game.to_fen());
+        assert_eq!(None, game.last_move());
+        assert_eq!(1, game.repetition_count());
+        assert_eq!(GameStats::default(), game.statistics());
+
+        process_moves(&mut game, &["O-O"]);
+        assert_eq!("-", game.castling_rights_label(true));
+
+        // nothing from the previous positions leaks into the new one
+        let fen = "4k3/8/8/8/4p3/8/3P4/4K3 b - d3 5 40";
+        game.set_position(fen).unwrap();
+        assert_eq!(fen, game.to_fen());
+        assert_eq!("-", game.castling_rights_label(true));
+        assert_eq!("-", game.castling_rights_label(false));
+        assert_eq!(None, game.last_move());
+        assert_eq!(Status::Ongoing, game.status());
+        assert!(!game.check);
+
+        // invalid fen keeps the current position
+        assert_eq!(Err(FenError::SideToMove), game.set_position("4k3/8/8/8/8/8/8/4K3 x - - 0 1"));
+        assert_eq!(fen, game.to_fen());
+    }
+
+    #[test]
+    fn test_from_uci_moves() {
+        let game = Game::from_uci_moves(
+            None,
+            &["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "e1g1", "f6e4"],
+        )
+        .unwrap();
+        assert_eq!(
+            "r1bqkb1r/pppp1ppp/2n5/4p3/2B1n3/5N2/PPPP1PPP/RNBQ1RK1 w kq - 0 5",
+            game.to_fen()
+        );
+        let san: Vec<&str> = game.history.iter().map(|mv| mv.san.as_str()).collect();
+        assert_eq!(vec!["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "O-O", "Nxe4"], san);
+
+        // disambiguation and promotion
+        let game = Game::from_uci_moves(Some("4k3/P7/8/8/8/8/4K3/R6R w - - 0 1"), &["a1d1", "e8f7", "a7a8n"]).unwrap();
+        assert_eq!("N7/5k2/8/8/8/8/4K3/3R3R b - - 0 2", game.to_fen());
+        let san: Vec<&str> = game.history.iter().map(|mv| mv.san.as_str()).collect();
+        assert_eq!(vec!["Rad1", "Kf7", "a8=N"], san);
+
+        assert_eq!(
+            Err(MoveError::InvalidMove(InvalidMoveReason::InvalidSourceOrTarget)),
+            Game::from_uci_moves(None, &["e2e5"]).map(|_| ())
+        );
+        assert_eq!(Err(MoveError::ParseError), Game::from_uci_moves(None, &["e2"]).map(|_| ()));
+        assert_eq!(Err(MoveError::ParseError), Game::from_uci_moves(Some("8/8 w"), &[]).map(|_| ()));
+    }
+
+    #[test]
+    fn test_legal_uci_moves() {
+        assert_eq!(20, Game::default().legal_uci_moves().len());
+
+        let game = Game::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut moves = game.legal_uci_moves();
+        moves.sort();
+        assert_eq!(
+            vec!["a7a8b", "a7a8n", "a7a8q", "a7a8r", "e1d1", "e1d2", "e1e2", "e1f1", "e1f2"],
+            moves
+        );
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let square = |name| parse_square(name).unwrap();
+        assert_eq!(20, Game::default().legal_moves().len());
+
+        // castling both sides
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        let moves = game.legal_moves();
+        for to in ["g1", "c1"] {
+            assert!(moves.contains(&Move { from: square("e1"), to: square(to), promotion: None }));
+        }
+
+        // en passant
+        let game = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert!(game
+            .legal_moves()
+            .contains(&Move { from: square("e5"), to: square("d6"), promotion: None }));
+
+        // pinned knight can't move, promotions for every piece
+        let game = Game::from_fen("4k3/P7/8/8/4r3/8/4N3/4K3 w - - 0 1").unwrap();
+        let moves = game.legal_moves();
+        assert!(moves.iter().all(|mv| mv.from != square("e2")));
+        let promotions: Vec<Option<Piece>> = moves
+            .iter()
+            .filter(|mv| mv.from == square("a7"))
+            .map(|mv| mv.promotion)
+            .collect();
+        assert_eq!(
+            vec![Some(Piece::Queen), Some(Piece::Rook), Some(Piece::Bishop), Some(Piece::Knight)],
+            promotions
+        );
+
+        // checkmated
+        let game = Game::from_fen("7k/8/8/8/8/8/6qr/7K w - - 0 1").unwrap();
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_legal_moves_cache() {
+        let assert_fresh = |game: &Game| assert_eq!(game.generate_legal_moves(), game.legal_moves());
+
+        let mut game = Game::from_fen("r3k2r/8/8/8/1p6/8/P7/R3K2R w KQkq - 0 1").unwrap();
+        assert_fresh(&game);
+        // en passant, castling on both sides, then a quiet move
+        for mv in ["a4", "bxa3", "O-O", "O-O-O", "Rf7"] {
+            game.process_move(mv).unwrap();
+            assert_fresh(&game);
+        }
+        for _ in 0..5 {
+            game.undo().unwrap();
+            assert_fresh(&game);
+        }
+        // filled before the move, generated again after it
+        game.process_move("a4").unwrap();
+        assert!(game
+            .legal_moves()
+            .contains(&Move { from: parse_square("b4").unwrap(), to: parse_square("a3").unwrap(), promotion: None }));
+
+        // moving for the other side in analysis mode
+        game.enter_analysis();
+        game.process_move("Kf1").unwrap();
+        assert!(!game.is_white());
+        assert_fresh(&game);
+        game.exit_analysis();
+        assert_fresh(&game);
+
+        let game = Game::with_side_to_move(Board::from_fen("4k3/8/8/8/8/8/8/R3K3"), false);
+        assert_fresh(&game);
+        assert_eq!(5, game.legal_uci_moves().len());
+    }
+
+    #[test]
+    fn test_apply_move() {
+        let mv = |from, to, promotion| Move {
+            from: parse_square(from).unwrap(),
+            to: parse_square(to).unwrap(),
+            promotion,
+        };
+
+        let mut game = Game::default();
+        assert_eq!("e4", game.apply_move(mv("e2", "e4", None)).unwrap().san);
+        assert_eq!(
+            Err(MoveError::InvalidMove(InvalidMoveReason::NoSourceOrTarget)),
+            game.apply_move(mv("e4", "e5", None))
+        );
+
+        // castling, en passant and promotion
+        let mut game = Game::from_fen("4k3/1P6/8/3pP3/8/8/8/R3K2R w KQ d6 0 1").unwrap();
+        assert_eq!("exd6", game.clone().apply_move(mv("e5", "d6", None)).unwrap().san);
+        assert_eq!("O-O-O", game.clone().apply_move(mv("e1", "c1", None)).unwrap().san);
+        assert_eq!("b8=N", game.clone().apply_move(mv("b7", "b8", Some(Piece::Knight))).unwrap().san);
+        assert_eq!("O-O", game.apply_move(mv("e1", "g1", None)).unwrap().san);
+        assert_eq!("4k3/1P6/8/3pP3/8/8/8/R4RK1 b - - 1 1", game.to_fen());
+
+        // pinned piece and squares the piece can't reach
+        let mut game = Game::from_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        let invalid = Err(MoveError::InvalidMove(InvalidMoveReason::InvalidSourceOrTarget));
+        assert_eq!(invalid, game.apply_move(mv("e2", "c3", None)));
+        let mut game = Game::default();
+        assert_eq!(invalid, game.apply_move(mv("e2", "d3", None)));
+        assert_eq!(invalid, game.apply_move(mv("g1", "g3", None)));
+        assert_eq!(Game::default().to_fen(), game.to_fen());
+        assert!(game.history().is_empty());
+
+        // promotion to a piece other than Q, R, B or N, or a missing promotion piece
+        let mut game = Game::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let invalid_promotion = Err(MoveError::InvalidMove(InvalidMoveReason::PawnInvalidPromotion));
+        assert_eq!(invalid_promotion, game.apply_move(mv("a7", "a8", Some(Piece::King))));
+        assert_eq!(invalid_promotion, game.apply_move(mv("a7", "a8", Some(Piece::Pawn))));
+        assert_eq!(invalid_promotion, game.apply_move(mv("a7", "a8", None)));
+        assert_eq!(invalid_promotion, game.apply_uci("a7a8"));
+        assert!(game.history().is_empty());
+        assert_eq!("a8=R+", game.apply_move(mv("a7", "a8", Some(Piece::Rook))).unwrap().san);
+
+        // promotion piece on a move that doesn't promote
+        let mut game = Game::default();
+        assert_eq!(invalid_promotion, game.apply_move(mv("g1", "f3", Some(Piece::Queen))));
+        assert_eq!(invalid_promotion, game.apply_uci("e2e4q"));
+        assert_eq!(Game::default().to_fen(), game.to_fen());
+        assert_eq!("Nf3", game.apply_uci("g1f3").unwrap().san);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut game = Game::new(Board::default());
+        game.require_explicit_promotion = true;
+        process_moves(&mut game, &["e4", "d5", "Bb5+", "c6", "e5", "f5"]);
+        assert_ne!(0, game.en_passant_target);
+        game.enter_analysis();
+        process_moves(&mut game, &["exf6"]);
+        game.reset();
+
+        assert_eq!(Board::default(), game.board);
+        assert_eq!(1, game.turn);
+        assert_eq!(Game::default().to_fen(), game.to_fen());
+        assert_eq!(Game::default().hash(), game.hash());
+        assert!(!game.check && !game.is_analysis());
+        assert_eq!((0, 0, 0), (game.pinned_white, game.pinned_black, game.en_passant_target));
+        assert!(game.history().is_empty());
+        assert_eq!(Err(MoveError::NothingToUndo), game.undo());
+        assert_eq!(GameResult::Ongoing, game.result());
+        assert_eq!(20, game.legal_moves().len());
+        assert!(game.require_explicit_promotion);
+
+        // a finished game can be played again
+        process_moves(&mut game, &["f3", "e5", "g4", "Qh4#"]);
+        assert_eq!(Status::Checkmate, game.status());
+        game.reset();
+        assert_eq!(Status::Ongoing, game.status());
+        process_moves(&mut game, &["f3", "e5", "g4"]);
+        assert_eq!(1, game.repetition_count());
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut game = Game::default();
+        assert_eq!(Err(MoveError::NothingToUndo), game.undo());
+
+        let start = game.to_fen();
+        process_moves(&mut game, &["e4", "d5", "exd5"]);
+        assert_eq!(Ok(()), game.undo());
+        assert_eq!("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", game.to_fen());
+        assert_eq!(2, game.history().len());
+        process_moves(&mut game, &["exd5"]);
+        for _ in 0..3 {
+            game.undo().unwrap();
+        }
+        assert_eq!(start, game.to_fen());
+        assert!(game.history().is_empty());
+
+        // promotion with capture restores the pawn and the captured piece
+        let fen = "1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        let mut game = Game::from_fen(fen).unwrap();
+        process_moves(&mut game, &["axb8=N"]);
+        game.undo().unwrap();
+        assert_eq!(fen, game.to_fen());
+        assert_eq!(Game::from_fen(fen).unwrap().board, game.board);
+
+        // castling moves the king and the rook back and restores the rights
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let mut game = Game::from_fen(fen).unwrap();
+        process_moves(&mut game, &["O-O", "O-O-O"]);
+        game.undo().unwrap();
+        game.undo().unwrap();
+        assert_eq!(fen, game.to_fen());
+
+        // en passant resurrects the captured pawn
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+        let mut game = Game::from_fen(fen).unwrap();
+        process_moves(&mut game, &["exd6"]);
+        game.undo().unwrap();
+        assert_eq!(fen, game.to_fen());
+        assert_eq!(bitboard_single('d', 5).unwrap(), game.board.black_pawns);
+
+        // game over and repetition history are restored
+        let mut game = Game::default();
+        process_moves(&mut game, &["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7"]);
+        assert_eq!(Status::Checkmate, game.status());
+        game.undo().unwrap();
+        assert_eq!(Status::Ongoing, game.status());
+        process_moves(&mut game, &["Qf3", "Ng8", "Qh5", "Nf6"]);
+        assert_eq!(2, game.repetition_count());
+    }
+
+    #[test]
+    fn test_perft() {
+        let game = Game::default();
+        assert_eq!(1, game.perft(0));
+        assert_eq!(20, game.perft(1));
+        assert_eq!(400, game.perft(2));
+        assert_eq!(8902, game.perft(3));
+        assert_eq!(197281, game.perft(4));
+    }
+
+    #[test]
+    fn test_perft_special_moves() {
+        // kiwipete, castling, en passant and promotions
+        let game = Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(48, game.perft(1));
+        assert_eq!(2039, game.perft(2));
+        assert_eq!(97862, game.perft(3));
+
+        // en passant and discovered checks along the rank
+        let game = Game::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(14, game.perft(1));
+        assert_eq!(191, game.perft(2));
+        assert_eq!(2812, game.perft(3));
+        assert_eq!(43238, game.perft(4));
+
+        // promotions
+        let game = Game::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap();
+        assert_eq!(6, game.perft(1));
+        assert_eq!(264, game.perft(2));
+        assert_eq!(9467, game.perft(3));
+    }
+
+    #[test]
+    fn test_perft_divide() {
+        let game = Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let expected = [
+            ("a1b1", 43), ("a1c1", 43), ("a1d1", 43), ("a2a3", 44), ("a2a4", 44), ("b2b3", 42),
+            ("c3a4", 42), ("c3b1", 42), ("c3b5", 39), ("c3d1", 42), ("d2c1", 43), ("d2e3", 43),
+            ("d2f4", 43), ("d2g5", 42), ("d2h6", 41), ("d5d6", 41), ("d5e6", 46), ("e1c1", 43),
+            ("e1d1", 43), ("e1f1", 43), ("e1g1", 43), ("e2a6", 36), ("e2b5", 39), ("e2c4", 41),
+            ("e2d1", 44), ("e2d3", 42), ("e2f1", 44), ("e5c4", 42), ("e5c6", 41), ("e5d3", 43),
+            ("e5d7", 45), ("e5f7", 44), ("e5g4", 44), ("e5g6", 42), ("f3d3", 42), ("f3e3", 43),
+            ("f3f4", 43), ("f3f5", 45), ("f3f6", 39), ("f3g3", 43), ("f3g4", 43), ("f3h3", 43),
+            ("f3h5", 43), ("g2g3", 42), ("g2g4", 42), ("g2h3", 43), ("h1f1", 43), ("h1g1", 43),
+        ];
+        let expected: Vec<(String, u64)> = expected.iter().map(|(mv, count)| (mv.to_string(), *count)).collect();
+        assert_eq!(expected, game.perft_divide(2));
+
+        let total: u64 = game.perft_divide(2).iter().map(|(_, count)| count).sum();
+        assert_eq!(game.perft(2), total);
+        assert!(game.perft_divide(0).is_empty());
+    }
+
+    #[test]
+    fn test_explain_illegal() {
+        let game = Game::default();
+        assert_eq!(None, game.explain_illegal("e4"));
+        assert_eq!(
+            Some("The f1 bishop is blocked by your pawn on e2".to_string()),
+            game.explain_illegal("Bc4")
+        );
+        assert_eq!(
+            Some("The d1 queen is blocked by your pawn on d2".to_string()),
+            game.explain_illegal("Qd4")
+        );
+        assert_eq!(Some("No knight can move to e4".to_string()), game.explain_illegal("Ne4"));
+        assert_eq!(Some("\"Zz9\" is not a valid move".to_string()), game.explain_illegal("Zz9"));
+
+        let game = Game::from_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            Some("The knight on e2 is pinned, moving it would put your king in check from the rook on e7".to_string()),
+            game.explain_illegal("Nc3")
+        );
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/r7/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            Some("Your king would be in check from the rook on a2".to_string()),
+            game.explain_illegal("Ke2")
+        );
+        assert_eq!(None, game.explain_illegal("Kf1"));
+    }
+
+    /// check validation before make/unmake: full recompute on a copied board
+    fn validate_move_check_by_clone(board: &Board, from: u64, to: u64, is_white: bool) -> bool {
+        let mut simulated_board = *board;
+        if to == Game::get_pieces(board, Piece::King, !is_white) {
+            return false;
+        }
+        if simulated_board.is_capture(to, is_white) {
+            simulated_board.remove_piece(to, !is_white);
+        }
+        simulated_board.move_piece(from, to, is_white);
+        simulated_board.update_compute_moves();
+        Game::is_in_check(&simulated_board, is_white)
+    }
+
+    #[test]
+    fn test_validate_move_check_make_unmake() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "4k3/8/8/1N6/8/8/8/r2NK3 w - - 0 1",
+            "3rkb2/2pp1p2/3N4/8/8/8/8/4R1K1 b - - 0 1",
+        ] {
+            let board = Game::from_fen(fen).unwrap().board;
+            for (from, piece, is_white) in board.pieces() {
+                let mut targets = compute_piece_moves(&board, piece, from, is_white);
+                while targets != 0 {
+                    let to = 1u64 << targets.trailing_zeros();
+                    targets &= targets - 1;
+                    let mut made = board;
+                    assert_eq!(
+                        validate_move_check_by_clone(&board, from, to, is_white),
+                        Game::validate_move_check(&mut made, from, to, is_white),
+                        "{} {}{}",
+                        fen,
+                        square_name(from).unwrap(),
+                        square_name(to).unwrap()
+                    );
+                    assert_eq!(board, made);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_disambiguate() {
+        let mut game = Game::from_fen("4k3/4p3/8/8/8/8/4P3/1N2KN2 w - - 0 1").unwrap();
+        let err = game.process_move("Nd2").unwrap_err();
+        assert_eq!(MoveError::AmbiguousSource(bitboard_single('b', 1).unwrap() | bitboard_single('f', 1).unwrap()), err);
+        let MoveError::AmbiguousSource(sources) = err else { unreachable!() };
+        assert_eq!(vec!["Nbd2", "Nfd2"], game.disambiguate("Nd2", sources));
+        assert_eq!(
+            Some("More than one knight can move to d2, did you mean Nbd2 or Nfd2?".to_string()),
+            game.explain_illegal("Nd2")
+        );
+
+        // pinned knight is not suggested
+        let game = Game::from_fen("4k3/8/8/1N6/8/8/8/r2NK3 w - - 0 1").unwrap();
+        let sources = bitboard_single('b', 5).unwrap() | bitboard_single('d', 1).unwrap();
+        assert_eq!(vec!["Nc3"], game.disambiguate("Nc3", sources));
+
+        assert_eq!("", or_list(&[]));
+        assert_eq!("Q3b2, Qa1b2 or Qcb2", or_list(&["Q3b2".to_string(), "Qa1b2".to_string(), "Qcb2".to_string()]));
+    }
+
+    #[test]
+    fn test_pgn_movetext() {
+        let mut game = Game::default();
+        assert_eq!("*", game.pgn_movetext());
+
+        process_moves(&mut game, &["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6"]);
+        assert_eq!("1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 *", game.pgn_movetext());
+        process_moves(&mut game, &["Qxf7"]);
+        assert_eq!("1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0", game.pgn_movetext());
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 12").unwrap();
+        process_moves(&mut game, &["Kd7", "e4"]);
+        assert_eq!("12... Kd7 13. e4 *", game.pgn_movetext());
+    }
+
+    #[test]
+    fn test_to_pgn() {
+        let roster = |result: &str| {
+            format!(
+                "[Event \"Casual game\"]\n[Site \"chessterm\"]\n[Date \"????.??.??\"]\n[Round \"-\"]\n\
+                 [White \"?\"]\n[Black \"?\"]\n[Result \"{}\"]\n",
+                result
+            )
+        };
+        let mut game = Game::default();
+        process_moves(&mut game, &["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7"]);
+        assert_eq!(format!("{}\n1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n", roster("1-0")), game.to_pgn());
+
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 12";
+        let mut game = Game::from_fen(fen).unwrap();
+        process_moves(&mut game, &["Kd7", "e4"]);
+        assert_eq!(
+            format!("{}[SetUp \"1\"]\n[FEN \"{}\"]\n\n12... Kd7 13. e4 *\n", roster("*"), fen),
+            game.to_pgn()
+        );
+
+        // movetext wrapped at 80 columns
+        let mut game = Game::default();
+        process_moves(
+            &mut game,
+            &[
+                "e4", "e5", "Nf3", "d6", "d4", "Bg4", "dxe5", "Bxf3", "Qxf3", "dxe5", "Bc4", "Nf6", "Qb3",
+                "Qe7", "Nc3", "c6", "Bg5", "b5", "Nxb5", "cxb5", "Bxb5+", "Nbd7",
+            ],
+        );
+        let pgn = game.to_pgn();
+        let movetext: Vec<&str> = pgn.lines().skip(8).collect();
+        assert!(movetext.len() > 1);
+        assert!(movetext.iter().all(|line| line.len() <= 80));
+        assert_eq!(game.pgn_movetext(), movetext.join(" "));
+    }
+
+    #[test]
+    fn test_checkers() {
+        let game = Game::default();
+        assert_eq!(0, game.checkers());
+
+        let game = Game::from_fen("4k3/8/8/8/7b/8/8/r3K3 w - - 0 1").unwrap();
+        assert!(game.check);
+        assert_eq!(
+            PositionBuilder::new().add_piece('a', 1).add_piece('h', 4).build(),
+            game.checkers()
+        );
+    }
+
+    #[test]
+    fn test_check_info() {
+        assert_eq!(None, Game::default().check_info());
+
+        // double check by a rook and a bishop, lines up to the king
+        let game = Game::from_fen("4k3/8/8/8/7b/8/8/r3K3 w - - 0 1").unwrap();
+        let info = game.check_info().unwrap();
+        assert_eq!(game.checkers(), info.checkers);
+        assert_eq!(
+            PositionBuilder::new()
+                .add_piece('a', 1)
+                .add_piece('b', 1)
+                .add_piece('c', 1)
+                .add_piece('d', 1)
+                .add_piece('h', 4)
+                .add_piece('g', 3)
+                .add_piece('f', 2)
+                .build(),
+            info.lines
+        );
+
+        // knight and pawn checks have no line
+        let game = Game::from_fen("4k3/8/8/8/8/3n4/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(bitboard_single('d', 3).unwrap(), game.check_info().unwrap().lines);
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/3p3p/2K5 w - - 0 1").unwrap();
+        assert_eq!(bitboard_single('d', 2).unwrap(), game.check_info().unwrap().lines);
+        process_moves(&mut game, &["Kxd2"]);
+        assert_eq!(None, game.check_info());
+    }
+
+    #[test]
+    fn test_double_check_only_king_moves() {
+        // Nd6 is double check (knight and rook), the knight can be captured by the pawn
+        // but only king moves count. No king move is available so it's checkmate
+        let mut game = Game::from_fen("3rkb2/2pp1p2/8/8/4N3/8/8/4R1K1 w - - 0 1").unwrap();
+        let checks_before = game.non_king_move_checks;
+        process_moves(&mut game, &["Nd6"]);
+        assert!(game.check);
+        assert_eq!(2, game.checkers().count_ones());
+        assert_eq!(Status::Checkmate, game.status());
+        assert_eq!(checks_before, game.non_king_move_checks);
+
+        // single check still looks at every piece
+        let mut game = Game::from_fen("3rkb2/2pp1p2/8/8/4N3/8/8/6K1 w - - 0 1").unwrap();
+        let checks_before = game.non_king_move_checks;
+        process_moves(&mut game, &["Nd6"]);
+        assert_eq!(1, game.checkers().count_ones());
+        assert_eq!(Status::Ongoing, game.status());
+        assert_eq!(checks_before + 1, game.non_king_move_checks);
+    }
+
+    #[test]
+    fn test_double_check_rejects_non_king_moves() {
+        // Nd6 checks with the knight and discovers the rook on the e-file
+        let mut game = Game::from_fen("r1bqk2r/pppp1ppp/2n2n2/8/4N3/8/PPPP1PPP/RNB1R1K1 w kq - 0 1").unwrap();
+        assert_eq!(0, game.checker_count);
+        process_moves(&mut game, &["Nd6"]);
+        assert_eq!(2, game.checker_count);
+        assert_eq!(
+            PositionBuilder::new().add_piece('d', 6).add_piece('e', 1).build(),
+            game.checkers()
+        );
+
+        // capturing the knight or blocking the rook doesn't stop the other check
+        process_moves_error(
+            &mut game,
+            &[
+                ("cxd6", MoveError::Checked),
+                ("Qe7", MoveError::Checked),
+                ("Ne7", MoveError::Checked),
+                ("O-O", MoveError::Checked),
+                ("Ke7", MoveError::Checked),
+            ],
+        );
+        assert_eq!(
+            Some("Your king is in double check, only the king can move".to_string()),
+            game.explain_illegal("cxd6")
+        );
+        assert_eq!(vec!["e8f8".to_string()], game.legal_uci_moves());
+
+        process_moves(&mut game, &["Kf8"]);
+        assert_eq!(0, game.checker_count);
+        assert_eq!(0, game.checkers());
+        game.undo().unwrap();
+        assert_eq!(2, game.checker_count);
+    }
+
+    #[test]
+    fn test_repetition_history_bounded() {
+        use crate::engine::rng::Rng;
+
+        let mut rng = Rng::new(451);
+        let mut game = Game::default();
+        for _ in 0..200 {
+            let moves = game.legal_uci_moves();
+            if moves.is_empty() || game.status() != Status::Ongoing {
+                break;
+            }
+            game.apply_uci(&moves[rng.next_range(moves.len())]).unwrap();
+
+            // only position keys since the last irreversible move are kept
+            let history: &Vec<u64> = &game.position_history;
+            assert!(history.len() <= game.halfmove_clock as usize + 1);
+            assert!(history.len() <= 151);
+            assert_eq!(Some(&game.hash()), history.last());
+        }
+        assert!(game.history.len() > 100);
+    }
+
+    #[test]
+    fn test_check_resolution_squares() {
+        assert_eq!(0, Game::default().check_resolution_squares());
+
+        // rook check from a1: king escapes to d2/e2/f2, knight blocks on c1 or captures the rook
+        let game = Game::from_fen("4k3/8/8/8/8/1N6/8/r3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            PositionBuilder::new()
+                .add_piece('a', 1)
+                .add_piece('c', 1)
+                .add_piece('d', 2)
+                .add_piece('e', 2)
+                .add_piece('f', 2)
+                .build(),
+            game.check_resolution_squares()
+        );
+
+        // double check (rook and bishop), the knight could capture the rook but only the king can move
+        let game = Game::from_fen("4k3/8/8/8/7b/1N6/8/r3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            PositionBuilder::new().add_piece('d', 2).add_piece('e', 2).build(),
+            game.check_resolution_squares()
+        );
+    }
+
+    #[test]
+    fn test_history() {
+        let mut game = Game::default();
+        assert!(game.history().is_empty());
+
+        process_moves(&mut game, &["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7"]);
+        let history = game.history();
+        assert_eq!(7, history.len());
+        let san: Vec<&str> = history.iter().map(|mv| mv.san.as_str()).collect();
+        assert_eq!(vec!["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"], san);
+        assert_eq!(
+            MoveInfo {
+                san: "Qxf7#".to_string(),
+                is_white: true,
+                piece: Piece::Queen,
+                from: bitboard_single('h', 5).unwrap(),
+                to: bitboard_single('f', 7).unwrap(),
+                is_capture: true,
+                is_check: true,
+            },
+            history[6]
+        );
+        assert!(history.iter().step_by(2).all(|mv| mv.is_white));
+        assert_eq!(game.last_move(), history.last());
+    }
+
+    #[test]
+    fn test_display() {
+        let mut game = Game::default();
+        assert_eq!("white to move, castling KQkq, en passant -", game.to_string());
+
+        process_moves(&mut game, &["e4"]);
+        assert_eq!("black to move, castling KQkq, en passant e3", game.to_string());
+
+        process_moves(&mut game, &["Nf6", "Ke2"]);
+        assert_eq!("black to move, castling kq, en passant -", game.to_string());
+
+        process_moves(&mut game, &["d5"]);
+        assert_eq!("white to move, castling kq, en passant d6", game.to_string());
+        process_moves(&mut game, &["exd5"]);
+        assert_eq!("black to move, castling kq, en passant -", game.to_string());
+    }
+
+    #[test]
+    fn test_process_move_returns_san() {
+        let mut game = Game::default();
+        assert_eq!("e4", game.process_move("e4").unwrap().san);
+        process_moves(&mut game, &["e5", "Qh5", "Nc6", "Bc4", "Nf6"]);
+
+        let move_info = game.process_move("Qxf7").unwrap();
+        assert_eq!("Qxf7#", move_info.san);
+        assert!(move_info.is_capture);
+        assert_eq!(game.last_move(), Some(&move_info));
+
+        let mut game = Game::default();
+        process_moves(&mut game, &["e4", "f5"]);
+        assert_eq!("Qh5+", game.process_move("Qh5").unwrap().san);
+        assert!(game.process_move("Nf6").is_err());
+    }
+
+    #[test]
+    fn test_process_move_suffixes() {
+        // Morphy vs Duke Karl / Count Isouard, Paris 1858 with annotations
+        let mut game = Game::default();
+        process_moves(
+            &mut game,
+            &[
+                "e4", "e5", "Nf3", "d6", "d4", "Bg4?!", "dxe5", "Bxf3", "Qxf3", "dxe5", "Bc4", "Nf6?",
+                "Qb3!", "Qe7", "Nc3", "c6", "Bg5", "b5?", "Nxb5!", "cxb5", "Bxb5+", "Nbd7", "O-O-O",
+                "Rd8", "Rxd7!", "Rxd7", "Rd1", "Qe6", "Bxd7+", "Nxd7", "Qb8+!!", "Nxb8", "Rd8#",
+            ],
+        );
+        assert_eq!(Status::Checkmate, game.status());
+        assert_eq!("Qb8+", game.history[30].san);
+
+        // check marker has to match the position when given
+        let mut game = Game::default();
+        let fen = game.to_fen();
+        assert_eq!(Err(MoveError::WrongCheckSuffix), game.process_move("e4+"));
+        assert_eq!(fen, game.to_fen());
+        assert!(game.history().is_empty());
+        process_moves(&mut game, &["e4", "f5"]);
+        assert_eq!(Err(MoveError::WrongCheckSuffix), game.process_move("Qh5#"));
+        assert_eq!(
+            Some("Qh5# is not checkmate".to_string()),
+            game.explain_illegal("Qh5#")
+        );
+        assert_eq!("Qh5+", game.process_move("Qh5+!").unwrap().san);
+    }
+
+    #[test]
+    fn test_process_move_uci() {
+        let mut game = Game::default();
+        assert_eq!("e4", game.process_move("e2e4").unwrap().san);
+        assert_eq!("e5", game.process_move("e5").unwrap().san);
+        assert_eq!("Nf3", game.process_move(" g1f3 ").unwrap().san);
+        assert_eq!(Err(MoveError::ParseError), game.process_move("e7e9"));
+        assert_eq!(
+            Err(MoveError::InvalidMove(InvalidMoveReason::InvalidSourceOrTarget)),
+            game.process_move("e7e4")
+        );
+
+        let mut game = Game::from_fen("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!("O-O", game.process_move("e1g1").unwrap().san);
+        assert_eq!("Kd7", game.process_move("e8d7").unwrap().san);
+        assert_eq!("b8=N+", game.process_move("b7b8n").unwrap().san);
+    }
+
+    #[test]
+    fn test_san() {
+        let mv = |from, to, promotion| Move {
+            from: parse_square(from).unwrap(),
+            to: parse_square(to).unwrap(),
+            promotion,
+        };
+
+        // over-disambiguated input is recorded in canonical SAN
+        let mut game = Game::default();
+        assert_eq!("Nf3", game.process_move("Ngf3").unwrap().san);
+
+        // disambiguation by file, rank, or both
+        let game = Game::from_fen("5k2/8/8/8/Q6Q/8/8/Q3K1R1 w - - 0 1").unwrap();
+        assert_eq!(Ok("Qa4d4".to_string()), game.san(mv("a4", "d4", None)));
+        assert_eq!(Ok("Qhe4".to_string()), game.san(mv("h4", "e4", None)));
+        assert_eq!(Ok("Q1a3+".to_string()), game.san(mv("a1", "a3", None)));
+        assert_eq!(Ok("Qb2".to_string()), game.san(mv("a1", "b2", None)));
+        assert_eq!(Ok("Rg8+".to_string()), game.san(mv("g1", "g8", None)));
+        assert_eq!(Ok("Q1d4".to_string()), game.san(mv("a1", "d4", None)));
+
+        // promotion, castling and checkmate
+        let game = Game::from_fen("6k1/1P3ppp/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        assert_eq!(Ok("b8=Q#".to_string()), game.san(mv("b7", "b8", Some(Piece::Queen))));
+        assert_eq!(Ok("b8=N".to_string()), game.san(mv("b7", "b8", Some(Piece::Knight))));
+        assert_eq!(Ok("O-O".to_string()), game.san(mv("e1", "g1", None)));
+        assert_eq!(Ok("O-O-O".to_string()), game.san(mv("e1", "c1", None)));
+        assert_eq!(Ok("Ra8#".to_string()), game.san(mv("a1", "a8", None)));
+        assert!(game.san(mv("a1", "b2", None)).is_err());
+    }
+
+    #[test]
+    fn test_analysis_mode() {
+        let mut game = Game::default();
+        process_moves(&mut game, &["e4", "e5"]);
+        let fen = game.to_fen();
+        assert!(!game.is_analysis());
+        assert!(game.process_move("Nc6").is_err());
+
+        game.enter_analysis();
+        assert!(game.is_analysis());
+        // two white moves in a row, then black
+        process_moves(&mut game, &["Nf3", "Bc4", "Nc6", "Nf6"]);
+        assert_eq!(6, game.history().len());
+        assert_ne!(0, game.board.white_bishops & bitboard_single('c', 4).unwrap());
+        // still illegal for both colors
+        assert!(game.process_move("Ke4").is_err());
+        assert_ne!(fen, game.to_fen());
+
+        game.exit_analysis();
+        assert!(!game.is_analysis());
+        assert_eq!(fen, game.to_fen());
+        assert_eq!(2, game.history().len());
+        process_moves(&mut game, &["Nf3"]);
+        assert!(game.process_move("Bc4").is_err());
+    }
+
+    #[test]
+    fn test_position_after() {
+        let moves = ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Bxc6", "dxc6", "O-O"];
+        let mut game = Game::default();
+        process_moves(&mut game, &moves);
+
+        for plies in 0..=moves.len() {
+            let mut fresh = Game::default();
+            process_moves(&mut fresh, &moves[..plies]);
+            let position = game.position_after(plies).unwrap();
+            assert_eq!(fresh.to_fen(), position.to_fen());
+            assert_eq!(fresh.hash(), position.hash());
+            assert_eq!(fresh.status(), position.status());
+            let sans = |game: &Game| game.history().iter().map(|mv| mv.san.clone()).collect::<Vec<_>>();
+            assert_eq!(sans(&fresh), sans(&position));
+        }
+        assert!(game.position_after(moves.len() + 1).is_none());
+
+        // analysis moves are replayed too, the live game is kept
+        let live = game.to_fen();
+        game.enter_analysis();
+        process_moves(&mut game, &["b5", "Nf6"]);
+        let mut position = game.position_after(10).unwrap();
+        assert!(position.is_analysis());
+        assert_eq!("r1bqkbnr/2p2ppp/p1p5/1p2p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 w kq b6 0 6", position.to_fen());
+        position.exit_analysis();
+        assert_eq!(live, position.to_fen());
+    }
+
+    /// plays a random game (legal moves only) until it ends, returns the number of plies
+    fn play_random_game(seed: u64) -> usize {
+        use crate::engine::rng::Rng;
+
+        let mut rng = Rng::new(seed);
+        let mut game = Game::default();
+        while game.status() == Status::Ongoing {
+            let moves = game.legal_uci_moves();
+            assert!(!moves.is_empty(), "seed {}: no legal move in ongoing game {}", seed, game.to_fen());
+            let mv = &moves[rng.next_range(moves.len())];
+            let before = game.clone();
+            assert_eq!(Ok(()), game.apply_uci(mv).map(|_| ()), "seed {}: {} failed in {}", seed, mv, before.to_fen());
+            assert_eq!(Board::from_fen(&game.board.to_fen()), game.board, "seed {}: board out of sync", seed);
+
+            // taking the move back restores the previous state
+            let mut undone = game.clone();
+            assert_eq!(Ok(()), undone.undo(), "seed {}: {} undo failed", seed, mv);
+            assert_eq!(before.to_fen(), undone.to_fen(), "seed {}: {} undo", seed, mv);
+            assert_eq!(before.board, undone.board, "seed {}: {} undo", seed, mv);
+            assert_eq!(before.repetition_count(), undone.repetition_count());
+            assert_eq!(before.hash(), undone.hash());
+            assert_eq!((before.check, before.status()), (undone.check, undone.status()));
+            assert_eq!((before.pinned_white, before.pinned_black), (undone.pinned_white, undone.pinned_black));
+            // the game must end (50-move rule, repetition, material) long before this
+            assert!(game.history().len() < 6000, "seed {}: game doesn't end", seed);
+        }
+        game.history().len()
+    }
+
+    #[test]
+    fn test_random_games() {
+        for seed in [1, 2, 3, 42, 460, 2024, 31337, 65535] {
+            play_random_game(seed);
+        }
+    }
+
+    #[test]
+    fn test_opening() {
+        let mut game = Game::default();
+        assert_eq!(None, game.opening());
+        process_moves(&mut game, &["e4", "e5", "Nf3", "Nc6", "Bb5"]);
+        assert_eq!(Some("Ruy Lopez"), game.opening());
+        process_moves(&mut game, &["Nf6", "O-O"]);
+        assert_eq!(None, game.opening());
+    }
+
+    #[test]
+    fn test_statistics() {
+        let mut game = Game::default();
+        assert_eq!(GameStats::default(), game.statistics());
+
+        process_moves(
+            &mut game,
+            &["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "O-O", "Nxe4", "Bxf7", "Kxf7", "d4", "exd4", "Ng5", "Kg8", "Qf3", "Qxg5", "Qf7"],
+        );
+
+        let stats = game.statistics();
+        assert_eq!(
+            SideStats {
+                moves: 9,
+                captures: 1,
+                checks: 3,
+                castled: true,
+            },
+            stats.white
+        );
+        assert_eq!(
+            SideStats {
+                moves: 8,
+                captures: 4,
+                checks: 0,
+                castled: false,
+            },
+            stats.black
+        );
+        assert_eq!("Bxf7+", game.history[8].san);
+        assert_eq!("Qf7+", game.history[16].san);
+        assert_eq!(bitboard_single('c', 4).unwrap(), game.history[8].from);
+        assert_eq!(bitboard_single('f', 7).unwrap(), game.history[8].to);
+
+        // castling records the king move
+        assert_eq!(bitboard_single('e', 1).unwrap(), game.history[6].from);
+        assert_eq!(bitboard_single('g', 1).unwrap(), game.history[6].to);
+        assert_eq!(Some(&game.history[16]), game.last_move());
+    }
+
+    #[test]
+    fn test_short_game() {
+        let mut game = Game::default();
+        process_moves(&mut game, &["e4", "e5", "Bc4", "Nc6", "Qh5", "Nd4", "Qxf7"]);
+        assert_eq!(Status::Checkmate, game.status());
+    }
+
+    #[test]
+    fn test_longer_game() {
+        let mut game = Game::default();
+        // reference: https://www.chess.com/game/computer/214517513
+        process_moves(
+            &mut game,
+            &[
+                "b4", "Nf6", "Bb2", "e6", "a3", "d5", "e3", "Be7", "c4", "dxc4", "Bxc4", "Rf8",
+                "Nf3", "c6", "Nc3", "Bd7", "d4", "Ng4", "h3", "Nf6", "O-O", "h6", "e4", "c5",
+                "dxc5", "Nc6", "e5", "Nh7", "Ne4", "Nxb4", "axb4", "Qc7", "Re1", "b5", "Bb3",
+                "Rc8", "Nd4", "Qxe5", "Qd2", "f5", "Nc6", "Qxe4", "Rxe4", "fxe4", "Bxg7", "Bxc6",
+                "Bxf8", "Nxf8", "Qxh6", "Bd5", // Qh5+
+                "Qh5", "Kd8", "Rd1", "Bxc5", "bxc5", "Rxc5", // Qg5+
+                "Qg5", "Ke8", "f3", "exf3", "Bxd5", "Rxd5", "Rxd5", "exd5", "Qxd5", "fxg2", "Kxg2",
+                "b4", // Qe4+
+                "Qe4", "Kd8", "Qxb4", "a6", // Qa5+
+                "Qa5", "Ke8", "Qxa6", "Nh7", // Qg6+
+                "Qg6", "Ke7", // Qxh7+
+                "Qxh7", "Kf6", "Kg3", "Ke5", "Kg4", "Kd5", // Qf5+
+                "Qf5", "Kd6", "Kg5", "Kc7", "Kf6", "Kd8", "Ke6", "Kc7", // Qe5+
+                "Qe5", "Kc8", // Qc5+
+                "Qc5", "Kb7", "Kd7", "Ka6", "Qd5", "Kb6", "Kd6", "Ka6", "Kc6", "Ka7", "Kc7", "Ka6",
+                // Qc6+
+                "Qc6", "Ka5", // Qc5+
+                "Qc5", "Ka4", // Qc6+
+                "Qc6", "Kb4", "Kb6", "Kb3", "Kb5", "Kb2", "Qc4", "Ka3", "Kc5", "Kb2", "Kb4", "Kb1",
+                "Kb3", "Ka1", "Qc1",
+            ],
+        );
+        assert_eq!(Status::Checkmate, game.status());
+        process_moves_error(&mut game, &[("Ka2", MoveError::GameOver)]);
+    }
+
+    #[test]
+    fn test_stalemate_game() {
+        let mut game = Game::default();
+        // reference: https://lichess.org/zV6oGNVz
+        process_moves(
+            &mut game,
+            &[
+                "e4", "e5", "Nf3", "h5", "Nxe5", "f6", "Nf3", "Ne7", "Bc4", "d5", "exd5", "Nxd5",
+                "O-O", "Nf4", "d4", "Rh6", "Bxf4", "Bd6", // Bxh2+
+                "Bxh6", "Bxh2", "Nxh2", "Qxd4", "Qxd4", "Nc6", // Qe4+
                 "Qe4", "Kf8", "Re1", "gxh6", // Qe8+
                 "Qe8", "Kg7", "Nc3", "Bh3", // Qg8+
                 "Qg8", "Rxg8", "Bxg8", "Kxg8", "gxh3", "Ne7", "Rxe7", "Kf8", "Rae1", "c5",
@@ -2041,16 +5089,63 @@ pub mod tests {
                 "f5", "Ra4",
             ],
         );
-        assert_eq!(Status::Draw, game.status);
+        assert_eq!(Status::Draw, game.status());
         process_moves_error(&mut game, &[("Kg5", MoveError::GameOver)]);
     }
 
+    #[test]
+    fn test_repetition_count() {
+        let mut game = Game::default();
+        assert_eq!(1, game.repetition_count());
+
+        process_moves(&mut game, &["Nf3", "Nf6"]);
+        assert_eq!(1, game.repetition_count());
+        process_moves(&mut game, &["Ng1", "Ng8"]);
+        // back to starting position
+        assert_eq!(2, game.repetition_count());
+
+        // pawn move is irreversible, new position has not been repeated
+        process_moves(&mut game, &["e4"]);
+        assert_eq!(1, game.repetition_count());
+        process_moves(&mut game, &["Nf6", "Nf3", "Ng8", "Ng1"]);
+        assert_eq!(1, game.repetition_count());
+        process_moves(&mut game, &["Nf6"]);
+        assert_eq!(2, game.repetition_count());
+    }
+
+    #[test]
+    fn test_repetition_history_irreversible_move() {
+        let mut game = Game::default();
+        process_moves(&mut game, &["Nf3", "Nf6", "Ng1", "Ng8"]);
+        assert_eq!(5, game.position_history.len());
+        assert_eq!(2, game.repetition_count());
+
+        // pawn move resets the history
+        process_moves(&mut game, &["d4"]);
+        assert_eq!(1, game.position_history.len());
+        process_moves(&mut game, &["Nf6", "Nf3", "Ng8", "Ng1"]);
+        assert_eq!(5, game.position_history.len());
+
+        // capture resets the history
+        process_moves(&mut game, &["e5", "dxe5"]);
+        assert_eq!(1, game.position_history.len());
+        assert_eq!(1, game.repetition_count());
+
+        // losing castling rights resets the history
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R");
+        let mut game = Game::new(board);
+        process_moves(&mut game, &["Kd1", "Kd8", "Ke1", "Ke8"]);
+        // history starts from black king move (last castling right lost)
+        assert_eq!(3, game.position_history.len());
+        assert_eq!(1, game.repetition_count());
+        process_moves(&mut game, &["Kd1", "Kd8", "Ke1", "Ke8"]);
+        assert_eq!(2, game.repetition_count());
+    }
+
     #[test]
     fn test_valid_move() {
         let board = Board::from_fen("r7/1p1k1ppp/p1n4q/1B6/3Pp3/4P3/1B1N1PPP/R2QK2R");
-        let mut game = Game::new(board);
-        // black's turn
-        game.turn = 2;
+        let mut game = Game::with_side_to_move(board, false);
         process_moves(&mut game, &["axb5"]);
     }
 }
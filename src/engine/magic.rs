@@ -0,0 +1,186 @@
+use crate::engine::moves::{
+    compute_sliding_moves, BISHOP_RAYS_DIRECTIONS, QUEEN_RAYS, ROOK_RAYS_DIRECTIONS, DOWN, DOWN_LEFT, DOWN_RIGHT,
+    LEFT,
+};
+use std::sync::LazyLock;
+
+/// magic numbers of the rook on every square, found by trying sparse random numbers
+/// (AND of three) until every occupancy of the relevant mask maps to a slot holding
+/// the same attacks
+const ROOK_MAGICS: [u64; 64] = [
+    0x0900_1080_0041_0021, 0x0100_2010_4001_0080, 0x4300_0B00_2000_4110, 0x2100_0420_1001_0008,
+    0x0200_0200_6008_4490, 0x0C00_8210_2008_0400, 0x3980_0A00_0080_0100, 0x0100_0020_9041_0002,
+    0xC410_8000_4010_8020, 0x0400_4020_0040_1004, 0x0008_8010_0420_0080, 0x0402_8008_0080_1000,
+    0x0000_8004_0008_0080, 0x4202_0004_0200_1008, 0x2062_0002_0041_4804, 0x8802_0000_4429_0C82,
+    0x8640_2080_0080_4000, 0x0410_1040_0020_0048, 0x0500_1100_4100_2000, 0x0008_0900_2090_0100,
+    0x0009_0100_0802_1004, 0x5084_0040_4002_0100, 0x0000_0400_0102_1008, 0x0005_2200_0400_4081,
+    0x8840_0080_8000_4025, 0x3000_2002_4000_5000, 0x0820_0021_0041_0010, 0x100A_020A_0010_2040,
+    0x2204_0080_8008_0004, 0x0002_0002_8080_0400, 0x0040_8104_0008_0250, 0x0840_0102_0020_8044,
+    0x0000_8040_0280_002D, 0x8600_4082_0600_2104, 0x4012_0010_8200_4020, 0x0000_1001_0100_0822,
+    0x8008_0010_0900_0500, 0x000A_0420_0801_4010, 0x2000_0802_0400_0110, 0x0902_0100_8200_0C54,
+    0x0080_0044_2002_4001, 0x1C40_2000_5000_4004, 0x00B0_8600_2042_0010, 0x0902_0240_2012_0008,
+    0x30CE_0004_0822_0010, 0x0080_0200_0400_8080, 0x0000_0200_0401_0100, 0x2008_0041_0082_0004,
+    0x0204_2280_0140_1480, 0x0B00_4001_0090_2100, 0x0000_1882_0020_4200, 0x0805_00A1_1850_0100,
+    0x0280_0800_8004_0080, 0x0008_8004_0002_0080, 0x0000_1042_3148_0400, 0x0040_0401_82C1_1A00,
+    0x0442_0100_2040_1082, 0x4448_1021_0084_4202, 0x8001_0210_0A40_2001, 0x0016_1001_0020_0409,
+    0x00CA_0020_5008_0406, 0x4002_0010_2938_2426, 0x0090_20A8_3001_2204, 0x8400_2106_80C4_0032,
+];
+
+/// magic numbers of the bishop on every square, see [`ROOK_MAGICS`]
+const BISHOP_MAGICS: [u64; 64] = [
+    0x8011_1010_0044_4040, 0x2804_9022_0059_0474, 0x8141_0202_0040_9000, 0x8C11_0400_8000_1000,
+    0x2007_1040_2002_00B0, 0x0001_0420_0440_4000, 0x20B0_4212_1040_1987, 0x1020_8048_0201_6096,
+    0x0004_2023_0208_1104, 0x0210_8230_120A_0842, 0x2200_9806_4C04_2122, 0x0000_0920_4201_1680,
+    0x0040_0110_4000_1005, 0x4800_2444_2004_0068, 0x0000_C101_2820_0400, 0x2480_0104_0202_2240,
+    0x4020_0050_2102_6080, 0x002A_B008_0808_4280, 0x0531_0010_0F12_2100, 0x0000_8128_1200_4024,
+    0x0114_0088_2208_0020, 0xC405_4002_0110_2100, 0x0040_4002_0210_0401, 0x0010_8000_20A8_0802,
+    0x0108_0410_4010_4202, 0x0110_1009_0224_0106, 0x0000_9000_5800_24A0, 0x0005_0800_0400_4110,
+    0x0408_8480_0400_2008, 0x4604_0188_2100_A000, 0x0041_0C00_4124_0100, 0x0002_2020_3044_0A00,
+    0x0002_2010_3104_1033, 0x0804_4104_5060_0400, 0x4022_4402_0C10_0020, 0x8000_0404_0008_0120,
+    0x4000_4200_2002_0080, 0x0A10_024A_0000_C110, 0x0164_4102_0100_4820, 0x1008_2100_4C00_2210,
+    0x0148_0894_1000_2440, 0x4C00_4108_0802_2043, 0xA081_2100_4040_1800, 0x1102_8042_0082_1804,
+    0x0008_0801_0440_0400, 0x1444_8088_0200_1040, 0x1002_5004_0290_0D01, 0x0501_8C04_0085_0044,
+    0xC042_0211_0540_0042, 0x0000_4C04_0402_6002, 0x4009_0020_8450_0400, 0x4041_0280_2088_2189,
+    0x0020_8420_2041_0100, 0x1000_3882_088A_0040, 0x4010_0481_0802_0080, 0x4002_A468_0204_4201,
+    0x8102_024D_0801_1005, 0x0028_0041_1421_2000, 0x0002_2000_8058_0808, 0x0050_2A08_402A_0800,
+    0x0400_8400_2018_5040, 0x1020_0042_5002_1080, 0x8400_1110_9028_8082, 0x08C8_1610_0200_2908,
+];
+
+/// magic bitboard of one square: the occupancy of `mask` multiplied by `magic` and
+/// shifted by `shift` is the index of the attacks in the table (after `offset`)
+#[derive(Debug, Clone, Copy, Default)]
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: u64) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+/// attack tables of rooks and bishops for every square and occupancy
+struct SlidingAttacks {
+    rook: [Magic; 64],
+    bishop: [Magic; 64],
+    table: Vec<u64>,
+}
+
+/// filled on first use from the ray-walk attacks of every occupancy
+static ATTACKS: LazyLock<SlidingAttacks> = LazyLock::new(|| {
+    let mut table = Vec::new();
+    let rook = fill_table(&ROOK_MAGICS, &ROOK_RAYS_DIRECTIONS, &mut table);
+    let bishop = fill_table(&BISHOP_MAGICS, &BISHOP_RAYS_DIRECTIONS, &mut table);
+    SlidingAttacks { rook, bishop, table }
+});
+
+/// squares attacked by a rook on the square (bit index), up to and including the
+/// first piece in every direction whatever its color
+pub fn rook_attacks(index: usize, occupied: u64) -> u64 {
+    let attacks = &*ATTACKS;
+    attacks.table[attacks.rook[index].index(occupied)]
+}
+
+/// squares attacked by a bishop on the square (bit index), see [`rook_attacks`]
+pub fn bishop_attacks(index: usize, occupied: u64) -> u64 {
+    let attacks = &*ATTACKS;
+    attacks.table[attacks.bishop[index].index(occupied)]
+}
+
+/// squares whose occupancy changes the attacks: the rays without the last square on
+/// the edge, a piece there doesn't block anything
+fn relevant_mask(index: usize, directions: &[usize]) -> u64 {
+    directions
+        .iter()
+        .map(|&dir| {
+            let ray = QUEEN_RAYS[index][dir];
+            // rays going down or left run towards the lower bits
+            let edge = if ray == 0 {
+                0
+            } else if matches!(dir, DOWN | DOWN_LEFT | DOWN_RIGHT | LEFT) {
+                ray & ray.wrapping_neg()
+            } else {
+                1 << (63 - ray.leading_zeros())
+            };
+            ray & !edge
+        })
+        .fold(0, |mask, ray| mask | ray)
+}
+
+/// appends the attacks of every square and every subset of its relevant mask to
+/// `table`, at the slot given by the magic number
+fn fill_table(magics: &[u64; 64], directions: &[usize], table: &mut Vec<u64>) -> [Magic; 64] {
+    let mut squares = [Magic::default(); 64];
+    for (index, square) in squares.iter_mut().enumerate() {
+        let mask = relevant_mask(index, directions);
+        let bits = mask.count_ones();
+        *square = Magic { mask, magic: magics[index], shift: 64 - bits, offset: table.len() };
+        table.resize(table.len() + (1 << bits), 0);
+
+        // enumerate the subsets of the mask (carry-rippler)
+        let mut subset = 0u64;
+        loop {
+            table[square.index(subset)] = compute_sliding_moves(1 << index, directions, 0, subset);
+            subset = subset.wrapping_sub(mask) & mask;
+            if subset == 0 {
+                break;
+            }
+        }
+    }
+    squares
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::engine::moves::QUEEN_RAYS_DIRECTIONS;
+    use crate::engine::rng::Rng;
+
+    #[test]
+    fn test_relevant_mask() {
+        // rook on a1: a2-a7 and b1-g1
+        assert_eq!(0x0001_0101_0101_017E, relevant_mask(0, &ROOK_RAYS_DIRECTIONS));
+        // bishop on d4: inner diagonals only
+        assert_eq!(9, relevant_mask(27, &BISHOP_RAYS_DIRECTIONS).count_ones());
+        assert_eq!(12, relevant_mask(0, &ROOK_RAYS_DIRECTIONS).count_ones());
+        assert_eq!(10, relevant_mask(27, &ROOK_RAYS_DIRECTIONS).count_ones());
+    }
+
+    #[test]
+    fn test_attacks_match_ray_walk() {
+        let mut rng = Rng::new(533);
+        for index in 0..64 {
+            let square = 1u64 << index;
+            for sparse in 0..200 {
+                // from nearly empty to crowded boards
+                let mut occupied = rng.next_u64();
+                for _ in 0..sparse % 4 {
+                    occupied &= rng.next_u64();
+                }
+                assert_eq!(
+                    compute_sliding_moves(square, &ROOK_RAYS_DIRECTIONS, 0, occupied),
+                    rook_attacks(index, occupied),
+                    "rook {} {:#x}",
+                    index,
+                    occupied
+                );
+                assert_eq!(
+                    compute_sliding_moves(square, &BISHOP_RAYS_DIRECTIONS, 0, occupied),
+                    bishop_attacks(index, occupied),
+                    "bishop {} {:#x}",
+                    index,
+                    occupied
+                );
+                assert_eq!(
+                    compute_sliding_moves(square, &QUEEN_RAYS_DIRECTIONS, 0, occupied),
+                    rook_attacks(index, occupied) | bishop_attacks(index, occupied)
+                );
+            }
+            assert_eq!(compute_sliding_moves(square, &ROOK_RAYS_DIRECTIONS, 0, 0), rook_attacks(index, 0));
+            assert_eq!(compute_sliding_moves(square, &BISHOP_RAYS_DIRECTIONS, 0, !0), bishop_attacks(index, !0));
+        }
+    }
+}
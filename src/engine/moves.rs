@@ -2,7 +2,8 @@ use crate::engine::board::{
     bitboard_single, is_file, is_rank, Board, MASK_FILE_A, MASK_FILE_B, MASK_FILE_G, MASK_FILE_H,
     MASK_RANK_2, MASK_RANK_7,
 };
-use crate::engine::parser::ParsedMove;
+use crate::engine::magic::{bishop_attacks, rook_attacks};
+use crate::engine::parser::{ParsedMove, Piece};
 use crate::precompute_moves;
 /// move generation related, only generate pseudo-legal moves which ensure that
 /// moves are within bounds, exclude friendly pieces and exclude blocked pieces
@@ -76,8 +77,9 @@ pub fn compute_pawns_moves(board: &Board, is_white: bool) -> (u64, u64) {
     while pawns != 0 {
         let index = pawns.trailing_zeros() as usize;
 
-        // add pawn's precomputed moves and exclude own piece
-        moves |= precomputed_moves[index][0] & !own_pieces;
+        // add pawn's precomputed moves and exclude own piece. Blocked moves are removed
+        // from this pawn's moves only, another pawn may still reach the same square
+        let mut pawn_moves = precomputed_moves[index][0] & !own_pieces;
         attack_moves |= precomputed_moves[index][1] & !own_pieces;
 
         // additional check for double move only for rank 2 for white
@@ -87,11 +89,11 @@ pub fn compute_pawns_moves(board: &Board, is_white: bool) -> (u64, u64) {
             let rank4_free = (1u64 << (index + 16)) & board.free;
             if rank3_free == 0 {
                 // if rank3 is blocked, remove rank 3 and rank 4
-                moves &= !(1u64 << (index + 8));
-                moves &= !(1u64 << (index + 16));
+                pawn_moves &= !(1u64 << (index + 8));
+                pawn_moves &= !(1u64 << (index + 16));
             } else if rank4_free == 0 {
                 // if only rank 4 is blocked, remove rank 4
-                moves &= !(1u64 << (index + 16));
+                pawn_moves &= !(1u64 << (index + 16));
             }
         } else if !is_white && index >= 48 && index <= 55 {
             // Check if both rank 6 and rank 5 squares are free
@@ -99,13 +101,14 @@ pub fn compute_pawns_moves(board: &Board, is_white: bool) -> (u64, u64) {
             let rank5_free = (1u64 << (index - 16)) & board.free;
             if rank6_free == 0 {
                 // if rank 6 is blocked, remove both rank 6 and 5
-                moves &= !(1u64 << (index - 16));
-                moves &= !(1u64 << (index - 8));
+                pawn_moves &= !(1u64 << (index - 16));
+                pawn_moves &= !(1u64 << (index - 8));
             } else if rank5_free == 0 {
                 // If rank 5 is blocked, remove only the rank 5 move from precomputed moves
-                moves &= !(1u64 << (index - 16));
+                pawn_moves &= !(1u64 << (index - 16));
             }
         }
+        moves |= pawn_moves;
 
         // Remove the processed pawns (use lsb approach)
         pawns &= pawns - 1;
@@ -234,7 +237,9 @@ const fn precompute_rook_rays(index: u8) -> [u64; 4] {
     [top, right, bottom, left]
 }
 
-fn compute_sliding_moves(
+/// walks every ray until the first blocker, used to build and check the magic
+/// attack tables, move generation uses [`compute_magic_moves`]
+pub fn compute_sliding_moves(
     mut pieces: u64,
     directions: &[usize],
     own_pieces: u64,
@@ -265,6 +270,22 @@ fn compute_sliding_moves(
     moves
 }
 
+/// moves of all the `pieces` using the magic attack lookup (e.g. [`rook_attacks`]),
+/// a blocker is included when it isn't one of `own_pieces`
+pub fn compute_magic_moves(
+    mut pieces: u64,
+    attacks: fn(usize, u64) -> u64,
+    own_pieces: u64,
+    occupied: u64,
+) -> u64 {
+    let mut moves = 0u64;
+    while pieces != 0 {
+        moves |= attacks(pieces.trailing_zeros() as usize, occupied);
+        pieces &= pieces - 1;
+    }
+    moves & !own_pieces
+}
+
 pub fn compute_rooks_moves(board: &Board, is_white: bool) -> u64 {
     let mut moves = 0u64;
     let own_pieces: u64;
@@ -278,7 +299,7 @@ pub fn compute_rooks_moves(board: &Board, is_white: bool) -> u64 {
         own_pieces = board.black_pieces;
     };
 
-    compute_sliding_moves(rooks, &ROOK_RAYS_DIRECTIONS, own_pieces, occupied)
+    compute_magic_moves(rooks, rook_attacks, own_pieces, occupied)
 }
 
 const fn precompute_bishop_rays(index: u8) -> [u64; 4] {
@@ -341,7 +362,7 @@ pub fn compute_bishops_moves(board: &Board, is_white: bool) -> u64 {
         own_pieces = board.black_pieces;
     };
 
-    compute_sliding_moves(bishops, &BISHOP_RAYS_DIRECTIONS, own_pieces, occupied)
+    compute_magic_moves(bishops, bishop_attacks, own_pieces, occupied)
 }
 
 // clockwise direction
@@ -371,7 +392,33 @@ pub fn compute_queens_moves(board: &Board, is_white: bool) -> u64 {
         own_pieces = board.black_pieces;
     };
 
-    compute_sliding_moves(queens, &QUEEN_RAYS_DIRECTIONS, own_pieces, occupied)
+    compute_magic_moves(queens, rook_attacks, own_pieces, occupied)
+        | compute_magic_moves(queens, bishop_attacks, own_pieces, occupied)
+}
+
+/// squares strictly between two squares on the same rank, file or diagonal, 0 when
+/// they aren't aligned or are next to each other
+pub static BETWEEN: [[u64; 64]; 64] = precompute_between();
+
+const fn precompute_between() -> [[u64; 64]; 64] {
+    let mut between = [[0u64; 64]; 64];
+    let mut from = 0;
+    while from < 64 {
+        let mut dir = 0;
+        while dir < 8 {
+            let ray = QUEEN_RAYS[from][dir];
+            let mut targets = ray;
+            while targets != 0 {
+                let to = targets.trailing_zeros() as usize;
+                // the ray from `from` meets the opposite ray from `to` in between
+                between[from][to] = ray & QUEEN_RAYS[to][(dir + 4) % 8];
+                targets &= targets - 1;
+            }
+            dir += 1;
+        }
+        from += 1;
+    }
+    between
 }
 
 pub const KING_MOVES: [u64; 64] = precompute_moves!(precompute_king_moves);
@@ -408,19 +455,49 @@ pub fn compute_king_moves(board: &Board, is_white: bool) -> u64 {
     moves
 }
 
-// pawn source will always be resolvable
+/// pseudolegal moves for a single piece at `from` (pins and checks are not
+/// considered). Unlike the combined pawn moves, pawns only include pushes to free
+/// squares and diagonal captures of opponent pieces (en passant excluded)
+pub fn compute_piece_moves(board: &Board, piece: Piece, from: u64, is_white: bool) -> u64 {
+    let (own_pieces, opponent_pieces) = if is_white {
+        (board.white_pieces, board.black_pieces)
+    } else {
+        (board.black_pieces, board.white_pieces)
+    };
+    let index = from.trailing_zeros() as usize;
+
+    match piece {
+        Piece::Pawn => {
+            let (pawn_moves, single_push) = if is_white {
+                (WHITE_PAWN_MOVES[index], from << 8)
+            } else {
+                (BLACK_PAWN_MOVES[index], from >> 8)
+            };
+            // precomputed moves include the diagonals, exclude them for pushes
+            let mut pushes = pawn_moves[0] & !pawn_moves[1] & board.free;
+            // double push is only possible if the single push square is free
+            if single_push & board.free == 0 {
+                pushes = 0;
+            }
+            pushes | (pawn_moves[1] & opponent_pieces)
+        }
+        Piece::Knight => KNIGHT_MOVES[index] & !own_pieces,
+        Piece::Rook => rook_attacks(index, board.occupied) & !own_pieces,
+        Piece::Bishop => bishop_attacks(index, board.occupied) & !own_pieces,
+        Piece::Queen => (rook_attacks(index, board.occupied) | bishop_attacks(index, board.occupied)) & !own_pieces,
+        Piece::King | Piece::Castling => KING_MOVES[index] & !own_pieces,
+    }
+}
+
+// returns the pawn moving to the target, 0 when there is none (e.g. a capture without
+// the source file or without a pawn on it)
 pub fn resolve_pawn_source(board: &Board, parsed_move: &ParsedMove, is_white: bool) -> u64 {
     let target_rank: u64 = (parsed_move.to.trailing_zeros() / 8) as u64 + 1;
     // determine from
     if is_white {
         if parsed_move.is_capture {
             // find the target rank, move 1 step backward
-            let rank = target_rank - 1;
-            if rank <= 0 {
-                0
-            } else {
-                bitboard_single(parsed_move.from_file.unwrap(), rank).unwrap() & board.white_pawns
-            }
+            pawn_capture_source(parsed_move.from_file, target_rank - 1) & board.white_pawns
         } else {
             let one_step = parsed_move.to >> 8 & board.white_pawns;
 
@@ -435,12 +512,7 @@ pub fn resolve_pawn_source(board: &Board, parsed_move: &ParsedMove, is_white: bo
     } else {
         if parsed_move.is_capture {
             // find the target rank, move 1 step backward
-            let rank = target_rank + 1;
-            if rank >= 8 {
-                0
-            } else {
-                bitboard_single(parsed_move.from_file.unwrap(), rank).unwrap() & board.black_pawns
-            }
+            pawn_capture_source(parsed_move.from_file, target_rank + 1) & board.black_pawns
         } else {
             let one_step = parsed_move.to << 8 & board.black_pawns;
             // figure out from either 1 step or 2 steps backwards if target rank is 5 only
@@ -454,6 +526,11 @@ pub fn resolve_pawn_source(board: &Board, parsed_move: &ParsedMove, is_white: bo
     }
 }
 
+// square a pawn captures from, 0 without a file or off the board
+fn pawn_capture_source(from_file: Option<char>, rank: u64) -> u64 {
+    from_file.and_then(|file| bitboard_single(file, rank)).unwrap_or(0)
+}
+
 // returns bitboard of the potential source, this means there may be more than 1 knight
 pub fn resolve_knight_source(board: &Board, parsed_move: &ParsedMove, is_white: bool) -> u64 {
     let mut knights = if is_white {
@@ -1347,7 +1424,16 @@ pub mod tests {
         assert_eq!(
             bitboard_single('a', 2).unwrap(),
             resolve_pawn_source(&board, &parse_move("a4").unwrap(), true)
-        )
+        );
+
+        // no pawn on the capturing file, or no file at all
+        assert_eq!(0, resolve_pawn_source(&board, &parse_move("fxg3").unwrap(), true));
+        assert_eq!(0, resolve_pawn_source(&board, &parse_move("cxd2").unwrap(), false));
+        let no_file = ParsedMove { from_file: None, ..parse_move("exd3").unwrap() };
+        assert_eq!(0, resolve_pawn_source(&board, &no_file, true));
+        assert_eq!(0, resolve_pawn_source(&board, &no_file, false));
+        // capturing onto the first rank has no source rank
+        assert_eq!(0, resolve_pawn_source(&board, &parse_move("bxa1").unwrap(), true));
     }
 
     #[test]
@@ -1665,4 +1751,23 @@ pub mod tests {
             );
         }
     }
+    #[test]
+    fn test_between() {
+        let idx = |file, rank| bitboard_single(file, rank).unwrap().trailing_zeros() as usize;
+        let squares = |squares: &[(char, u64)]| {
+            squares.iter().fold(PositionBuilder::new(), |builder, &(file, rank)| builder.add_piece(file, rank)).build()
+        };
+        // file, rank and both diagonals, in both directions
+        assert_eq!(squares(&[('e', 2), ('e', 3), ('e', 4), ('e', 5), ('e', 6), ('e', 7)]), BETWEEN[idx('e', 1)][idx('e', 8)]);
+        assert_eq!(BETWEEN[idx('e', 1)][idx('e', 8)], BETWEEN[idx('e', 8)][idx('e', 1)]);
+        assert_eq!(squares(&[('b', 4), ('c', 4)]), BETWEEN[idx('d', 4)][idx('a', 4)]);
+        assert_eq!(squares(&[('b', 2), ('c', 3), ('d', 4), ('e', 5), ('f', 6), ('g', 7)]), BETWEEN[idx('a', 1)][idx('h', 8)]);
+        assert_eq!(squares(&[('g', 2)]), BETWEEN[idx('f', 3)][idx('h', 1)]);
+        // next to each other, not aligned or the same square
+        assert_eq!(0, BETWEEN[idx('e', 1)][idx('e', 2)]);
+        assert_eq!(0, BETWEEN[idx('e', 1)][idx('f', 3)]);
+        assert_eq!(0, BETWEEN[idx('a', 1)][idx('h', 2)]);
+        assert_eq!(0, BETWEEN[idx('h', 1)][idx('a', 2)]);
+        assert_eq!(0, BETWEEN[idx('d', 4)][idx('d', 4)]);
+    }
 }
@@ -0,0 +1,137 @@
+/// small table of well known openings, each line is the SAN move sequence
+/// (without check symbols) from the start position
+const OPENINGS: [(&str, &str); 42] = [
+    ("King's Pawn Opening", "e4"),
+    ("Queen's Pawn Opening", "d4"),
+    ("English Opening", "c4"),
+    ("Zukertort Opening", "Nf3"),
+    ("Bird's Opening", "f4"),
+    ("Reti Opening", "Nf3 d5 c4"),
+    ("King's Pawn Game", "e4 e5"),
+    ("King's Knight Opening", "e4 e5 Nf3"),
+    ("Petrov's Defense", "e4 e5 Nf3 Nf6"),
+    ("Philidor Defense", "e4 e5 Nf3 d6"),
+    ("Ruy Lopez", "e4 e5 Nf3 Nc6 Bb5"),
+    ("Ruy Lopez, Morphy Defense", "e4 e5 Nf3 Nc6 Bb5 a6"),
+    ("Ruy Lopez, Berlin Defense", "e4 e5 Nf3 Nc6 Bb5 Nf6"),
+    ("Italian Game", "e4 e5 Nf3 Nc6 Bc4"),
+    ("Giuoco Piano", "e4 e5 Nf3 Nc6 Bc4 Bc5"),
+    ("Two Knights Defense", "e4 e5 Nf3 Nc6 Bc4 Nf6"),
+    ("Scotch Game", "e4 e5 Nf3 Nc6 d4"),
+    ("Four Knights Game", "e4 e5 Nf3 Nc6 Nc3 Nf6"),
+    ("Vienna Game", "e4 e5 Nc3"),
+    ("King's Gambit", "e4 e5 f4"),
+    ("King's Gambit Accepted", "e4 e5 f4 exf4"),
+    ("Bishop's Opening", "e4 e5 Bc4"),
+    ("Sicilian Defense", "e4 c5"),
+    ("Sicilian Defense, Najdorf Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6"),
+    ("Sicilian Defense, Dragon Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6"),
+    ("French Defense", "e4 e6"),
+    ("Caro-Kann Defense", "e4 c6"),
+    ("Scandinavian Defense", "e4 d5"),
+    ("Alekhine's Defense", "e4 Nf6"),
+    ("Modern Defense", "e4 g6"),
+    ("Pirc Defense", "e4 d6 d4 Nf6 Nc3 g6"),
+    ("Queen's Gambit", "d4 d5 c4"),
+    ("Queen's Gambit Accepted", "d4 d5 c4 dxc4"),
+    ("Queen's Gambit Declined", "d4 d5 c4 e6"),
+    ("Slav Defense", "d4 d5 c4 c6"),
+    ("London System", "d4 d5 Bf4"),
+    ("Indian Defense", "d4 Nf6"),
+    ("King's Indian Defense", "d4 Nf6 c4 g6"),
+    ("Grunfeld Defense", "d4 Nf6 c4 g6 Nc3 d5"),
+    ("Nimzo-Indian Defense", "d4 Nf6 c4 e6 Nc3 Bb4"),
+    ("Queen's Indian Defense", "d4 Nf6 c4 e6 Nf3 b6"),
+    ("Dutch Defense", "d4 f5"),
+];
+
+/// SAN line of the opening with the given name (case insensitive)
+pub fn line(name: &str) -> Option<&'static str> {
+    OPENINGS
+        .iter()
+        .find(|(opening, _)| opening.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, line)| *line)
+}
+
+/// returns the name of the most specific opening matching the moves played so
+/// far (SAN, check symbols are ignored). Returns `None` before the first move or
+/// once the game leaves the known lines
+pub fn identify(moves: &[&str]) -> Option<&'static str> {
+    let moves: Vec<&str> = moves
+        .iter()
+        .map(|mv| mv.trim_end_matches(['+', '#']))
+        .collect();
+
+    let mut in_theory = false;
+    let mut opening = None;
+    let mut opening_len = 0;
+
+    for (name, line) in OPENINGS {
+        let line: Vec<&str> = line.split_whitespace().collect();
+
+        // still following a known line
+        if moves.len() <= line.len() && line[..moves.len()] == moves[..] {
+            in_theory = true;
+        }
+
+        // longest known line reached so far
+        if line.len() <= moves.len() && moves[..line.len()] == line[..] && line.len() > opening_len {
+            opening = Some(name);
+            opening_len = line.len();
+        }
+    }
+
+    if in_theory {
+        opening
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identify() {
+        assert_eq!(None, identify(&[]));
+        assert_eq!(Some("King's Pawn Opening"), identify(&["e4"]));
+        assert_eq!(Some("Sicilian Defense"), identify(&["e4", "c5"]));
+        assert_eq!(Some("Sicilian Defense"), identify(&["e4", "c5", "Nf3", "d6"]));
+        assert_eq!(
+            Some("Ruy Lopez"),
+            identify(&["e4", "e5", "Nf3", "Nc6", "Bb5"])
+        );
+        assert_eq!(
+            Some("Ruy Lopez, Morphy Defense"),
+            identify(&["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"])
+        );
+        assert_eq!(
+            Some("Queen's Gambit Declined"),
+            identify(&["d4", "d5", "c4", "e6"])
+        );
+        assert_eq!(
+            Some("Sicilian Defense, Najdorf Variation"),
+            identify(&["e4", "c5", "Nf3", "d6", "d4", "cxd4", "Nxd4", "Nf6", "Nc3", "a6"])
+        );
+    }
+
+    #[test]
+    fn test_line() {
+        assert_eq!(Some("e4 e5 Nf3 Nc6 Bb5"), line("Ruy Lopez"));
+        assert_eq!(Some("e4 e5 Nf3 Nc6 Bb5"), line(" ruy lopez "));
+        assert_eq!(None, line("Ruy"));
+    }
+
+    #[test]
+    fn test_identify_out_of_theory() {
+        // check symbols are ignored
+        assert_eq!(Some("King's Gambit Accepted"), identify(&["e4", "e5", "f4", "exf4"]));
+        assert_eq!(Some("Bishop's Opening"), identify(&["e4", "e5", "Bc4+"]));
+
+        // game left the known lines
+        assert_eq!(None, identify(&["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4"]));
+        assert_eq!(None, identify(&["a3"]));
+        assert_eq!(None, identify(&["e4", "h5"]));
+    }
+}
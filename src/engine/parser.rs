@@ -38,10 +38,34 @@ pub struct ParsedMove {
     pub special_move: Option<SpecialMove>,
 }
 
+/// check marker at the end of a SAN move (`+` or `#`)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CheckSuffix {
+    Check,
+    Checkmate,
+}
+
+/// splits the check marker and annotations (`!`, `?`, e.g. `Qxf7#`, `e4!?`) off a
+/// SAN move, returns the bare move and the check marker if any
+pub fn split_suffix(cmd: &str) -> (&str, Option<CheckSuffix>) {
+    let cmd = cmd.trim_end_matches(['!', '?']);
+    let suffix = match cmd.chars().last() {
+        Some('+') => Some(CheckSuffix::Check),
+        Some('#') => Some(CheckSuffix::Checkmate),
+        _ => None,
+    };
+    (cmd.trim_end_matches(['+', '#']), suffix)
+}
+
 /// parses PGN moves, there is no validation of the move. All validations are
 /// done on game.rs (this includes promotion logic)
 /// It is only responsible to make sure the string is a correct PGN format
+///
+/// Promotion is accepted both with and without `=` (`e8=Q`, `e8Q`, `hxg8=Q`, `hxg8Q`),
+/// other forms such as `e8(Q)` or `e8/Q` are rejected. Check markers and
+/// annotations are ignored, see [`split_suffix`]
 pub fn parse_move(cmd: &str) -> Result<ParsedMove, ParseError> {
+    let (cmd, _) = split_suffix(cmd);
     if cmd.len() <= 1 {
         // invalid
         return Err(ParseError::InvalidLength);
@@ -243,6 +267,7 @@ fn parse_pawn(source: char, mut chars: Chars) -> Result<ParsedMove, ParseError>
         TargetParsed,
         Capturing,
         PromotionPiece,
+        Promoted,
     }
 
     let mut state = PawnParserState::Initial;
@@ -289,21 +314,19 @@ fn parse_pawn(source: char, mut chars: Chars) -> Result<ParsedMove, ParseError>
                 '=' => {
                     state = PawnParserState::PromotionPiece;
                 }
+                // promotion without `=` (e.g. e8Q)
                 _ => {
-                    return Err(ParseError::InvalidTarget);
+                    special_move = Some(SpecialMove::Promotion(parse_promotion_piece(c)?));
+                    state = PawnParserState::Promoted;
                 }
             },
             PawnParserState::PromotionPiece => {
-                let promotion = match c {
-                    'N' => Piece::Knight,
-                    'R' => Piece::Rook,
-                    'B' => Piece::Bishop,
-                    'Q' => Piece::Queen,
-                    _ => {
-                        return Err(ParseError::InvalidTarget);
-                    }
-                };
-                special_move = Some(SpecialMove::Promotion(promotion));
+                special_move = Some(SpecialMove::Promotion(parse_promotion_piece(c)?));
+                state = PawnParserState::Promoted;
+            }
+            PawnParserState::Promoted => {
+                // nothing is allowed after the promotion piece
+                return Err(ParseError::InvalidTarget);
             }
         }
     }
@@ -326,6 +349,45 @@ fn parse_pawn(source: char, mut chars: Chars) -> Result<ParsedMove, ParseError>
     })
 }
 
+#[derive(Debug, PartialEq)]
+pub struct UciMove {
+    pub from: u64,
+    pub to: u64,
+    pub promotion: Option<Piece>,
+}
+
+/// parses UCI coordinate moves (`e2e4`, `e7e8q`, castling as the king's move `e1g1`).
+/// Like [`parse_move`] only the format is checked, legality is done on game.rs
+pub fn parse_uci(cmd: &str) -> Result<UciMove, ParseError> {
+    let chars: Vec<char> = cmd.chars().collect();
+    if chars.len() != 4 && chars.len() != 5 {
+        return Err(ParseError::InvalidLength);
+    }
+
+    let square = |file: char, rank: char| match rank {
+        '1'..='8' => bitboard_single(file, rank.to_digit(10).unwrap() as u64),
+        _ => None,
+    };
+    let from = square(chars[0], chars[1]).ok_or(ParseError::InvalidSource)?;
+    let to = square(chars[2], chars[3]).ok_or(ParseError::InvalidTarget)?;
+    let promotion = match chars.get(4) {
+        Some(c) => Some(parse_promotion_piece(c.to_ascii_uppercase())?),
+        None => None,
+    };
+
+    Ok(UciMove { from, to, promotion })
+}
+
+fn parse_promotion_piece(c: char) -> Result<Piece, ParseError> {
+    match c {
+        'N' => Ok(Piece::Knight),
+        'R' => Ok(Piece::Rook),
+        'B' => Ok(Piece::Bishop),
+        'Q' => Ok(Piece::Queen),
+        _ => Err(ParseError::InvalidTarget),
+    }
+}
+
 fn parse_source(c: char) -> Result<Piece, ParseError> {
     match c {
         'a'..='h' => Ok(Piece::Pawn),
@@ -495,6 +557,39 @@ pub mod tests {
         assert_eq!(Err(ParseError::InvalidTarget), parse_move("h8=O"));
     }
 
+    #[test]
+    fn test_parse_pawn_promotion_without_equal_sign() {
+        assert_eq!(parse_move("e8=Q").unwrap(), parse_move("e8Q").unwrap());
+        assert_eq!(
+            Some(SpecialMove::Promotion(Piece::Queen)),
+            parse_move("e8Q").unwrap().special_move
+        );
+
+        assert_eq!(parse_move("hxg8=Q").unwrap(), parse_move("hxg8Q").unwrap());
+        assert_eq!(
+            ParsedMove {
+                piece: Piece::Pawn,
+                from_file: Some('h'),
+                from_rank: None,
+                to: bitboard_single('g', 8).unwrap(),
+                is_capture: true,
+                special_move: Some(SpecialMove::Promotion(Piece::Queen)),
+            },
+            parse_move("hxg8Q").unwrap()
+        );
+        assert_eq!(
+            Some(SpecialMove::Promotion(Piece::Knight)),
+            parse_move("d1N").unwrap().special_move
+        );
+
+        assert_eq!(Err(ParseError::InvalidTarget), parse_move("e8=X"));
+        assert_eq!(Err(ParseError::InvalidTarget), parse_move("e8X"));
+        assert_eq!(Err(ParseError::InvalidTarget), parse_move("e8K"));
+        assert_eq!(Err(ParseError::InvalidTarget), parse_move("e8(Q)"));
+        assert_eq!(Err(ParseError::InvalidTarget), parse_move("e8=QQ"));
+        assert_eq!(Err(ParseError::InvalidTarget), parse_move("e8Q="));
+    }
+
     #[test]
     fn test_parse_castling() {
         assert_eq!(
@@ -704,6 +799,48 @@ pub mod tests {
         assert_eq!(Err(ParseError::InvalidTarget), parse_move("Ke2xe3"));
     }
 
+    #[test]
+    fn test_split_suffix() {
+        assert_eq!(("e4", None), split_suffix("e4"));
+        assert_eq!(("e4", None), split_suffix("e4!?"));
+        assert_eq!(("Nf3", Some(CheckSuffix::Check)), split_suffix("Nf3+"));
+        assert_eq!(("Qxf7", Some(CheckSuffix::Checkmate)), split_suffix("Qxf7#"));
+        assert_eq!(("O-O-O", Some(CheckSuffix::Check)), split_suffix("O-O-O+!!"));
+        assert_eq!(("e8=Q", Some(CheckSuffix::Checkmate)), split_suffix("e8=Q#?"));
+
+        assert_eq!(parse_move("Qxf7").unwrap(), parse_move("Qxf7#").unwrap());
+        assert_eq!(parse_move("O-O").unwrap(), parse_move("O-O+").unwrap());
+        assert_eq!(parse_move("exd8=N").unwrap(), parse_move("exd8=N+?!").unwrap());
+        assert_eq!(Err(ParseError::InvalidLength), parse_move("+"));
+        assert!(parse_move("Nf3+x").is_err());
+    }
+
+    #[test]
+    fn test_parse_uci() {
+        assert_eq!(
+            UciMove {
+                from: bitboard_single('e', 2).unwrap(),
+                to: bitboard_single('e', 4).unwrap(),
+                promotion: None,
+            },
+            parse_uci("e2e4").unwrap()
+        );
+        assert_eq!(
+            UciMove {
+                from: bitboard_single('a', 7).unwrap(),
+                to: bitboard_single('b', 8).unwrap(),
+                promotion: Some(Piece::Knight),
+            },
+            parse_uci("a7b8n").unwrap()
+        );
+
+        assert_eq!(Err(ParseError::InvalidLength), parse_uci("e4"));
+        assert_eq!(Err(ParseError::InvalidLength), parse_uci("e7e8qq"));
+        assert_eq!(Err(ParseError::InvalidSource), parse_uci("i2e4"));
+        assert_eq!(Err(ParseError::InvalidTarget), parse_uci("e2e9"));
+        assert_eq!(Err(ParseError::InvalidTarget), parse_uci("e7e8k"));
+    }
+
     #[test]
     fn test_parse_source() {
         assert_eq!(Ok(Piece::Pawn), parse_source('a'));
@@ -0,0 +1,139 @@
+use crate::engine::game::{Game, LoadError};
+
+impl Game {
+    /// replays a PGN game through [`Game::process_move`], starting from the `FEN` tag
+    /// if there is one. Other tags, comments (`{...}` and `;`), variations, move
+    /// numbers, NAGs and the result are skipped, `0-0` is read as `O-O`
+    pub fn from_pgn(pgn: &str) -> Result<Game, LoadError> {
+        let (fen, movetext) = split_tags(pgn);
+        let mut game = match fen {
+            Some(fen) => Game::from_fen(&fen).map_err(LoadError::InvalidFen)?,
+            None => Game::default(),
+        };
+        for san in movetext_tokens(&movetext) {
+            if let Err(error) = game.process_move(&san) {
+                return Err(LoadError::IllegalMove {
+                    move_number: game.turn.div_ceil(2),
+                    san,
+                    error,
+                });
+            }
+        }
+        Ok(game)
+    }
+}
+
+/// value of the `FEN` tag and the text outside the tag pairs
+fn split_tags(pgn: &str) -> (Option<String>, String) {
+    let mut fen = None;
+    let mut movetext = String::new();
+    for line in pgn.lines() {
+        let tag = line.trim();
+        if tag.starts_with('[') && tag.ends_with(']') {
+            if let Some(("FEN", value)) = tag[1..tag.len() - 1].trim().split_once(char::is_whitespace) {
+                fen = Some(value.trim().trim_matches('"').to_string());
+            }
+        } else {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+    (fen, movetext)
+}
+
+/// SAN tokens of the movetext without comments, variations, move numbers, NAGs and
+/// the result, castling written with zeros is converted to `O-O`
+fn movetext_tokens(movetext: &str) -> Vec<String> {
+    let mut text = String::new();
+    let mut chars = movetext.chars();
+    let mut depth = 0; // variation nesting
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                chars.by_ref().find(|&c| c == '}');
+                text.push(' ');
+            }
+            ';' => {
+                chars.by_ref().find(|&c| c == '\n');
+                text.push(' ');
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                text.push(' ');
+            }
+            _ if depth > 0 => {}
+            _ => text.push(c),
+        }
+    }
+
+    text.split_whitespace()
+        .filter(|token| !["1-0", "0-1", "1/2-1/2", "*"].contains(token))
+        .map(strip_move_number)
+        .filter(|token| !token.is_empty() && !token.starts_with('$'))
+        .map(|token| if token.starts_with("0-0") { token.replace('0', "O") } else { token.to_string() })
+        .collect()
+}
+
+/// removes a move number, it can be attached to the move (e.g. `12.e4`, `12...Nf6`)
+fn strip_move_number(token: &str) -> &str {
+    let after_digits = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    if after_digits.len() < token.len() && after_digits.starts_with('.') {
+        after_digits.trim_start_matches('.')
+    } else {
+        token
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::engine::game::Status;
+
+    #[test]
+    fn test_movetext_tokens() {
+        assert_eq!(
+            vec!["e4", "e5", "Nf3", "Nc6", "O-O"],
+            movetext_tokens("1. e4 {best by test} e5 $1 2.Nf3 (2. f4 exf4 (2... d5)) 2...Nc6 ; comment\n3.0-0 1-0")
+        );
+        assert!(movetext_tokens("*").is_empty());
+        assert_eq!(vec!["Kd7"], movetext_tokens("12... Kd7 1/2-1/2"));
+    }
+
+    #[test]
+    fn test_from_pgn() {
+        let pgn = "[Event \"Paris\"]\n\
+                   [Site \"Paris FRA\"]\n\
+                   [Result \"1-0\"]\n\
+                   \n\
+                   1. e4 e5 2. Nf3 d6 3. d4 Bg4?! {This is a weak move already.} 4. dxe5 Bxf3 5. Qxf3\n\
+                   dxe5 6. Bc4 Nf6? 7. Qb3! Qe7 8. Nc3 c6 9. Bg5 b5?! 10. Nxb5! cxb5 11. Bxb5+ Nbd7\n\
+                   12. 0-0-0 Rd8 13. Rxd7! Rxd7 14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+!! Nxb8 17. Rd8# 1-0\n";
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(Status::Checkmate, game.status());
+        assert_eq!(33, game.history().len());
+        assert_eq!("O-O-O", game.history()[22].san);
+
+        // round trip with a custom start position
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 12").unwrap();
+        game.process_move("Kd7").unwrap();
+        game.process_move("e4").unwrap();
+        let imported = Game::from_pgn(&game.to_pgn()).unwrap();
+        assert_eq!(game.to_fen(), imported.to_fen());
+        assert_eq!(game.to_pgn(), imported.to_pgn());
+
+        let err = Game::from_pgn("1. e4 e5 2. Nf6 Nc6 *").err();
+        assert!(
+            matches!(&err, Some(LoadError::IllegalMove { move_number: 2, san, .. }) if san == "Nf6"),
+            "{:?}",
+            err
+        );
+        assert!(matches!(Game::from_pgn("[FEN \"8/8 w\"]\n*"), Err(LoadError::InvalidFen(_))));
+
+        // only the FEN tag itself sets up the position
+        let game = Game::from_pgn("[FENComment \"x\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n1. e4 *").unwrap();
+        assert_eq!("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1", game.to_fen());
+        let game = Game::from_pgn("[FENComment \"x\"]\n1. e4 *").unwrap();
+        assert_eq!(Game::default().start_fen(), game.start_fen());
+    }
+}
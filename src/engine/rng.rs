@@ -0,0 +1,76 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Small seedable pseudo random number generator (xorshift64*), shared by all
+/// randomized features so a game can be reproduced from its seed
+#[derive(Debug, Clone, Copy)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // scramble the seed with splitmix64 so nearby seeds don't produce
+        // similar sequences, xorshift state must never be 0
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        Rng {
+            state: if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z },
+        }
+    }
+
+    /// seed derived from current time, used when no seed is given
+    pub fn time_seed() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// returns number within 0..upper (upper must be > 0)
+    pub fn next_range(&mut self, upper: usize) -> usize {
+        (self.next_u64() % upper as u64) as usize
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut rng1 = Rng::new(42);
+        let mut rng2 = Rng::new(42);
+        for _ in 0..100 {
+            assert_eq!(rng1.next_u64(), rng2.next_u64());
+        }
+
+        let mut rng3 = Rng::new(43);
+        let sequence1: Vec<u64> = (0..10).map(|_| rng1.next_u64()).collect();
+        let sequence3: Vec<u64> = (0..10).map(|_| rng3.next_u64()).collect();
+        assert_ne!(sequence1, sequence3);
+    }
+
+    #[test]
+    fn test_seed_zero() {
+        let mut rng = Rng::new(0);
+        assert_ne!(0, rng.next_u64());
+    }
+
+    #[test]
+    fn test_next_range() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            assert!(rng.next_range(20) < 20);
+        }
+    }
+}
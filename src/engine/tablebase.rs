@@ -0,0 +1,236 @@
+use crate::engine::board::Board;
+use std::sync::LazyLock;
+
+/// outcome for the side to move
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Wdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// exact result of an endgame position for the side to move. `dtz` is the number of
+/// plies until the next pawn move (or capture) with best play, 0 for draws
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TbResult {
+    pub wdl: Wdl,
+    pub dtz: u8,
+}
+
+/// looks the position up in the endgame tables, `None` when the tables don't cover
+/// it. Only king and pawn against king is known, and only when built with the
+/// `tablebase` feature (the table is generated on the first probe)
+pub fn probe(board: &Board, white_to_move: bool) -> Option<TbResult> {
+    if cfg!(feature = "tablebase") {
+        probe_kpk(board, white_to_move)
+    } else {
+        None
+    }
+}
+
+// positions of the king and pawn against king table, with the pawn side as white and
+// the pawn on files a-d (the other files are mirrored)
+const KPK_SIZE: usize = 24 * 64 * 64 * 2;
+// not a win for white (yet), draws once the table is complete
+const UNKNOWN: u8 = u8::MAX;
+
+static KPK: LazyLock<Vec<u8>> = LazyLock::new(generate_kpk);
+
+fn probe_kpk(board: &Board, white_to_move: bool) -> Option<TbResult> {
+    if board.occupied.count_ones() != 3 {
+        return None;
+    }
+    // pawn side becomes white, black pawns are flipped to the other side of the board
+    let (pawn, strong_king, weak_king, strong_to_move, flip) = if board.white_pawns != 0 {
+        (board.white_pawns, board.white_king, board.black_king, white_to_move, 0)
+    } else if board.black_pawns != 0 {
+        (board.black_pawns, board.black_king, board.white_king, !white_to_move, 56)
+    } else {
+        return None;
+    };
+    let (pawn, strong_king, weak_king) = (
+        pawn.trailing_zeros() as usize ^ flip,
+        strong_king.trailing_zeros() as usize ^ flip,
+        weak_king.trailing_zeros() as usize ^ flip,
+    );
+    if !(8..56).contains(&pawn) {
+        return None;
+    }
+    let mirror = if pawn % 8 > 3 { 7 } else { 0 };
+    let dtz = KPK[kpk_index(pawn ^ mirror, strong_king ^ mirror, weak_king ^ mirror, strong_to_move)];
+    Some(match (dtz, strong_to_move) {
+        (UNKNOWN, _) => TbResult { wdl: Wdl::Draw, dtz: 0 },
+        (dtz, true) => TbResult { wdl: Wdl::Win, dtz },
+        (dtz, false) => TbResult { wdl: Wdl::Loss, dtz },
+    })
+}
+
+// pawn on files a-d of ranks 2-7
+fn kpk_index(pawn: usize, white_king: usize, black_king: usize, white_to_move: bool) -> usize {
+    let pawn = (pawn / 8 - 1) * 4 + pawn % 8;
+    ((pawn * 64 + white_king) * 64 + black_king) * 2 + white_to_move as usize
+}
+
+fn adjacent(a: usize, b: usize) -> bool {
+    (a % 8).abs_diff(b % 8) <= 1 && (a / 8).abs_diff(b / 8) <= 1
+}
+
+fn king_moves(square: usize) -> impl Iterator<Item = usize> {
+    (0..64).filter(move |&to| to != square && adjacent(square, to))
+}
+
+fn pawn_attacks(pawn: usize, square: usize) -> bool {
+    square / 8 == pawn / 8 + 1 && (square % 8).abs_diff(pawn % 8) == 1
+}
+
+/// dtz of every position white wins, the pawn slices are solved from the 7th rank
+/// down since pawn moves only lead to solved slices
+fn generate_kpk() -> Vec<u8> {
+    let mut table = vec![UNKNOWN; KPK_SIZE];
+    for rank in (1..7).rev() {
+        for file in 0..4 {
+            solve_kpk_slice(&mut table, rank * 8 + file);
+        }
+    }
+    table
+}
+
+fn solve_kpk_slice(table: &mut [u8], pawn: usize) {
+    let legal = |white_king: usize, black_king: usize| {
+        white_king != black_king && white_king != pawn && black_king != pawn && !adjacent(white_king, black_king)
+    };
+    let mut changes = true;
+    while changes {
+        changes = false;
+        let mut solved = Vec::new();
+        for white_king in 0..64 {
+            for black_king in 0..64 {
+                if !legal(white_king, black_king) {
+                    continue;
+                }
+                // black can't be in check with white to move
+                if table[kpk_index(pawn, white_king, black_king, true)] == UNKNOWN && !pawn_attacks(pawn, black_king) {
+                    let dtz = white_kpk_dtz(table, pawn, white_king, black_king);
+                    if dtz != UNKNOWN {
+                        solved.push((kpk_index(pawn, white_king, black_king, true), dtz));
+                    }
+                }
+                if table[kpk_index(pawn, white_king, black_king, false)] == UNKNOWN {
+                    let dtz = black_kpk_dtz(table, pawn, white_king, black_king);
+                    if dtz != UNKNOWN {
+                        solved.push((kpk_index(pawn, white_king, black_king, false), dtz));
+                    }
+                }
+            }
+        }
+        for (index, dtz) in solved {
+            table[index] = dtz;
+            changes = true;
+        }
+    }
+}
+
+// 1 when a pawn move wins, otherwise one more than the quickest king move to a lost
+// position for black
+fn white_kpk_dtz(table: &[u8], pawn: usize, white_king: usize, black_king: usize) -> u8 {
+    let push = pawn + 8;
+    if push != white_king && push != black_king {
+        if push >= 56 {
+            // the new queen wins unless black takes it
+            if !adjacent(black_king, push) || adjacent(white_king, push) {
+                return 1;
+            }
+        } else if table[kpk_index(push, white_king, black_king, false)] != UNKNOWN {
+            return 1;
+        }
+        let double = pawn + 16;
+        if pawn / 8 == 1
+            && double != white_king
+            && double != black_king
+            && table[kpk_index(double, white_king, black_king, false)] != UNKNOWN
+        {
+            return 1;
+        }
+    }
+    king_moves(white_king)
+        .filter(|&to| to != pawn && !adjacent(to, black_king))
+        .map(|to| table[kpk_index(pawn, to, black_king, false)])
+        .filter(|&dtz| dtz != UNKNOWN)
+        .min()
+        .map_or(UNKNOWN, |dtz| dtz + 1)
+}
+
+// one more than the slowest loss when every black move loses, 0 when mated
+fn black_kpk_dtz(table: &[u8], pawn: usize, white_king: usize, black_king: usize) -> u8 {
+    let mut slowest = None;
+    for to in king_moves(black_king) {
+        if adjacent(to, white_king) || pawn_attacks(pawn, to) {
+            continue;
+        }
+        // taking the pawn draws
+        if to == pawn {
+            return UNKNOWN;
+        }
+        let dtz = table[kpk_index(pawn, white_king, to, true)];
+        if dtz == UNKNOWN {
+            return UNKNOWN;
+        }
+        slowest = slowest.max(Some(dtz));
+    }
+    match slowest {
+        Some(dtz) => dtz + 1,
+        None if pawn_attacks(pawn, black_king) => 0,
+        // stalemate
+        None => UNKNOWN,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn kpk(fen: &str) -> Option<TbResult> {
+        let mut fields = fen.split_whitespace();
+        let board = Board::from_fen(fields.next().unwrap());
+        probe_kpk(&board, fields.next() == Some("w"))
+    }
+
+    #[test]
+    fn test_probe_kpk() {
+        let win = |dtz| Some(TbResult { wdl: Wdl::Win, dtz });
+        let draw = Some(TbResult { wdl: Wdl::Draw, dtz: 0 });
+
+        // promotes right away
+        assert_eq!(win(1), kpk("8/4P3/8/8/8/8/8/k3K3 w"));
+        // stalemate
+        assert_eq!(draw, kpk("4k3/4P3/4K3/8/8/8/8/8 b"));
+        // Kf6 Kd7 Kf7 and the pawn queens
+        assert_eq!(Wdl::Win, kpk("4k3/4P3/4K3/8/8/8/8/8 w").unwrap().wdl);
+        // king on the 6th in front of the pawn wins whoever moves
+        assert_eq!(Wdl::Win, kpk("4k3/8/4K3/4P3/8/8/8/8 w").unwrap().wdl);
+        assert_eq!(Wdl::Loss, kpk("4k3/8/4K3/4P3/8/8/8/8 b").unwrap().wdl);
+        // rook pawn with the king in the corner
+        assert_eq!(draw, kpk("k7/8/8/8/8/8/P7/K7 w"));
+        // the mirrored files are the same table
+        assert_eq!(kpk("4k3/8/4K3/4P3/8/8/8/8 w"), kpk("3k4/8/3K4/3P4/8/8/8/8 w"));
+        // black pawn
+        assert_eq!(draw, kpk("8/8/8/8/8/4k3/4p3/4K3 w"));
+        assert_eq!(kpk("4k3/4P3/4K3/8/8/8/8/8 w").map(|tb| tb.dtz), kpk("8/8/8/8/8/4k3/4p3/4K3 b").map(|tb| tb.dtz));
+        assert_eq!(Wdl::Win, kpk("8/8/8/8/8/4k3/4p3/4K3 b").unwrap().wdl);
+
+        // not in the table
+        assert_eq!(None, kpk("4k3/8/8/8/8/8/8/4K3 w"));
+        assert_eq!(None, kpk("4k3/8/8/8/8/8/8/R3K3 w"));
+        assert_eq!(None, kpk("4k3/4p3/8/8/8/8/4P3/4K3 w"));
+    }
+
+    #[test]
+    fn test_probe() {
+        let board = Board::from_fen("8/4P3/8/8/8/8/8/k3K3");
+        if cfg!(feature = "tablebase") {
+            assert_eq!(Some(TbResult { wdl: Wdl::Win, dtz: 1 }), probe(&board, true));
+        } else {
+            assert_eq!(None, probe(&board, true));
+        }
+    }
+}
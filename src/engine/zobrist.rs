@@ -0,0 +1,153 @@
+use crate::engine::board::Board;
+use crate::engine::parser::Piece;
+
+/// random keys for Zobrist hashing, generated at compile time so every run (and
+/// every build) hashes a position to the same value
+pub struct ZobristKeys {
+    /// indexed by [`piece_index`] and square
+    pub pieces: [[u64; 64]; 12],
+    pub black_to_move: u64,
+    /// white kingside, white queenside, black kingside, black queenside
+    pub castling: [u64; 4],
+    /// file of the en passant target
+    pub en_passant: [u64; 8],
+}
+
+pub static KEYS: ZobristKeys = generate_keys();
+
+const fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys() -> ZobristKeys {
+    let mut state = 0x4348_4553_5354_4552; // "CHESSTER"
+    let mut pieces = [[0u64; 64]; 12];
+    let mut piece = 0;
+    while piece < 12 {
+        let mut square = 0;
+        while square < 64 {
+            pieces[piece][square] = splitmix64(&mut state);
+            square += 1;
+        }
+        piece += 1;
+    }
+    let black_to_move = splitmix64(&mut state);
+    let mut castling = [0u64; 4];
+    let mut i = 0;
+    while i < 4 {
+        castling[i] = splitmix64(&mut state);
+        i += 1;
+    }
+    let mut en_passant = [0u64; 8];
+    let mut i = 0;
+    while i < 8 {
+        en_passant[i] = splitmix64(&mut state);
+        i += 1;
+    }
+    ZobristKeys {
+        pieces,
+        black_to_move,
+        castling,
+        en_passant,
+    }
+}
+
+const PIECES: [Piece; 6] = [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King];
+
+/// row of [`ZobristKeys::pieces`] for the piece, white pieces first
+pub fn piece_index(piece: Piece, is_white: bool) -> usize {
+    let idx = match piece {
+        Piece::Pawn => 0,
+        Piece::Knight => 1,
+        Piece::Bishop => 2,
+        Piece::Rook => 3,
+        Piece::Queen => 4,
+        Piece::King | Piece::Castling => 5,
+    };
+    if is_white {
+        idx
+    } else {
+        idx + 6
+    }
+}
+
+/// XOR of the piece keys of every square that differs between the boards, applying
+/// it to the hash of `before` gives the hash of `after`
+pub fn board_delta(before: &Board, after: &Board) -> u64 {
+    pieces_key(|piece, is_white| before.get_pieces(piece, is_white) ^ after.get_pieces(piece, is_white))
+}
+
+/// hash of the piece placement only
+pub fn hash_board(board: &Board) -> u64 {
+    pieces_key(|piece, is_white| board.get_pieces(piece, is_white))
+}
+
+/// XOR of the piece keys of the squares given for every piece
+fn pieces_key(squares: impl Fn(Piece, bool) -> u64) -> u64 {
+    let mut key = 0;
+    for is_white in [true, false] {
+        for piece in PIECES {
+            let keys = &KEYS.pieces[piece_index(piece, is_white)];
+            let mut squares = squares(piece, is_white);
+            while squares != 0 {
+                key ^= keys[squares.trailing_zeros() as usize];
+                squares &= squares - 1;
+            }
+        }
+    }
+    key
+}
+
+/// keys of the castling rights (white kingside, white queenside, black kingside,
+/// black queenside) that are set
+pub fn castling_key(rights: (bool, bool, bool, bool)) -> u64 {
+    let rights = [rights.0, rights.1, rights.2, rights.3];
+    (0..4).filter(|i| rights[*i]).fold(0, |key, i| key ^ KEYS.castling[i])
+}
+
+/// key of the en passant target square, 0 without one
+pub fn en_passant_key(target: u64) -> u64 {
+    if target == 0 {
+        0
+    } else {
+        KEYS.en_passant[(target.trailing_zeros() % 8) as usize]
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_unique() {
+        let mut keys: Vec<u64> = KEYS.pieces.iter().flatten().copied().collect();
+        keys.push(KEYS.black_to_move);
+        keys.extend(KEYS.castling);
+        keys.extend(KEYS.en_passant);
+        let count = keys.len();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(12 * 64 + 1 + 4 + 8, count);
+        assert_eq!(count, keys.len());
+        assert!(!keys.contains(&0));
+    }
+
+    #[test]
+    fn test_board_delta() {
+        let before = Board::default();
+        let mut after = before;
+        after.move_piece(0x1000, 0x1000_0000, true); // e2e4
+        let key = KEYS.pieces[piece_index(Piece::Pawn, true)];
+        assert_eq!(key[12] ^ key[28], board_delta(&before, &after));
+        assert_eq!(hash_board(&after), hash_board(&before) ^ board_delta(&before, &after));
+        let kings = Board::from_fen("4k3/8/8/8/8/8/8/4K3");
+        assert_eq!(
+            KEYS.pieces[piece_index(Piece::King, true)][4] ^ KEYS.pieces[piece_index(Piece::King, false)][60],
+            hash_board(&kings)
+        );
+    }
+}
@@ -1,11 +1,23 @@
 #![allow(unused)]
 
 mod engine;
+mod uci;
 mod ui;
 
-use crate::ui::app::{App, CurrentScreen};
+use crate::engine::ai;
+use crate::engine::book::{Book, BookError};
+use crate::engine::chess960;
+use crate::engine::drill::Drill;
+use crate::engine::game::{Game, LoadError};
+use crate::engine::parser::Piece;
+use crate::engine::rng::Rng;
+use crate::uci::ExternalEngine;
+use crate::ui::app::{next_promotion, App, CurrentScreen, Verbosity, DEFAULT_MOVETIME};
+use crate::ui::theme::{Theme, THEMES};
 use crate::ui::ui::{render, render_size_error};
-use crossterm::event::{self, DisableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEventKind,
+};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
@@ -19,10 +31,14 @@ use ratatui::text::{Line, Text};
 use ratatui::widgets::{Block, Clear, Paragraph, Widget};
 use ratatui::{DefaultTerminal, Frame, Terminal};
 use std::io::{stdout, Error, ErrorKind, Stdout};
+use std::time::Duration;
 use std::{env, io, process};
 
 pub const MIN_WIDTH: u16 = 132;
 pub const MIN_HEIGHT: u16 = 46;
+const FLASH_DURATION: Duration = Duration::from_millis(150);
+// how often the computer's search is checked for progress
+const SEARCH_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 fn check_size(terminal: &mut DefaultTerminal) -> Result<(), io::Error> {
     let size = terminal.size()?;
@@ -43,7 +59,7 @@ fn check_size(terminal: &mut DefaultTerminal) -> Result<(), io::Error> {
                         && key.modifiers.contains(event::KeyModifiers::CONTROL)
                         || key.code == KeyCode::Esc
                     {
-                        ratatui::restore();
+                        restore_terminal();
                         process::exit(0);
                     }
                 }
@@ -54,47 +70,295 @@ fn check_size(terminal: &mut DefaultTerminal) -> Result<(), io::Error> {
     Ok(())
 }
 
+/// returns the value following the given flag (e.g. `--seed 42`)
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|idx| args.get(idx + 1))
+        .cloned()
+}
+
 fn main() -> Result<(), io::Error> {
     let args: Vec<String> = env::args().collect();
     let use_halfblocks = args.contains(&"--halfblocks".to_string());
+    let seed = arg_value(&args, "--seed")
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(Rng::time_seed);
+    let verbosity = if args.contains(&"--verbose".to_string()) {
+        Verbosity::Debug
+    } else {
+        Verbosity::Normal
+    };
+    let default_promotion = match arg_value(&args, "--promote").as_deref() {
+        None | Some("q") | Some("Q") => Piece::Queen,
+        Some("n") | Some("N") => Piece::Knight,
+        Some("r") | Some("R") => Piece::Rook,
+        Some("b") | Some("B") => Piece::Bishop,
+        Some(other) => {
+            eprintln!("invalid --promote: {} (expected q, r, b or n)", other);
+            process::exit(1);
+        }
+    };
+    let drill = arg_value(&args, "--drill").map(|name| {
+        let user_is_white = !args.contains(&"--drill-black".to_string());
+        Drill::from_opening(&name, user_is_white).unwrap_or_else(|| {
+            eprintln!("unknown opening for --drill: {}", name);
+            process::exit(1);
+        })
+    });
+    let ai_is_white = match arg_value(&args, "--ai").as_deref() {
+        None => None,
+        Some("white") => Some(true),
+        Some("black") => Some(false),
+        Some(other) => {
+            eprintln!("invalid --ai: {} (expected white or black)", other);
+            process::exit(1);
+        }
+    };
+    let movetime = match arg_value(&args, "--movetime") {
+        None => DEFAULT_MOVETIME,
+        Some(ms) => ms.parse::<u64>().ok().filter(|ms| *ms > 0).unwrap_or_else(|| {
+            eprintln!("invalid --movetime: {} (expected milliseconds)", ms);
+            process::exit(1);
+        }),
+    };
+    // a random Chess960 position (the same one for a given --seed) unless --fen is given
+    let start_fen = arg_value(&args, "--fen").or_else(|| {
+        args.contains(&"--chess960".to_string()).then(|| {
+            let index = Rng::new(seed).next_range(chess960::POSITIONS as usize) as u16;
+            Game::chess960(index).to_fen()
+        })
+    });
+    if let Some(Err(err)) = start_fen.as_deref().map(Game::from_fen) {
+        eprintln!("invalid --fen: {:?}", err);
+        process::exit(1);
+    }
+    let loaded_game = arg_value(&args, "--load").map(|path| {
+        let contents = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+            eprintln!("cannot read --load {}: {}", path, err);
+            process::exit(1);
+        });
+        Game::from_save_file(&contents).unwrap_or_else(|err| {
+            match err {
+                LoadError::InvalidFen(err) => eprintln!("invalid start position in {}: {:?}", path, err),
+                LoadError::IllegalMove { move_number, san, error } => {
+                    eprintln!("cannot load {}: move {} ({}) failed: {:?}", path, move_number, san, error)
+                }
+            }
+            process::exit(1);
+        })
+    });
+    let theme = arg_value(&args, "--theme").map(|name| {
+        Theme::from_name(&name).unwrap_or_else(|| {
+            let names: Vec<&str> = THEMES.iter().map(|theme| theme.name).collect();
+            eprintln!("unknown --theme: {} (expected {})", name, names.join(", "));
+            process::exit(1);
+        })
+    });
+    let book = arg_value(&args, "--book").map(|path| {
+        let contents = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+            eprintln!("cannot read --book {}: {}", path, err);
+            process::exit(1);
+        });
+        Book::parse(&contents).unwrap_or_else(|err| {
+            match err {
+                BookError::InvalidFen { line, error } => eprintln!("invalid position on line {} of {}: {:?}", line, path, error),
+                BookError::IllegalMove { line, san, error } => {
+                    eprintln!("cannot read {}: move {} on line {} failed: {:?}", path, san, line, error)
+                }
+            }
+            process::exit(1);
+        })
+    });
+    let save_path = arg_value(&args, "--save");
+    let pgn_path = arg_value(&args, "--export-pgn");
+    if args.contains(&"--uci".to_string()) {
+        return uci::run(io::stdin().lock(), stdout(), book, seed);
+    }
+    // the engine plays black unless --ai picks its color
+    let engine = arg_value(&args, "--engine").map(|path| {
+        ExternalEngine::spawn(&path).unwrap_or_else(|err| {
+            eprintln!("cannot start --engine {}: {}", path, err);
+            process::exit(1);
+        })
+    });
+    let ai_is_white = ai_is_white.or(engine.as_ref().map(|_| false));
+    // ratatui::init also installs a panic hook restoring the terminal (raw mode,
+    // alternate screen, cursor) before the panic message is printed, mouse capture
+    // is released before that
     let mut terminal = ratatui::init();
-    let mut app = App::new(use_halfblocks);
-    run(&mut terminal, &mut app)?;
+    let restore_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = execute!(stdout(), DisableMouseCapture);
+        restore_hook(info);
+    }));
+    execute!(stdout(), EnableMouseCapture)?;
+    let mut app = App::new(use_halfblocks, seed, verbosity);
+    app.start_fen = start_fen;
+    app.default_promotion = default_promotion;
+    app.drill = drill;
+    app.ai_is_white = ai_is_white;
+    if args.contains(&"--ai-resign".to_string()) {
+        app.ai.config.resign_threshold = Some(ai::DEFAULT_RESIGN_THRESHOLD);
+    }
+    app.engine = engine;
+    app.movetime = movetime;
+    app.book = book;
+    app.bell = args.contains(&"--bell".to_string());
+    if let Some(theme) = theme {
+        app.set_theme(theme);
+    }
+    app.new_game();
+    if let Some(game) = loaded_game {
+        app.load_game(game);
+    }
+    // restore before returning the error as well, otherwise the shell is left in raw mode
+    let result = run(&mut terminal, &mut app);
+    restore_terminal();
+    if let Some(path) = save_path {
+        if let Err(err) = app.save_game(&path) {
+            eprintln!("cannot write --save {}: {}", path, err);
+        }
+    }
+    if let Some(path) = pgn_path {
+        if let Err(err) = std::fs::write(&path, app.game.to_pgn()) {
+            eprintln!("cannot write --export-pgn {}: {}", path, err);
+        }
+    }
+    result.map(|_| ())
+}
+
+/// releases the mouse and restores the terminal as before [`ratatui::init`]
+fn restore_terminal() {
+    let _ = execute!(stdout(), DisableMouseCapture);
     ratatui::restore();
-    Ok(())
 }
 
 fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<bool> {
     loop {
+        app.poll_search();
+        app.update_move_scores();
         check_size(terminal)?;
         terminal.hide_cursor()?;
         terminal.draw(|frame| render(frame, app))?;
-        if let Event::Key(key) = event::read()? {
+        // the flash lasts until the next key or a moment later
+        if app.flash {
+            app.flash = false;
+            if !event::poll(FLASH_DURATION)? {
+                continue;
+            }
+        }
+        // keep drawing the progress while the computer thinks
+        if app.is_thinking() && !event::poll(SEARCH_POLL_INTERVAL)? {
+            continue;
+        }
+        let event = event::read()?;
+        if let Event::Mouse(mouse) = event {
+            if mouse.kind == MouseEventKind::Down(MouseButton::Left) && matches!(app.current_screen, CurrentScreen::Main) {
+                app.click_square(mouse.column, mouse.row);
+            }
+            continue;
+        }
+        if let Event::Key(key) = event {
             if key.kind == KeyEventKind::Press {
+                app.status_message = None;
+                // the board cursor takes the arrow keys from scrolling and recalling
+                let board_cursor = app.cursor.is_some() && matches!(app.current_screen, CurrentScreen::Main);
                 match key.code {
+                    KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right if board_cursor => {
+                        match key.code {
+                            KeyCode::Up => app.move_cursor(0, 1),
+                            KeyCode::Down => app.move_cursor(0, -1),
+                            KeyCode::Left => app.move_cursor(-1, 0),
+                            _ => app.move_cursor(1, 0),
+                        }
+                        continue;
+                    }
                     KeyCode::Char('.') => {
                         app.flipped = !app.flipped;
                         continue;
                     }
-                    KeyCode::Up => {
-                        if app.show_scrollbar {
-                            app.scroll_up(1);
-                        }
+                    KeyCode::Char(',') => {
+                        app.show_coordinates = !app.show_coordinates;
                         continue;
                     }
-                    KeyCode::Down => {
-                        if app.show_scrollbar {
-                            app.scroll_down(1);
+                    KeyCode::Up | KeyCode::Down => {
+                        let up = key.code == KeyCode::Up;
+                        // shift recalls entered commands even when the move list scrolls
+                        let shift = key.modifiers.contains(event::KeyModifiers::SHIFT);
+                        match app.current_screen {
+                            CurrentScreen::Pgn if up => app.pgn_scroll = app.pgn_scroll.saturating_sub(1),
+                            // clamped when rendering
+                            CurrentScreen::Pgn => app.pgn_scroll = app.pgn_scroll.saturating_add(1),
+                            _ if app.show_scrollbar && !shift => {
+                                if up {
+                                    app.scroll_up(1)
+                                } else {
+                                    app.scroll_down(1)
+                                }
+                            }
+                            CurrentScreen::Main => app.recall_command(if up { -1 } else { 1 }),
+                            _ => {}
                         }
                         continue;
                     }
+                    KeyCode::PageUp if app.show_scrollbar => {
+                        app.scroll_page_up();
+                        continue;
+                    }
+                    KeyCode::PageDown if app.show_scrollbar => {
+                        app.scroll_page_down();
+                        continue;
+                    }
+                    KeyCode::Home if app.show_scrollbar => {
+                        app.scroll_to_top();
+                        continue;
+                    }
+                    KeyCode::End if app.show_scrollbar => {
+                        app.scroll_to_bottom();
+                        continue;
+                    }
                     _ => {}
                 }
 
                 match app.current_screen {
                     CurrentScreen::Main => match key.code {
+                        KeyCode::Tab => app.toggle_cursor(),
+                        KeyCode::Esc if app.cursor.is_some() => app.toggle_cursor(),
                         KeyCode::Esc => app.current_screen = CurrentScreen::Exiting,
+                        KeyCode::Enter if app.cursor.is_some() => app.pick_cursor_square(),
                         KeyCode::Enter => app.process_cmd(),
+                        KeyCode::Char('r') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.current_screen = CurrentScreen::ConfirmReset;
+                        }
+                        KeyCode::Char('a') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.toggle_analysis();
+                        }
+                        KeyCode::Char('b') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.toggle_bell();
+                        }
+                        KeyCode::Char('t') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.set_theme(app.theme.next());
+                        }
+                        KeyCode::Char('o') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.default_promotion = next_promotion(app.default_promotion);
+                        }
+                        KeyCode::Char('f') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.copy_fen();
+                        }
+                        KeyCode::Char('g') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.copy_pgn();
+                        }
+                        KeyCode::Char('w') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.swap_sides();
+                        }
+                        KeyCode::Char('e') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.toggle_move_scores();
+                        }
+                        KeyCode::Char('p') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.pgn_scroll = 0;
+                            app.current_screen = CurrentScreen::Pgn;
+                        }
                         KeyCode::Char(to_insert) => app.add_char(to_insert),
                         KeyCode::Backspace => app.delete_char(),
                         _ => {}
@@ -108,6 +372,37 @@ fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<bool> {
                         KeyCode::Char('n') | KeyCode::Esc => return Ok(true),
                         _ => {}
                     },
+                    CurrentScreen::DrawOffer => match key.code {
+                        KeyCode::Char('y') => app.accept_draw(),
+                        KeyCode::Char('n') | KeyCode::Esc => {
+                            app.current_screen = CurrentScreen::Main;
+                        }
+                        _ => {}
+                    },
+                    CurrentScreen::ConfirmReset => match key.code {
+                        KeyCode::Char('y') => {
+                            app.current_screen = CurrentScreen::Main;
+                            app.new_game();
+                        }
+                        KeyCode::Char('n') | KeyCode::Esc => {
+                            app.current_screen = CurrentScreen::Main;
+                        }
+                        _ => {}
+                    },
+                    CurrentScreen::Pgn => {
+                        if key.code == KeyCode::Esc {
+                            app.current_screen = CurrentScreen::Main;
+                        }
+                    }
+                    CurrentScreen::Promotion => match key.code {
+                        KeyCode::Enter => app.promote(app.default_promotion),
+                        KeyCode::Char('q') | KeyCode::Char('Q') => app.promote(Piece::Queen),
+                        KeyCode::Char('r') | KeyCode::Char('R') => app.promote(Piece::Rook),
+                        KeyCode::Char('b') | KeyCode::Char('B') => app.promote(Piece::Bishop),
+                        KeyCode::Char('n') | KeyCode::Char('N') => app.promote(Piece::Knight),
+                        KeyCode::Esc => app.current_screen = CurrentScreen::Main,
+                        _ => {}
+                    },
                     CurrentScreen::Exiting => match key.code {
                         KeyCode::Char('y') => return Ok(true),
                         KeyCode::Char('n') => {
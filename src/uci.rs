@@ -0,0 +1,445 @@
+use crate::engine::ai::MATE_SCORE;
+use crate::engine::book::Book;
+use crate::engine::game::{Game, MoveInfo};
+use crate::engine::rng::Rng;
+use std::fmt;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// minimal UCI (Universal Chess Interface) front end so chessterm can be driven by
+/// chess GUIs such as CuteChess or Arena. Supports `uci`, `isready`, `ucinewgame`,
+/// `position [startpos|fen ...] moves ...`, `go [depth N]` and `quit`, other commands are ignored
+pub struct UciSession {
+    game: Game,
+    // opening book consulted before searching (`--book`)
+    book: Option<Book>,
+    rng: Rng,
+}
+
+/// search depth of `go` without a `depth` (e.g. `go movetime 1000` or `go infinite`)
+pub const DEFAULT_DEPTH: u8 = 4;
+
+/// deepest `go depth N` searched, deeper requests are clamped since `go` answers
+/// before reading the next command (so `stop` can't cut a search short)
+pub const MAX_DEPTH: u8 = 8;
+
+impl UciSession {
+    pub fn new(book: Option<Book>, seed: u64) -> UciSession {
+        UciSession {
+            game: Game::default(),
+            book,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// handles a single command line and returns the response lines
+    pub fn handle(&mut self, line: &str) -> Vec<String> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first() {
+            Some(&"uci") => vec![
+                format!("id name chessterm {}", env!("CARGO_PKG_VERSION")),
+                "id author ronaldsuwandi".to_string(),
+                "uciok".to_string(),
+            ],
+            Some(&"isready") => vec!["readyok".to_string()],
+            Some(&"ucinewgame") => {
+                self.game = Game::default();
+                vec![]
+            }
+            Some(&"position") => self.position(&tokens[1..]),
+            Some(&"go") => self.go(&tokens[1..]),
+            _ => vec![],
+        }
+    }
+
+    /// `position startpos moves e2e4 ...` or `position fen <fen> moves e2e4 ...`,
+    /// the current position is kept if the command is invalid
+    fn position(&mut self, args: &[&str]) -> Vec<String> {
+        let moves_idx = args.iter().position(|arg| *arg == "moves").unwrap_or(args.len());
+        let moves = args.get(moves_idx + 1..).unwrap_or(&[]);
+        let fen = match args.first() {
+            Some(&"startpos") => None,
+            Some(&"fen") => Some(args[1..moves_idx].join(" ")),
+            _ => return vec!["info string invalid position command".to_string()],
+        };
+
+        match Game::from_uci_moves(fen.as_deref(), moves) {
+            Ok(game) => {
+                self.game = game;
+                vec![]
+            }
+            Err(err) => vec![format!("info string invalid position: {:?}", err)],
+        }
+    }
+
+    /// plays from the book while in book, otherwise searches `depth N` plies (or
+    /// [`DEFAULT_DEPTH`], at most [`MAX_DEPTH`]), time controls are ignored. `0000`
+    /// (null move) when the game is over
+    fn go(&mut self, args: &[&str]) -> Vec<String> {
+        if let Some(mv) = self.book.as_ref().and_then(|book| book.pick(&self.game, self.rng.next_u64())) {
+            return vec!["info string book move".to_string(), format!("bestmove {}", mv.uci())];
+        }
+        let depth = args
+            .iter()
+            .position(|arg| *arg == "depth")
+            .and_then(|idx| args.get(idx + 1))
+            .and_then(|depth| depth.parse::<u8>().ok())
+            .map_or(DEFAULT_DEPTH, |depth| depth.clamp(1, MAX_DEPTH));
+        match self.game.best_move(depth) {
+            Some((mv, score)) => vec![
+                format!("info depth {} score {} pv {}", depth, uci_score(score), mv.uci()),
+                format!("bestmove {}", mv.uci()),
+            ],
+            None => vec!["bestmove 0000".to_string()],
+        }
+    }
+}
+
+/// `cp <centipawns>` or `mate <moves>` (negative when getting mated)
+fn uci_score(score: i32) -> String {
+    let plies = MATE_SCORE - score.abs();
+    if plies > 100 {
+        return format!("cp {}", score);
+    }
+    let moves = (plies + 1) / 2;
+    format!("mate {}", if score > 0 { moves } else { -moves })
+}
+
+/// runs the UCI loop until `quit` or end of input
+pub fn run(input: impl BufRead, mut output: impl Write, book: Option<Book>, seed: u64) -> io::Result<()> {
+    let mut session = UciSession::new(book, seed);
+    for line in input.lines() {
+        let line = line?;
+        if line.trim() == "quit" {
+            break;
+        }
+        for response in session.handle(&line) {
+            writeln!(output, "{}", response)?;
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// what went wrong talking to an external engine (`--engine`)
+#[derive(Debug)]
+pub enum EngineError {
+    /// the engine can't be started or written to
+    Io(io::Error),
+    /// the engine exited or stopped answering
+    Closed,
+    /// the `bestmove` answer isn't a legal move in the position
+    IllegalMove(String),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Io(err) => write!(f, "Engine failed: {}", err),
+            EngineError::Closed => write!(f, "Engine exited or stopped answering"),
+            EngineError::IllegalMove(mv) => write!(f, "Engine played an illegal move: {}", mv),
+        }
+    }
+}
+
+impl From<io::Error> for EngineError {
+    fn from(err: io::Error) -> EngineError {
+        EngineError::Io(err)
+    }
+}
+
+/// time the engine gets to answer the `uci` handshake
+const INIT_TIMEOUT: Duration = Duration::from_secs(10);
+/// time the engine gets on top of `movetime` to answer with its move
+const BESTMOVE_GRACE: Duration = Duration::from_secs(1);
+
+/// client side of the UCI conversation, the engine's output is read line by line on
+/// its own thread so an engine that stops answering can be given up on. `writer` is
+/// the engine's input
+pub struct EngineClient<W> {
+    lines: Receiver<String>,
+    writer: W,
+}
+
+impl<W: Write> EngineClient<W> {
+    pub fn new<R: BufRead + Send + 'static>(reader: R, writer: W) -> EngineClient<W> {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            // ends when the engine exits or the client is dropped
+            for line in reader.lines() {
+                if line.map(|line| sender.send(line)).is_err() {
+                    break;
+                }
+            }
+        });
+        EngineClient { lines, writer }
+    }
+
+    /// `uci` handshake, waits for `uciok` and `readyok`
+    pub fn init(&mut self) -> Result<(), EngineError> {
+        self.send("uci")?;
+        self.wait_for("uciok", INIT_TIMEOUT)?;
+        self.send("isready")?;
+        self.wait_for("readyok", INIT_TIMEOUT)?;
+        Ok(())
+    }
+
+    /// asks for a move in the current position of the game, searching for `movetime`
+    /// milliseconds, and plays it. The game is unchanged if the move is illegal
+    pub fn play(&mut self, game: &mut Game, movetime: u64) -> Result<MoveInfo, EngineError> {
+        let mv = self.best_move(game, movetime)?;
+        game.apply_uci(&mv).map_err(|_| EngineError::IllegalMove(mv))
+    }
+
+    /// the engine's answer to `go movetime` in the position of the game (not checked
+    /// for legality), [`EngineError::Closed`] if it doesn't answer in time
+    pub fn best_move(&mut self, game: &Game, movetime: u64) -> Result<String, EngineError> {
+        if game.chess960 {
+            self.send("setoption name UCI_Chess960 value true")?;
+        }
+        self.send(&format!("position fen {}", game.to_fen()))?;
+        self.send(&format!("go movetime {}", movetime))?;
+        let line = self.wait_for("bestmove", Duration::from_millis(movetime) + BESTMOVE_GRACE)?;
+        Ok(line.split_whitespace().nth(1).unwrap_or_default().to_string())
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.writer, "{}", command)?;
+        self.writer.flush()
+    }
+
+    /// skips lines (`id`, `info`, options...) until the one starting with `token`,
+    /// giving up after `timeout`
+    fn wait_for(&mut self, token: &str, timeout: Duration) -> Result<String, EngineError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let line = self
+                .lines
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .map_err(|_| EngineError::Closed)?;
+            if line.split_whitespace().next() == Some(token) {
+                return Ok(line.trim().to_string());
+            }
+        }
+    }
+}
+
+/// asks the engine for its move on a worker thread (see [`EngineClient::best_move`])
+/// so the caller can keep going, the answer arrives on the returned channel
+fn spawn_best_move<W: Write + Send + 'static>(
+    client: &Arc<Mutex<EngineClient<W>>>,
+    game: &Game,
+    movetime: u64,
+) -> Receiver<Result<String, EngineError>> {
+    let (sender, receiver) = mpsc::channel();
+    let client = Arc::clone(client);
+    let game = game.clone();
+    thread::spawn(move || {
+        let Ok(mut client) = client.lock() else {
+            return;
+        };
+        // nobody waits anymore if the receiver is gone
+        let _ = sender.send(client.best_move(&game, movetime));
+    });
+    receiver
+}
+
+/// engine binary (e.g. Stockfish) running as a child process, told to quit when dropped
+pub struct ExternalEngine {
+    child: Child,
+    // shared with the thread asking for a move, see [`ExternalEngine::spawn_best_move`]
+    client: Arc<Mutex<EngineClient<ChildStdin>>>,
+}
+
+impl ExternalEngine {
+    /// starts the engine and waits until it's ready, its stderr is discarded so it
+    /// doesn't draw over the TUI
+    pub fn spawn(path: &str) -> Result<ExternalEngine, EngineError> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(EngineError::Closed);
+        };
+        let mut client = EngineClient::new(BufReader::new(stdout), stdin);
+        let ready = client.init();
+        // killed on drop if the handshake failed
+        let engine = ExternalEngine {
+            child,
+            client: Arc::new(Mutex::new(client)),
+        };
+        ready?;
+        Ok(engine)
+    }
+
+    /// asks for the engine's move without waiting for it, see [`spawn_best_move`]
+    pub fn spawn_best_move(&self, game: &Game, movetime: u64) -> Receiver<Result<String, EngineError>> {
+        spawn_best_move(&self.client, game, movetime)
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        // the engine is killed anyway if a move is still being waited for
+        if let Ok(mut client) = self.client.try_lock() {
+            let _ = client.send("quit");
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn run_script(script: &str) -> Vec<String> {
+        run_with_book(script, None)
+    }
+
+    fn run_with_book(script: &str, book: Option<Book>) -> Vec<String> {
+        let mut output = Vec::new();
+        run(Cursor::new(script), &mut output, book, 42).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_handshake() {
+        let output = run_script("uci\nisready\nquit\ngo\n");
+        assert_eq!(
+            vec!["id name chessterm 0.1.0", "id author ronaldsuwandi", "uciok", "readyok"],
+            output
+        );
+    }
+
+    #[test]
+    fn test_position_and_go() {
+        let output = run_script("ucinewgame\nposition startpos moves e2e4 e7e5 g1f3\ngo depth 2\n");
+        assert_eq!(2, output.len());
+        assert!(output[0].starts_with("info depth 2 score cp "));
+        let best_move = output[1].strip_prefix("bestmove ").unwrap();
+        let game = Game::from_uci_moves(None, &["e2e4", "e7e5", "g1f3"]).unwrap();
+        assert_eq!(game.best_move(2).unwrap().0.uci(), best_move);
+
+        // only legal move is taking the queen
+        let output = run_script("position fen 7k/8/8/8/8/8/6q1/7K w - - 0 1\ngo movetime 100\n");
+        assert_eq!("bestmove h1g2", output[1]);
+        let output = run_script("position fen 7k/8/8/8/8/8/6q1/7K w - - 0 1\ngo depth 255\n");
+        assert_eq!(format!("info depth {} score cp 0 pv h1g2", MAX_DEPTH), output[0]);
+
+        // back rank mate
+        let output = run_script("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 2\n");
+        assert_eq!(vec!["info depth 2 score mate 1 pv a1a8", "bestmove a1a8"], output);
+
+        // checkmated
+        let output = run_script("position fen 7k/8/8/8/8/8/6qr/7K w - - 0 1\ngo\n");
+        assert_eq!(vec!["bestmove 0000"], output);
+    }
+
+    #[test]
+    fn test_invalid_position() {
+        let output = run_script("position startpos moves e2e4\nposition startpos moves e2e5\ngo\n");
+        assert_eq!(3, output.len());
+        assert!(output[0].starts_with("info string"));
+        // previous position is kept, black to move
+        let best_move = output[2].strip_prefix("bestmove ").unwrap();
+        let game = Game::from_uci_moves(None, &["e2e4"]).unwrap();
+        assert!(game.legal_uci_moves().contains(&best_move.to_string()));
+    }
+
+    #[test]
+    fn test_go_from_book() {
+        let book = Book::parse("e4 c5\ne4 c5").unwrap();
+        let output = run_with_book("position startpos moves e2e4\ngo depth 1\n", Some(book.clone()));
+        assert_eq!(vec!["info string book move", "bestmove c7c5"], output);
+        // out of book
+        let output = run_with_book("position startpos moves d2d4\ngo depth 1\n", Some(book));
+        assert!(output[0].starts_with("info depth 1 "));
+    }
+
+    #[test]
+    fn test_uci_score() {
+        assert_eq!("cp 35", uci_score(35));
+        assert_eq!("cp -900", uci_score(-900));
+        assert_eq!("mate 1", uci_score(MATE_SCORE - 1));
+        assert_eq!("mate 2", uci_score(MATE_SCORE - 3));
+        assert_eq!("mate -1", uci_score(-MATE_SCORE + 2));
+    }
+
+    #[test]
+    fn test_engine_client() {
+        let replies = "id name Stub\noption name Hash type spin\nuciok\nreadyok\ninfo depth 1 pv e7e5\nbestmove e7e5 ponder g1f3\n";
+        let mut commands = Vec::new();
+        let mut client = EngineClient::new(Cursor::new(replies), &mut commands);
+        let mut game = Game::from_uci_moves(None, &["e2e4"]).unwrap();
+        client.init().unwrap();
+        assert_eq!("e5", client.play(&mut game, 100).unwrap().san);
+        assert_eq!(
+            "uci\nisready\nposition fen rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1\ngo movetime 100\n",
+            String::from_utf8(commands).unwrap()
+        );
+
+        // engine exits without answering
+        let mut client = EngineClient::new(Cursor::new("info depth 1\n"), Vec::new());
+        assert!(matches!(client.play(&mut game, 100), Err(EngineError::Closed)));
+
+        // illegal answers leave the game as it was
+        let fen = game.to_fen();
+        for reply in ["bestmove e2e4\n", "bestmove (none)\n", "bestmove\n"] {
+            let mut client = EngineClient::new(Cursor::new(reply), Vec::new());
+            assert!(matches!(client.play(&mut game, 100), Err(EngineError::IllegalMove(_))), "{}", reply);
+            assert_eq!(fen, game.to_fen());
+        }
+
+        // answer from the worker thread
+        let client = Arc::new(Mutex::new(EngineClient::new(Cursor::new("bestmove e7e5\n"), Vec::new())));
+        let answer = spawn_best_move(&client, &game, 100).recv().unwrap();
+        assert_eq!("e7e5", answer.unwrap());
+    }
+
+    /// engine that never writes anything
+    struct Silent;
+
+    impl io::Read for Silent {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            loop {
+                thread::park();
+            }
+        }
+    }
+
+    #[test]
+    fn test_engine_timeout() {
+        // thinks but never answers with its move
+        let reader = BufReader::new(io::Read::chain(Cursor::new("info depth 1\n"), Silent));
+        let client = Arc::new(Mutex::new(EngineClient::new(reader, Vec::new())));
+        let game = Game::default();
+        let start = Instant::now();
+        let answer = spawn_best_move(&client, &game, 50);
+        // the caller isn't blocked while the engine thinks
+        assert!(answer.try_recv().is_err());
+        assert!(matches!(answer.recv().unwrap(), Err(EngineError::Closed)));
+        let waited = start.elapsed();
+        assert!(waited >= Duration::from_millis(50) + BESTMOVE_GRACE, "{:?}", waited);
+        assert!(waited < Duration::from_millis(50) + BESTMOVE_GRACE * 2, "{:?}", waited);
+    }
+
+    #[test]
+    fn test_spawn_missing_engine() {
+        let err = ExternalEngine::spawn("./no-such-engine").err().unwrap();
+        assert!(matches!(err, EngineError::Io(_)));
+        assert!(err.to_string().starts_with("Engine failed: "));
+    }
+}
@@ -1,2 +1,4 @@
 pub mod app;
+pub mod clipboard;
+pub mod theme;
 pub mod ui;
@@ -1,5 +1,16 @@
-use crate::engine::game::{Game, MoveError, Status};
+use crate::engine::ai;
+use crate::engine::ai::{Ai, AiMove, SearchUpdate};
+use crate::engine::board::{is_file, is_rank};
+use crate::engine::book::Book;
+use crate::engine::drill::Drill;
+use crate::engine::game::{Game, Move, MoveError, MoveInfo, Status};
+use crate::engine::parser::{parse_move, Piece};
+use crate::engine::rng::Rng;
+use crate::ui::clipboard;
+use crate::ui::clipboard::Copied;
+use crate::ui::theme::{rgba, Theme};
 use crate::ui::ui;
+use crate::uci::{EngineError, ExternalEngine};
 use crossterm::event;
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
 use image::{DynamicImage, ImageReader, Rgb, Rgba};
@@ -15,8 +26,10 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io;
+use std::io::Write;
 use std::io::{BufReader, Cursor};
 use std::path::Path;
+use std::sync::mpsc::{Receiver, TryRecvError};
 use include_dir::{include_dir, Dir};
 use ratatui::prelude::Color;
 
@@ -32,14 +45,72 @@ pub struct App {
     pub input: String,
     pub character_index: usize,
     pub error: Option<MoveError>,
+    // why the last move can't be played, shown below the input
+    pub error_message: Option<String>,
+    // confirmation of the last copy to the clipboard, until the next keypress
+    pub status_message: Option<String>,
     pub moves: Vec<String>,
     pub visible_moves: usize,
+    // entered commands, recalled with Up/Down like a shell
+    pub command_history: Vec<String>,
+    // recalled entry of `command_history`, `None` on a fresh line
+    pub history_index: Option<usize>,
 
     pub show_scrollbar: bool,
     pub scrollbar_state: ScrollbarState,
     pub scroll_offset: usize,
     pub table_state: TableState,
+    // first visible line of the PGN screen
+    pub pgn_scroll: usize,
     pub flipped: bool,
+    // terminal bell and a flash of the turn panel on check and at the end of the
+    // game (`--bell`, toggled with `Ctrl-B`)
+    pub bell: bool,
+    // the turn panel is drawn inverted for a moment after ringing the bell
+    pub flash: bool,
+    // board colors, cycled with `Ctrl-T`
+    pub theme: Theme,
+    // rank and file labels around the board, toggled with `,`
+    pub show_coordinates: bool,
+    pub verbosity: Verbosity,
+    // position new games start from (`--fen`), standard start position if `None`
+    pub start_fen: Option<String>,
+    // piece preselected in the promotion picker (confirmed with Enter)
+    pub default_promotion: Piece,
+    // opening drill (`--drill`), the book side is played automatically
+    pub drill: Option<Drill>,
+    pub drill_message: Option<String>,
+    // color played by the computer (`--ai`), `Some(true)` for white
+    pub ai_is_white: Option<bool>,
+    pub ai: Ai,
+    // search of the computer's move running in the background, and the frame of the
+    // thinking spinner
+    pub search: Option<Search>,
+    pub spinner: usize,
+    // every legal move with its score (toggled with `Ctrl-E`), with the hash of the
+    // position they were scored in
+    pub show_move_scores: bool,
+    pub move_scores: Option<(u64, Vec<(String, i32)>)>,
+    // external UCI engine playing the computer's color instead of the built-in search
+    // (`--engine`), given `movetime` milliseconds per move
+    pub engine: Option<ExternalEngine>,
+    pub movetime: u64,
+    // opening book the computer plays from before its own moves (`--book`)
+    pub book: Option<Book>,
+    // move list of the live game while in analysis mode
+    pub live_moves: Option<Vec<String>>,
+    // last rendered board area (without labels) and square size
+    pub board_area: Rect,
+    // square of the piece picked with the mouse or the board cursor, its legal targets
+    // are highlighted
+    pub selected: Option<u64>,
+    // board cursor for entering moves with the arrow keys (`Tab`), `None` when off
+    pub cursor: Option<u64>,
+    pub square_size: u16,
+
+    // seedable rng shared by all randomized features
+    pub seed: u64,
+    pub rng: Rng,
 
     // image related
     // mapped to both light and dark protocols
@@ -62,9 +133,23 @@ enum Audio {
     Error,
 }
 
+/// how much detail the move list and errors show
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Verbosity {
+    /// player facing, SAN and short error marker only
+    Normal,
+    /// includes from/to squares and the error reason (for bug reports)
+    Debug,
+}
+
 pub enum CurrentScreen {
     Main,
     GameOver,
+    ConfirmReset,
+    // the side to move offered a draw, the other player accepts or declines
+    DrawOffer,
+    Pgn,
+    Promotion,
     Exiting,
 }
 
@@ -74,10 +159,46 @@ pub enum CurrentlyEditing {
 }
 
 const MAX_MOVE_LENGTH: usize = 6;
-const LIGHT_SQUARE: [u8; 4] = [235, 209, 166, 255];
-const DARK_SQUARE: [u8; 4] = [165, 117, 80, 255];
 
-fn get_file_contents(path: &str) -> Vec<u8> {
+/// plies searched for each move of the move scores panel, every move gets a full
+/// search so it stays shallow
+pub const MOVE_SCORES_DEPTH: u8 = 2;
+
+/// computer's move being searched in a worker thread, by the built-in search (see
+/// [`ai::spawn_search`]) or the external engine (see [`ExternalEngine::spawn_best_move`])
+pub struct Search {
+    searcher: Searcher,
+    // position searched, the move is only played if it's still on the board
+    hash: u64,
+    // deepest search done so far
+    pub depth: u8,
+}
+
+enum Searcher {
+    Ai(Receiver<SearchUpdate>),
+    Engine(Receiver<Result<String, EngineError>>),
+}
+
+/// thinking time of the external engine per move (`--movetime`)
+pub const DEFAULT_MOVETIME: u64 = 1000;
+
+/// sprite for every FEN piece char, shared by all image protocols (including halfblocks)
+pub const PIECE_SPRITES: [(char, &str); 12] = [
+    ('p', "pawn_black"),
+    ('r', "rook_black"),
+    ('b', "bishop_black"),
+    ('n', "knight_black"),
+    ('q', "queen_black"),
+    ('k', "king_black"),
+    ('P', "pawn_white"),
+    ('R', "rook_white"),
+    ('B', "bishop_white"),
+    ('N', "knight_white"),
+    ('Q', "queen_white"),
+    ('K', "king_white"),
+];
+
+pub(crate) fn get_file_contents(path: &str) -> Vec<u8> {
     if let Some(content) = ASSETS.get_file(path).map(|f| f.contents()) {
         content.to_vec()
     } else {
@@ -85,7 +206,7 @@ fn get_file_contents(path: &str) -> Vec<u8> {
     }
 }
 
-fn load_image(data: Vec<u8>) -> DynamicImage {
+pub(crate) fn load_image(data: Vec<u8>) -> DynamicImage {
     ImageReader::new(Cursor::new(data))
         .with_guessed_format()
         .expect("Failed to read image format")
@@ -93,47 +214,172 @@ fn load_image(data: Vec<u8>) -> DynamicImage {
         .expect("Failed to decode image")
 }
 
+/// game at the configured start position, standard start position if there is no
+/// FEN or the FEN is invalid
+pub fn start_game(start_fen: Option<&str>) -> Game {
+    start_fen
+        .and_then(|fen| Game::from_fen(fen).ok())
+        .unwrap_or_default()
+}
+
+/// completes a promoting pawn move typed without a promotion piece (e.g. `e8`,
+/// `dxe8`) with the given piece, other moves are returned unchanged
+pub fn with_default_promotion(input: &str, game: &Game, promotion: Piece) -> String {
+    let input = input.trim();
+    let last_rank = if game.is_white() { 8 } else { 1 };
+    match parse_move(input) {
+        Ok(mv) if mv.piece == Piece::Pawn && mv.special_move.is_none() && is_rank(mv.to, last_rank) => {
+            format!("{}={}", input, promotion_letter(promotion))
+        }
+        _ => input.to_string(),
+    }
+}
+
+/// legal destinations of the pieces named by a partially typed move (e.g. `N`, `Nb`,
+/// `Ng1`, `e`, `e2`), the union of all of them when several pieces match. A square
+/// without a piece letter names any piece on it. 0 if the input names no source
+pub fn typed_destinations(input: &str, game: &Game) -> u64 {
+    let mut chars = input.trim().chars().peekable();
+    let piece = match chars.peek() {
+        Some('N') => Some(Piece::Knight),
+        Some('B') => Some(Piece::Bishop),
+        Some('R') => Some(Piece::Rook),
+        Some('Q') => Some(Piece::Queen),
+        Some('K') => Some(Piece::King),
+        _ => None,
+    };
+    if piece.is_some() {
+        chars.next();
+    }
+    let file = chars.next_if(|c| ('a'..='h').contains(c));
+    let rank = chars.next_if(|c| ('1'..='8').contains(c)).and_then(|c| c.to_digit(10));
+    if chars.next().is_some() || (piece.is_none() && file.is_none()) {
+        return 0;
+    }
+
+    let is_white = game.is_white();
+    let mut destinations = 0;
+    for idx in 0..64 {
+        let square = 1u64 << idx;
+        let matches = match (game.piece_at(square), piece) {
+            (Some((_, white)), _) if white != is_white => false,
+            (Some((found, _)), Some(piece)) => found == piece,
+            // a bare file names pawns, a full square any piece
+            (Some((found, _)), None) => found == Piece::Pawn || rank.is_some(),
+            (None, _) => false,
+        };
+        if matches
+            && file.map_or(true, |file| is_file(square, file))
+            && rank.map_or(true, |rank| is_rank(square, rank as u64))
+        {
+            destinations |= game.legal_destinations(square);
+        }
+    }
+    destinations
+}
+
+/// whether the input is a legal promoting pawn move typed without a promotion piece
+/// (e.g. `e8`), the piece is then picked in the promotion picker
+pub fn needs_promotion_piece(input: &str, game: &Game) -> bool {
+    let completed = with_default_promotion(input, game, Piece::Queen);
+    completed != input.trim() && game.clone().process_move(&completed).is_ok()
+}
+
+/// terminal bell (`BEL`), most terminals beep or flash their window
+fn ring_bell() {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+}
+
+/// square of the board cursor after moving it `right` and `up` on screen, the
+/// directions are reversed on a flipped board and the cursor stops at the edges
+pub fn moved_cursor(square: u64, right: i8, up: i8, flipped: bool) -> u64 {
+    let idx = square.trailing_zeros() as i8;
+    let (right, up) = if flipped { (-right, -up) } else { (right, up) };
+    let file = (idx % 8 + right).clamp(0, 7);
+    let rank = (idx / 8 + up).clamp(0, 7);
+    1 << (rank * 8 + file)
+}
+
+/// move from one picked square to another, a pawn reaching the last rank promotes to
+/// the given piece
+pub fn picked_move(game: &Game, from: u64, to: u64, promotion: Piece) -> Move {
+    let is_pawn = matches!(game.piece_at(from), Some((Piece::Pawn, _)));
+    let promotion = (is_pawn && (is_rank(to, 1) || is_rank(to, 8))).then_some(promotion);
+    Move { from, to, promotion }
+}
+
+/// entry recalled after moving `delta` entries through a history of `len` commands,
+/// negative is older. Moving past the newest entry returns to the fresh line (`None`),
+/// the oldest entry stays recalled
+pub fn recalled_index(index: Option<usize>, delta: isize, len: usize) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    let position = index.unwrap_or(len).saturating_add_signed(delta).min(len);
+    (position < len).then_some(position)
+}
+
+/// next piece when cycling through the promotion pieces
+pub fn next_promotion(piece: Piece) -> Piece {
+    match piece {
+        Piece::Queen => Piece::Knight,
+        Piece::Knight => Piece::Rook,
+        Piece::Rook => Piece::Bishop,
+        _ => Piece::Queen,
+    }
+}
+
+/// move list offset after scrolling `delta` rows, clamped between the first row and
+/// the offset where the last `visible` of the `rows` rows fill the list
+pub fn scrolled_offset(offset: usize, delta: isize, rows: usize, visible: usize) -> usize {
+    offset
+        .saturating_add_signed(delta)
+        .min(rows.saturating_sub(visible))
+}
+
+/// whether the last of the `rows` rows is visible at the offset, the move list only
+/// follows new moves from there so a list scrolled up by the user stays put
+pub fn is_scrolled_to_bottom(offset: usize, rows: usize, visible: usize) -> bool {
+    offset >= rows.saturating_sub(visible)
+}
+
+pub fn promotion_letter(piece: Piece) -> char {
+    match piece {
+        Piece::Knight => 'N',
+        Piece::Rook => 'R',
+        Piece::Bishop => 'B',
+        _ => 'Q',
+    }
+}
+
+/// sprite of every piece drawn on the picker's background color
+fn piece_protocols(picker: &Picker) -> HashMap<char, RefCell<StatefulProtocol>> {
+    PIECE_SPRITES
+        .iter()
+        .map(|(piece, filename)| {
+            let path = format!("sprite/{}.png", filename);
+            let protocol = picker.new_resize_protocol(load_image(get_file_contents(&path)));
+            (*piece, RefCell::new(protocol))
+        })
+        .collect()
+}
+
 impl App {
-    pub fn new(force_halfblocks: bool) -> Self {
-        let mut chess_pieces_light_bg = HashMap::new();
-        let mut chess_pieces_dark_bg = HashMap::new();
-        let fen_pieces = ['p', 'r', 'b', 'n', 'q', 'k', 'P', 'R', 'B', 'N', 'Q', 'K'];
+    pub fn new(force_halfblocks: bool, seed: u64, verbosity: Verbosity) -> Self {
+        let theme = Theme::default();
         let mut light_picker = Picker::from_query_stdio().unwrap();
         let mut dark_picker = Picker::from_query_stdio().unwrap();
-        light_picker.set_background_color(LIGHT_SQUARE);
-        dark_picker.set_background_color(DARK_SQUARE);
+        light_picker.set_background_color(rgba(theme.light_square));
+        dark_picker.set_background_color(rgba(theme.dark_square));
 
         if force_halfblocks {
             light_picker.set_protocol_type(ProtocolType::Halfblocks);
             dark_picker.set_protocol_type(ProtocolType::Halfblocks);
         }
 
-        for &piece in &fen_pieces {
-            if piece == '.' {
-                continue;
-            }
-            let filename = match piece {
-                'p' => "pawn_black",
-                'P' => "pawn_white",
-                'r' => "rook_black",
-                'R' => "rook_white",
-                'b' => "bishop_black",
-                'B' => "bishop_white",
-                'n' => "knight_black",
-                'N' => "knight_white",
-                'q' => "queen_black",
-                'Q' => "queen_white",
-                'k' => "king_black",
-                'K' => "king_white",
-                _ => panic!("Unknown piece: {}", piece),
-            };
-            let path = format!("sprite/{}.png", filename);
-            let dyn_img = load_image(get_file_contents(&path));
-            let light_protocol = light_picker.new_resize_protocol(dyn_img.clone());
-            let dark_protocol = dark_picker.new_resize_protocol(dyn_img);
-            chess_pieces_light_bg.insert(piece, RefCell::new(light_protocol));
-            chess_pieces_dark_bg.insert(piece, RefCell::new(dark_protocol));
-        }
+        let chess_pieces_light_bg = piece_protocols(&light_picker);
+        let chess_pieces_dark_bg = piece_protocols(&dark_picker);
 
         let (_audio_stream, audio_stream_handle) = OutputStream::try_default().unwrap();
         let mut audio_buffers = HashMap::new();
@@ -169,14 +415,45 @@ impl App {
             input: String::new(),
             character_index: 0,
             error: None,
+            error_message: None,
+            status_message: None,
             moves: Vec::new(),
             visible_moves: 0,
+            command_history: Vec::new(),
+            history_index: None,
             show_scrollbar: false,
             scrollbar_state: ScrollbarState::default(),
             scroll_offset: 0,
             table_state: TableState::default(),
+            pgn_scroll: 0,
 
             flipped: false,
+            bell: false,
+            flash: false,
+            theme,
+            show_coordinates: true,
+            verbosity,
+            start_fen: None,
+            default_promotion: Piece::Queen,
+            drill: None,
+            drill_message: None,
+            ai_is_white: None,
+            ai: Ai::default(),
+            search: None,
+            spinner: 0,
+            show_move_scores: false,
+            move_scores: None,
+            engine: None,
+            movetime: DEFAULT_MOVETIME,
+            book: None,
+            live_moves: None,
+            board_area: Rect::default(),
+            selected: None,
+            cursor: None,
+            square_size: 0,
+
+            seed,
+            rng: Rng::new(seed),
 
             chess_pieces_light_bg,
             chess_pieces_dark_bg,
@@ -195,39 +472,358 @@ impl App {
         if self.input.trim().is_empty() {
             return;
         }
+        if self.is_thinking() {
+            self.status_message = Some("Wait for the computer's move".to_string());
+            return;
+        }
 
-        match self.game.process_move(self.input.as_str()) {
-            Ok(_) => {
-                self.error = None;
+        if needs_promotion_piece(&self.input, &self.game) {
+            self.current_screen = CurrentScreen::Promotion;
+            return;
+        }
 
-                let mut rendered_input = self.input.clone();
+        let entered = self.input.trim().to_string();
+        if self.command_history.last() != Some(&entered) {
+            self.command_history.push(entered);
+        }
+        self.history_index = None;
 
-                // append checkmate/check symbol
-                if self.game.status == Status::Checkmate {
-                    rendered_input.push('#');
-                } else if self.game.check {
-                    rendered_input.push('+');
+        // commands ending the live game, the computer doesn't take draw offers
+        match self.input.trim() {
+            "resign" if !self.game.is_analysis() => return self.resign(),
+            "draw" if !self.game.is_analysis() && self.ai_is_white.is_none() => return self.offer_draw(),
+            _ => {}
+        }
+        if let Some(plies) = self.input.trim().strip_prefix("goto ").filter(|_| self.game.is_analysis()) {
+            let plies = plies.trim().parse().ok();
+            return self.goto(plies);
+        }
+
+        let cmd = with_default_promotion(&self.input, &self.game, self.default_promotion);
+        if let Some(Err(expected)) = self.drill.as_ref().map(|drill| drill.verify(&self.game, &cmd)) {
+            self.drill_message = Some(format!("Book move is {}", expected));
+            self.play_audio(Audio::Error);
+            return;
+        }
+
+        let result = self.game.process_move(&cmd);
+        self.finish_user_move(result, Some(&cmd));
+    }
+
+    /// picks the clicked square, see [`App::pick_square`]
+    pub fn click_square(&mut self, column: u16, row: u16) {
+        match self.screen_to_square(column, row) {
+            Some(square) => self.pick_square(square),
+            None => self.selected = None,
+        }
+    }
+
+    /// picks the square under the board cursor (`Enter`), see [`App::pick_square`]
+    pub fn pick_cursor_square(&mut self) {
+        if let Some(square) = self.cursor {
+            self.pick_square(square);
+        }
+    }
+
+    /// selects the piece of the side to move on the square and shows its legal
+    /// targets, picking one of them plays the move (promoting to the preselected
+    /// piece). Any other square clears the selection
+    fn pick_square(&mut self, square: u64) {
+        match self.selected.take() {
+            Some(from) if self.game.legal_destinations(from) & square != 0 => {
+                self.play_picked_move(picked_move(&self.game, from, square, self.default_promotion));
+            }
+            Some(from) if from == square => {}
+            _ if self.game.legal_destinations(square) != 0 => self.selected = Some(square),
+            _ => {}
+        }
+    }
+
+    /// turns the board cursor on (on the king of the side to move) or off
+    pub fn toggle_cursor(&mut self) {
+        self.selected = None;
+        self.cursor = match self.cursor {
+            Some(_) => None,
+            None if self.game.is_white() => Some(self.game.board.white_king),
+            None => Some(self.game.board.black_king),
+        };
+    }
+
+    /// moves the board cursor one square in the direction on screen
+    pub fn move_cursor(&mut self, right: i8, up: i8) {
+        self.cursor = self.cursor.map(|square| moved_cursor(square, right, up, self.flipped));
+    }
+
+    /// plays a move picked with the mouse or the board cursor, like a typed move it
+    /// has to follow the drill
+    fn play_picked_move(&mut self, mv: Move) {
+        if self.is_thinking() {
+            self.status_message = Some("Wait for the computer's move".to_string());
+            return;
+        }
+        let san = self.game.san(mv).ok();
+        if let (Some(drill), Some(san)) = (self.drill.as_ref(), san.as_ref()) {
+            if let Err(expected) = drill.verify(&self.game, san) {
+                self.drill_message = Some(format!("Book move is {}", expected));
+                self.play_audio(Audio::Error);
+                return;
+            }
+        }
+        let result = self.game.apply_move(mv);
+        self.finish_user_move(result, san.as_deref());
+    }
+
+    /// records a typed or picked move and lets the book or the computer answer, or
+    /// shows the error with the explanation of why `cmd` can't be played
+    fn finish_user_move(&mut self, result: Result<MoveInfo, MoveError>, cmd: Option<&str>) {
+        match result {
+            Ok(move_info) => {
+                self.clear_error();
+                self.selected = None;
+                self.input.clear();
+                self.reset_cursor();
+                self.record_move(&move_info);
+                if let Some(drill) = self.drill.as_mut().filter(|drill| !drill.is_finished()) {
+                    drill.advance();
                 }
+                self.play_drill_reply();
+                self.play_ai_reply();
+            }
+            Err(err) => {
+                self.error = Some(err);
+                self.error_message = cmd.and_then(|cmd| self.game.explain_illegal(cmd));
+                self.play_audio(Audio::Error);
+            }
+        }
+    }
+
+    /// clears the error of the last move, on the next keypress or move
+    fn clear_error(&mut self) {
+        self.error = None;
+        self.error_message = None;
+    }
+
+    /// the side to move resigns (`resign` command)
+    fn resign(&mut self) {
+        let result = self.game.resign();
+        self.end_by_players(result);
+    }
+
+    /// the side to move offers a draw (`draw` command), the other player answers on
+    /// the draw offer screen
+    fn offer_draw(&mut self) {
+        if self.game.status() != Status::Ongoing {
+            self.end_by_players(Err(MoveError::GameOver));
+            return;
+        }
+        self.input.clear();
+        self.reset_cursor();
+        self.current_screen = CurrentScreen::DrawOffer;
+    }
+
+    /// jumps to the position after that many plies of the move list (`goto N` command
+    /// in analysis mode), replaying the moves from the start position
+    fn goto(&mut self, plies: Option<usize>) {
+        let Some(game) = plies.and_then(|plies| self.game.position_after(plies)) else {
+            self.status_message = Some(format!("Type goto and a ply from 0 to {}", self.moves.len()));
+            self.play_audio(Audio::Error);
+            return;
+        };
+        self.game = game;
+        self.moves.truncate(self.game.history().len());
+        self.selected = None;
+        self.input.clear();
+        self.reset_cursor();
+        self.clear_error();
+        self.show_scrollbar = self.moves.len().div_ceil(2) > self.visible_moves;
+        self.scroll_offset = self.scroll_offset.min(self.moves.len());
+    }
+
+    /// the other player accepted the draw offer
+    pub fn accept_draw(&mut self) {
+        let result = self.game.agree_draw();
+        self.end_by_players(result);
+    }
 
-                self.moves.push(rendered_input);
+    /// switches to the game over screen once the players ended the game
+    fn end_by_players(&mut self, result: Result<(), MoveError>) {
+        match result {
+            Ok(()) => {
+                self.clear_error();
                 self.input.clear();
                 self.reset_cursor();
+                self.current_screen = CurrentScreen::GameOver;
+                self.play_audio(Audio::Notify);
+            }
+            Err(err) => {
+                self.current_screen = CurrentScreen::Main;
+                self.error = Some(err);
+                self.error_message = Some("The game is over".to_string());
+                self.play_audio(Audio::Error);
+            }
+        }
+    }
 
-                if self.game.status != Status::Ongoing {
-                    self.current_screen = CurrentScreen::GameOver;
-                    self.play_audio(Audio::Notify);
-                } else {
-                    self.play_audio(Audio::Move);
-                }
+    /// switches the board colors, the piece sprites are encoded again on the new
+    /// square colors
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.light_picker.set_background_color(rgba(theme.light_square));
+        self.dark_picker.set_background_color(rgba(theme.dark_square));
+        self.chess_pieces_light_bg = piece_protocols(&self.light_picker);
+        self.chess_pieces_dark_bg = piece_protocols(&self.dark_picker);
+    }
 
-                // auto scroll
-                self.show_scrollbar = self.moves.len().div_ceil(2) > self.visible_moves;
-                if self.show_scrollbar {
-                    self.scroll_down(self.visible_moves);
+    /// completes the move waiting in the promotion picker with the piece and plays it
+    pub fn promote(&mut self, piece: Piece) {
+        self.current_screen = CurrentScreen::Main;
+        self.input = format!("{}={}", self.input.trim(), promotion_letter(piece));
+        self.character_index = self.input.chars().count();
+        self.process_cmd();
+    }
+
+    /// adds the played move to the move list, plays the move sound (or switches to
+    /// the game over screen) and follows the new move unless the list was scrolled up
+    fn record_move(&mut self, move_info: &MoveInfo) {
+        let follow = is_scrolled_to_bottom(self.scroll_offset, self.moves.len().div_ceil(2), self.visible_moves);
+        self.moves.push(ui::format_move(move_info, self.verbosity));
+
+        if self.game.status() != Status::Ongoing && !self.game.is_analysis() {
+            self.current_screen = CurrentScreen::GameOver;
+            self.play_audio(Audio::Notify);
+        } else {
+            self.play_audio(Audio::Move);
+        }
+        if self.bell && (self.game.check || self.game.status() != Status::Ongoing) {
+            ring_bell();
+            self.flash = true;
+        }
+
+        // auto scroll
+        self.show_scrollbar = self.moves.len().div_ceil(2) > self.visible_moves;
+        if self.show_scrollbar && follow {
+            self.scroll_to_bottom();
+        }
+    }
+
+    /// turns the bell and flash on check and at the end of the game on or off
+    pub fn toggle_bell(&mut self) {
+        self.bell = !self.bell;
+    }
+
+    /// answers with the book move when it's the book side's turn in the drill
+    fn play_drill_reply(&mut self) {
+        let Some(drill) = self.drill.as_mut() else {
+            return;
+        };
+
+        if let Some(reply) = drill.next_move().filter(|_| drill.is_book_turn()) {
+            if let Ok(move_info) = self.game.process_move(reply) {
+                drill.advance();
+                self.record_move(&move_info);
+            }
+        }
+
+        self.drill_message = self
+            .drill
+            .as_ref()
+            .filter(|drill| drill.is_finished())
+            .map(|drill| format!("{} complete", drill.name));
+    }
+
+    /// answers with a book move when it's the computer's turn, otherwise the engine
+    /// (or the built-in search without one) starts thinking in the background and
+    /// [`App::poll_search`] plays its move. Analysis mode is left to the user
+    fn play_ai_reply(&mut self) {
+        if !self.is_ai_turn() || self.game.status() != Status::Ongoing {
+            return;
+        }
+        let book_move = self.book.as_ref().and_then(|book| book.pick(&self.game, self.rng.next_u64()));
+        if let Some(move_info) = book_move.and_then(|mv| self.game.apply_move(mv).ok()) {
+            self.record_move(&move_info);
+            return;
+        }
+        let searcher = match self.engine.as_ref() {
+            Some(engine) => Searcher::Engine(engine.spawn_best_move(&self.game, self.movetime)),
+            None => Searcher::Ai(ai::spawn_search(&self.game, self.ai.config.depth)),
+        };
+        self.search = Some(Search {
+            searcher,
+            hash: self.game.hash(),
+            depth: 0,
+        });
+    }
+
+    fn is_ai_turn(&self) -> bool {
+        self.ai_is_white == Some(self.game.is_white()) && !self.game.is_analysis()
+    }
+
+    /// whether the computer is searching its move
+    pub fn is_thinking(&self) -> bool {
+        self.search.is_some()
+    }
+
+    /// takes the progress of the background search without waiting and plays its
+    /// move once done. A result for a position that has changed since (new game,
+    /// analysis, swapped sides) is dropped
+    pub fn poll_search(&mut self) {
+        let Some(search) = self.search.as_mut() else {
+            return;
+        };
+        self.spinner = self.spinner.wrapping_add(1);
+        let best = match &search.searcher {
+            Searcher::Ai(updates) => loop {
+                match updates.try_recv() {
+                    Ok(SearchUpdate::Depth { depth, .. }) => search.depth = depth,
+                    Ok(SearchUpdate::Done(best)) => break best,
+                    Err(TryRecvError::Empty) => return,
+                    Err(TryRecvError::Disconnected) => break None,
+                }
+            },
+            Searcher::Engine(answer) => {
+                let answer = match answer.try_recv() {
+                    Ok(answer) => answer,
+                    Err(TryRecvError::Empty) => return,
+                    Err(TryRecvError::Disconnected) => Err(EngineError::Closed),
+                };
+                if self.finish_search() {
+                    let result =
+                        answer.and_then(|mv| self.game.apply_uci(&mv).map_err(|_| EngineError::IllegalMove(mv)));
+                    self.play_engine_reply(result);
+                }
+                return;
+            }
+        };
+        let on_board = self.finish_search();
+        let Some((mv, score)) = best.filter(|_| on_board) else {
+            return;
+        };
+        match self.ai.judge(mv, score) {
+            AiMove::Play(mv) => {
+                if let Ok(move_info) = self.game.apply_move(mv) {
+                    self.record_move(&move_info);
                 }
             }
+            AiMove::Resign => self.resign(),
+        }
+    }
+
+    /// drops the finished search, whether its position is still on the board with the
+    /// computer to move
+    fn finish_search(&mut self) -> bool {
+        let hash = self.search.take().map(|search| search.hash);
+        self.is_ai_turn() && hash == Some(self.game.hash())
+    }
+
+    /// records the engine's move. If the engine crashed or answered with an illegal
+    /// move it is stopped and the user plays both colors from here
+    fn play_engine_reply(&mut self, result: Result<MoveInfo, EngineError>) {
+        match result {
+            Ok(move_info) => self.record_move(&move_info),
             Err(err) => {
-                self.error = Some(err);
+                self.engine = None;
+                self.ai_is_white = None;
+                self.error_message = Some(format!("{}, you play both colors now", err));
                 self.play_audio(Audio::Error);
             }
         }
@@ -259,34 +855,357 @@ impl App {
     }
 
     pub fn scroll_up(&mut self, amount: usize) {
-        self.scroll_offset = self.scroll_offset
-            .saturating_sub(amount)
-            .clamp(0, self.moves.len());
+        self.scroll_by(-(amount as isize));
     }
 
     pub fn scroll_down(&mut self, amount: usize) {
-        self.scroll_offset = self.scroll_offset
-            .saturating_add(amount)
-            .clamp(0, self.moves.len());
+        self.scroll_by(amount as isize);
+    }
+
+    /// PageUp, scrolls the move list by the number of visible rows
+    pub fn scroll_page_up(&mut self) {
+        self.scroll_up(self.visible_moves.max(1));
+    }
+
+    /// PageDown, scrolls the move list by the number of visible rows
+    pub fn scroll_page_down(&mut self) {
+        self.scroll_down(self.visible_moves.max(1));
+    }
+
+    /// Home, jumps to the first move
+    pub fn scroll_to_top(&mut self) {
+        self.scroll_by(isize::MIN);
+    }
+
+    /// End, jumps to the last page of moves
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll_by(isize::MAX);
+    }
+
+    fn scroll_by(&mut self, delta: isize) {
+        let rows = self.moves.len().div_ceil(2);
+        self.scroll_offset = scrolled_offset(self.scroll_offset, delta, rows, self.visible_moves);
     }
 
     pub fn add_char(&mut self, ch: char) {
+        self.selected = None;
         if self.input.chars().count() < MAX_MOVE_LENGTH {
             self.input.push(ch);
             self.move_cursor_right();
-            self.error = None;
+            self.clear_error();
+            self.history_index = None;
         }
     }
     pub fn delete_char(&mut self) {
         self.input.pop();
-        self.error = None;
+        self.clear_error();
+        self.history_index = None;
         self.move_cursor_left();
     }
 
+    /// replaces the input with an older (`-1`) or newer (`1`) entered command, the
+    /// input is cleared when moving past the newest one
+    pub fn recall_command(&mut self, delta: isize) {
+        if self.history_index.is_none() && delta > 0 {
+            return;
+        }
+        self.history_index = recalled_index(self.history_index, delta, self.command_history.len());
+        self.input = self
+            .history_index
+            .map(|idx| self.command_history[idx].clone())
+            .unwrap_or_default();
+        self.character_index = self.input.chars().count();
+        self.clear_error();
+    }
+
+    /// board square under the screen cell (e.g. mouse position), `None` outside the board
+    pub fn screen_to_square(&self, column: u16, row: u16) -> Option<u64> {
+        ui::screen_to_square(self.board_area, self.square_size, column, row, self.flipped)
+    }
+
+    /// screen area of the board square, takes the board orientation into account
+    pub fn square_to_screen(&self, square: u64) -> Option<Rect> {
+        ui::square_to_screen(self.board_area, self.square_size, square, self.flipped)
+    }
+
+    /// enters analysis mode (moves for both colors) or leaves it, restoring the live
+    /// game and its move list
+    pub fn toggle_analysis(&mut self) {
+        self.search = None;
+        if let Some(moves) = self.live_moves.take() {
+            self.game.exit_analysis();
+            self.moves = moves;
+        } else {
+            self.game.enter_analysis();
+            self.live_moves = Some(self.moves.clone());
+        }
+        self.input.clear();
+        self.reset_cursor();
+        self.clear_error();
+        self.show_scrollbar = self.moves.len().div_ceil(2) > self.visible_moves;
+        self.scroll_offset = self.scroll_offset.min(self.moves.len());
+        // back in the live game the computer may be the one to move
+        self.play_ai_reply();
+    }
+
+    /// hands the user's color to the computer and takes over the other one (`Ctrl-W`),
+    /// the board turns to the user's side and the computer moves if it's its turn
+    pub fn swap_sides(&mut self) {
+        let Some(ai_is_white) = self.ai_is_white else {
+            self.status_message = Some("No computer opponent to swap sides with".to_string());
+            return;
+        };
+        self.ai_is_white = Some(!ai_is_white);
+        self.search = None;
+        // the user now plays the computer's old color
+        self.flipped = !ai_is_white;
+        self.ai.reset();
+        self.selected = None;
+        self.play_ai_reply();
+    }
+
+    /// shows or hides the score of every legal move (`Ctrl-E`)
+    pub fn toggle_move_scores(&mut self) {
+        self.show_move_scores = !self.show_move_scores;
+        self.update_move_scores();
+    }
+
+    /// scores the moves again once the position has changed while they're shown
+    pub fn update_move_scores(&mut self) {
+        if !self.show_move_scores {
+            self.move_scores = None;
+            return;
+        }
+        let hash = self.game.hash();
+        if self.move_scores.as_ref().is_some_and(|(scored, _)| *scored == hash) {
+            return;
+        }
+        self.move_scores = Some((hash, self.game.rank_moves(MOVE_SCORES_DEPTH)));
+    }
+
+    /// restarts from the configured start position and clears the input, move list
+    /// and scroll state
     pub fn new_game(&mut self) {
-        self.game = Game::default();
+        self.game = start_game(self.start_fen.as_deref());
+        self.search = None;
+        self.live_moves = None;
+        self.selected = None;
         self.input.clear();
+        self.reset_cursor();
         self.moves.clear();
-        self.error = None;
+        self.clear_error();
+        self.show_scrollbar = false;
+        self.scroll_offset = 0;
+        self.scrollbar_state = ScrollbarState::default();
+        self.table_state = TableState::default();
+
+        self.drill_message = None;
+        self.ai.reset();
+        if let Some(drill) = self.drill.as_mut() {
+            drill.restart();
+            // book plays the first move when the user drills black
+            self.play_drill_reply();
+        }
+        // computer opens when it plays white
+        self.play_ai_reply();
+    }
+
+    /// continues a loaded game (see [`Game::from_save_file`]), new games restart from
+    /// its start position
+    pub fn load_game(&mut self, game: Game) {
+        self.new_game();
+        self.start_fen = Some(game.start_fen());
+        self.moves = game.history().iter().map(|mv| ui::format_move(mv, self.verbosity)).collect();
+        self.game = game;
+        self.search = None;
+        self.show_scrollbar = self.moves.len().div_ceil(2) > self.visible_moves;
+        self.play_ai_reply();
+    }
+
+    /// copies the FEN of the shown position (`Ctrl-F`)
+    pub fn copy_fen(&mut self) {
+        let fen = self.game.to_fen();
+        self.copy("FEN", &fen, Path::new("chessterm.fen"));
+    }
+
+    /// copies the PGN of the shown game (`Ctrl-G`)
+    pub fn copy_pgn(&mut self) {
+        let pgn = self.game.to_pgn();
+        self.copy("PGN", &pgn, Path::new("chessterm.pgn"));
+    }
+
+    /// copies the text to the clipboard, or to the fallback file without one
+    fn copy(&mut self, what: &str, text: &str, fallback: &Path) {
+        match clipboard::copy(text, fallback) {
+            Ok(Copied::Clipboard) => self.status_message = Some(format!("{} copied", what)),
+            Ok(Copied::File(path)) => {
+                self.status_message = Some(format!("No clipboard, {} saved to {}", what, path.display()))
+            }
+            Err(err) => {
+                self.error_message = Some(format!("Cannot copy {}: {}", what, err));
+                self.play_audio(Audio::Error);
+            }
+        }
+    }
+
+    /// writes the live game to `path` in the save file format
+    pub fn save_game(&self, path: &str) -> io::Result<()> {
+        std::fs::write(path, self.game.to_save_file())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::engine::board::parse_square;
+
+    #[test]
+    fn test_start_game() {
+        let mut game = start_game(None);
+        let start = game.to_fen();
+        game.process_move("e4").unwrap();
+        game.process_move("e5").unwrap();
+        game.process_move("Nf3").unwrap();
+        assert_ne!(start, game.to_fen());
+
+        let game = start_game(None);
+        assert_eq!(start, game.to_fen());
+        assert_eq!(None, game.last_move());
+
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 12";
+        assert_eq!(fen, start_game(Some(fen)).to_fen());
+        assert_eq!(start, start_game(Some("invalid")).to_fen());
+    }
+
+    #[test]
+    fn test_with_default_promotion() {
+        let game = start_game(Some("1n2k3/P7/8/8/8/8/7p/4K3 w - - 0 1"));
+        assert_eq!("a8=Q", with_default_promotion("a8", &game, Piece::Queen));
+        assert_eq!("axb8=N", with_default_promotion("axb8 ", &game, Piece::Knight));
+        // explicit piece and non promoting moves are kept as is
+        assert_eq!("a8=R", with_default_promotion("a8=R", &game, Piece::Knight));
+        assert_eq!("Kd2", with_default_promotion("Kd2", &game, Piece::Knight));
+        assert_eq!("h1", with_default_promotion("h1", &game, Piece::Knight));
+
+        let game = start_game(Some("1n2k3/P7/8/8/8/8/7p/4K3 b - - 0 1"));
+        assert_eq!("h1=B", with_default_promotion("h1", &game, Piece::Bishop));
+        assert_eq!("a8", with_default_promotion("a8", &game, Piece::Bishop));
+    }
+
+    #[test]
+    fn test_typed_destinations() {
+        let squares = |names: &[&str]| names.iter().fold(0, |acc, name| acc | parse_square(name).unwrap());
+        let game = Game::default();
+        assert_eq!(squares(&["a3", "c3", "f3", "h3"]), typed_destinations("N", &game));
+        assert_eq!(squares(&["f3", "h3"]), typed_destinations("Ng", &game));
+        assert_eq!(squares(&["f3", "h3"]), typed_destinations("Ng1", &game));
+        assert_eq!(squares(&["e3", "e4"]), typed_destinations("e", &game));
+        assert_eq!(squares(&["e3", "e4"]), typed_destinations("e2", &game));
+        assert_eq!(squares(&["f3", "h3"]), typed_destinations("g1", &game));
+        // no movable piece, opponent piece or not a source
+        assert_eq!(0, typed_destinations("B", &game));
+        assert_eq!(0, typed_destinations("Ng8", &game));
+        assert_eq!(0, typed_destinations("e4", &game));
+        assert_eq!(0, typed_destinations("", &game));
+        assert_eq!(0, typed_destinations("O-O", &game));
+        assert_eq!(0, typed_destinations("Nxe5", &game));
+
+        // pinned knight can't move, black to move
+        let game = start_game(Some("4k3/4n3/8/8/8/8/1n6/4R1K1 b - - 0 1"));
+        assert_eq!(squares(&["a4", "c4", "d3", "d1"]), typed_destinations("N", &game));
+    }
+
+    #[test]
+    fn test_needs_promotion_piece() {
+        let game = start_game(Some("1n2k3/P7/8/8/8/8/7p/4K3 w - - 0 1"));
+        assert!(needs_promotion_piece("a8", &game));
+        assert!(needs_promotion_piece("axb8 ", &game));
+        // piece already given, not a promotion or not legal
+        assert!(!needs_promotion_piece("a8=N", &game));
+        assert!(!needs_promotion_piece("Kd2", &game));
+        assert!(!needs_promotion_piece("h8", &game));
+        assert!(!needs_promotion_piece("h1", &game));
+    }
+
+    #[test]
+    fn test_moved_cursor() {
+        let square = |name| crate::engine::board::parse_square(name).unwrap();
+        assert_eq!(square("e3"), moved_cursor(square("e2"), 0, 1, false));
+        assert_eq!(square("d2"), moved_cursor(square("e2"), -1, 0, false));
+        // up on screen is towards the first rank when flipped
+        assert_eq!(square("e1"), moved_cursor(square("e2"), 0, 1, true));
+        assert_eq!(square("f2"), moved_cursor(square("e2"), -1, 0, true));
+        // stops at the edges
+        assert_eq!(square("h8"), moved_cursor(square("h8"), 1, 1, false));
+        assert_eq!(square("a1"), moved_cursor(square("a1"), 1, 1, true));
+    }
+
+    #[test]
+    fn test_picked_move() {
+        let square = |name| crate::engine::board::parse_square(name).unwrap();
+        let game = start_game(Some("1n2k3/P7/8/8/8/8/p7/4K2R w K - 0 1"));
+        let mv = picked_move(&game, square("a7"), square("b8"), Piece::Knight);
+        assert_eq!(Move { from: square("a7"), to: square("b8"), promotion: Some(Piece::Knight) }, mv);
+        assert_eq!("axb8=N", game.san(mv).unwrap());
+        // castling is the king's move, black pawns promote on the first rank
+        let mv = picked_move(&game, square("e1"), square("g1"), Piece::Queen);
+        assert_eq!(None, mv.promotion);
+        assert_eq!("O-O", game.san(mv).unwrap());
+        assert_eq!(Some(Piece::Rook), picked_move(&game, square("a2"), square("a1"), Piece::Rook).promotion);
+    }
+
+    #[test]
+    fn test_recalled_index() {
+        // up from a fresh line recalls the newest command
+        assert_eq!(Some(2), recalled_index(None, -1, 3));
+        assert_eq!(Some(0), recalled_index(Some(1), -1, 3));
+        assert_eq!(Some(0), recalled_index(Some(0), -1, 3));
+        // down walks back to the fresh line
+        assert_eq!(Some(2), recalled_index(Some(1), 1, 3));
+        assert_eq!(None, recalled_index(Some(2), 1, 3));
+        assert_eq!(None, recalled_index(None, 1, 3));
+        // nothing entered yet
+        assert_eq!(None, recalled_index(None, -1, 0));
+    }
+
+    #[test]
+    fn test_next_promotion() {
+        let mut piece = Piece::Queen;
+        let mut letters = String::new();
+        for _ in 0..5 {
+            letters.push(promotion_letter(piece));
+            piece = next_promotion(piece);
+        }
+        assert_eq!("QNRBQ", letters);
+    }
+
+    #[test]
+    fn test_scrolled_offset() {
+        // 30 rows, 10 visible
+        assert_eq!(1, scrolled_offset(0, 1, 30, 10));
+        assert_eq!(0, scrolled_offset(3, -10, 30, 10));
+        assert_eq!(15, scrolled_offset(5, 10, 30, 10));
+        assert_eq!(20, scrolled_offset(15, 10, 30, 10));
+        assert_eq!(20, scrolled_offset(20, 1, 30, 10));
+
+        // home and end
+        assert_eq!(0, scrolled_offset(17, isize::MIN, 30, 10));
+        assert_eq!(20, scrolled_offset(3, isize::MAX, 30, 10));
+
+        // everything fits
+        assert_eq!(0, scrolled_offset(0, 10, 5, 10));
+        assert_eq!(0, scrolled_offset(0, isize::MAX, 5, 10));
+    }
+
+    #[test]
+    fn test_is_scrolled_to_bottom() {
+        // 30 rows, 10 visible
+        assert!(is_scrolled_to_bottom(20, 30, 10));
+        assert!(!is_scrolled_to_bottom(19, 30, 10));
+        assert!(!is_scrolled_to_bottom(0, 30, 10));
+
+        // everything fits
+        assert!(is_scrolled_to_bottom(0, 5, 10));
+        assert!(is_scrolled_to_bottom(0, 0, 10));
     }
 }
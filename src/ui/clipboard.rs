@@ -0,0 +1,71 @@
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// clipboard tools tried in order (macOS, Wayland, X11, WSL/Windows), each one reads
+/// the text from stdin and fails without a display to copy to
+const CLIPBOARD_COMMANDS: [(&str, &[&str]); 5] = [
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// where copied text ended up
+#[derive(Debug, PartialEq)]
+pub enum Copied {
+    Clipboard,
+    /// no clipboard (e.g. headless or over SSH), the text was written to the file
+    File(PathBuf),
+}
+
+/// copies the text to the system clipboard, or writes it to `fallback` when none of
+/// the clipboard tools work
+pub fn copy(text: &str, fallback: &Path) -> io::Result<Copied> {
+    copy_with(&CLIPBOARD_COMMANDS, text, fallback)
+}
+
+fn copy_with(commands: &[(&str, &[&str])], text: &str, fallback: &Path) -> io::Result<Copied> {
+    if commands.iter().any(|(program, args)| run_clipboard_command(program, args, text)) {
+        return Ok(Copied::Clipboard);
+    }
+    std::fs::write(fallback, text)?;
+    Ok(Copied::File(fallback.to_path_buf()))
+}
+
+/// whether the tool exists and took the text, its output is discarded so it doesn't
+/// draw over the TUI
+fn run_clipboard_command(program: &str, args: &[&str], text: &str) -> bool {
+    let Ok(mut child) = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+    let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+    // stdin is closed above so the tool sees the end of the text
+    child.wait().is_ok_and(|status| status.success()) && written
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_fallback() {
+        let fallback = std::env::temp_dir().join(format!("chessterm-clipboard-{}.fen", std::process::id()));
+        let fen = "8/8/8/8/8/8/8/K6k w - - 0 1";
+        let copied = copy_with(&[("./no-such-clipboard", &[])], fen, &fallback).unwrap();
+        assert_eq!(Copied::File(fallback.clone()), copied);
+        assert_eq!(fen, std::fs::read_to_string(&fallback).unwrap());
+        std::fs::remove_file(&fallback).unwrap();
+
+        // fallback directory missing
+        assert!(copy_with(&[], fen, &fallback.join("missing.fen")).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use ratatui::style::Color;
+
+/// board colors, squares are RGB so the piece sprites can be drawn on the same background
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Theme {
+    pub name: &'static str,
+    pub light_square: Color,
+    pub dark_square: Color,
+    /// squares getting the king out of check
+    pub check_resolution: Color,
+    /// checkers and their line of attack
+    pub check_line: Color,
+    /// destinations of the piece being typed or picked
+    pub legal_destination: Color,
+    /// rank and file labels
+    pub label: Color,
+    /// frame of the board cursor
+    pub cursor: Color,
+}
+
+/// themes in the order `Ctrl-T` cycles through them, the first one is the default
+pub const THEMES: [Theme; 4] = [
+    Theme {
+        name: "classic",
+        light_square: Color::Rgb(235, 209, 166),
+        dark_square: Color::Rgb(165, 117, 80),
+        check_resolution: Color::Rgb(170, 190, 90),
+        check_line: Color::Rgb(205, 95, 80),
+        legal_destination: Color::Rgb(120, 170, 200),
+        label: Color::Yellow,
+        cursor: Color::Black,
+    },
+    Theme {
+        name: "blue",
+        light_square: Color::Rgb(222, 227, 230),
+        dark_square: Color::Rgb(120, 150, 180),
+        check_resolution: Color::Rgb(150, 200, 130),
+        check_line: Color::Rgb(215, 100, 100),
+        legal_destination: Color::Rgb(240, 200, 110),
+        label: Color::LightBlue,
+        cursor: Color::Rgb(20, 40, 90),
+    },
+    Theme {
+        name: "grayscale",
+        light_square: Color::Rgb(200, 200, 200),
+        dark_square: Color::Rgb(125, 125, 125),
+        check_resolution: Color::Rgb(230, 230, 230),
+        check_line: Color::Rgb(70, 70, 70),
+        legal_destination: Color::Rgb(160, 160, 160),
+        label: Color::Gray,
+        cursor: Color::Black,
+    },
+    // named ANSI highlight and label colors for terminals without truecolor, only
+    // the squares (behind the sprites) are RGB
+    Theme {
+        name: "high-contrast",
+        light_square: Color::Rgb(255, 255, 255),
+        dark_square: Color::Rgb(100, 100, 100),
+        check_resolution: Color::Green,
+        check_line: Color::Red,
+        legal_destination: Color::LightBlue,
+        label: Color::White,
+        cursor: Color::Magenta,
+    },
+];
+
+impl Theme {
+    /// theme with the given name (case insensitive)
+    pub fn from_name(name: &str) -> Option<Theme> {
+        THEMES.iter().find(|theme| theme.name.eq_ignore_ascii_case(name)).copied()
+    }
+
+    /// next theme when cycling, back to the first after the last one
+    pub fn next(&self) -> Theme {
+        let idx = THEMES.iter().position(|theme| theme.name == self.name).unwrap_or(0);
+        THEMES[(idx + 1) % THEMES.len()]
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        THEMES[0]
+    }
+}
+
+/// RGBA background of the piece sprites for a square color
+pub fn rgba(color: Color) -> [u8; 4] {
+    match color {
+        Color::Rgb(r, g, b) => [r, g, b, 255],
+        _ => [0, 0, 0, 255],
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_cycle() {
+        let mut theme = Theme::default();
+        let mut names = Vec::new();
+        for _ in 0..THEMES.len() {
+            names.push(theme.name);
+            theme = theme.next();
+        }
+        assert_eq!(vec!["classic", "blue", "grayscale", "high-contrast"], names);
+        assert_eq!(Theme::default(), theme);
+    }
+
+    #[test]
+    fn test_theme_from_name() {
+        assert_eq!(Some(THEMES[3]), Theme::from_name("High-Contrast"));
+        assert_eq!(None, Theme::from_name("neon"));
+        // sprites are drawn on the square color
+        for theme in THEMES {
+            assert_ne!(0, rgba(theme.light_square)[0], "{}", theme.name);
+            assert!(matches!(theme.dark_square, Color::Rgb(..)), "{}", theme.name);
+        }
+        assert_eq!([235, 209, 166, 255], rgba(Theme::default().light_square));
+
+        let high_contrast = Theme::from_name("high-contrast").unwrap();
+        for color in [
+            high_contrast.check_resolution,
+            high_contrast.check_line,
+            high_contrast.legal_destination,
+            high_contrast.label,
+            high_contrast.cursor,
+        ] {
+            assert!(!matches!(color, Color::Rgb(..)), "{:?}", color);
+        }
+    }
+}
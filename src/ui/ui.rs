@@ -1,5 +1,9 @@
-use crate::engine::game::MoveError;
-use crate::ui::app::{App, CurrentScreen};
+use crate::engine::ai::MATE_SCORE;
+use crate::engine::board::{bitboard_single, piece_char, square_name};
+use crate::engine::game::{or_list, Game, GameResult, GameStats, MoveError, MoveInfo, SideStats, Status};
+use crate::engine::parser::Piece;
+use crate::ui::app::{promotion_letter, typed_destinations, App, CurrentScreen, Verbosity, MOVE_SCORES_DEPTH};
+use crate::ui::theme::Theme;
 use image::imageops::FilterType;
 use ratatui::buffer::Buffer;
 use ratatui::layout::{
@@ -9,12 +13,12 @@ use ratatui::style::{Color, Style, Stylize};
 use ratatui::symbols::border;
 use ratatui::text::{Line, Span, Text};
 use ratatui::widgets::{
-    Block, Borders, Cell, Clear, Padding, Paragraph, Row, Scrollbar, ScrollbarOrientation, Table,
-    Wrap,
+    Block, BorderType, Borders, Cell, Clear, Padding, Paragraph, Row, Scrollbar, ScrollbarOrientation, ScrollbarState, Table,
+    Widget, Wrap,
 };
 use ratatui::Frame;
 use ratatui_image::picker::Picker;
-use ratatui_image::protocol::ImageSource;
+use ratatui_image::protocol::{ImageSource, StatefulProtocol};
 use ratatui_image::{Image, Resize, StatefulImage};
 use std::cmp::min;
 use std::ops::Add;
@@ -23,21 +27,59 @@ use std::rc::Rc;
 const ERROR_MOVE: &str = "×";
 const ERROR_AMBIGUOUS: &str = "? Ambiguous";
 const ERROR_NONE: &str = "";
+// tallest the move scores panel gets, the move list keeps the rest
+const MAX_SCORES_HEIGHT: u16 = 14;
+// frames of the spinner shown while the computer is thinking
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
 
-fn render_error<'a>(err: Option<MoveError>) -> Span<'a> {
+fn render_error<'a>(err: Option<MoveError>, verbosity: Verbosity, game: &Game, input: &str) -> Span<'a> {
     if let Some(err) = err {
-        if err == MoveError::AmbiguousSource {
-            Span::from(ERROR_AMBIGUOUS).style(Style::default().fg(Color::Yellow).bold())
-        } else {
-            Span::from(ERROR_MOVE).style(Style::default().fg(Color::Red).bold())
-        }
+        let (text, color) = match err {
+            MoveError::AmbiguousSource(sources) => {
+                (format_ambiguous(&game.disambiguate(input, sources)), Color::Yellow)
+            }
+            _ => (format_error(err, verbosity), Color::Red),
+        };
+        Span::from(text).style(Style::default().fg(color).bold())
     } else {
         Span::from(ERROR_NONE)
     }
 }
 
-const LIGHT_SQUARE: Color = Color::Rgb(235, 209, 166);
-const DARK_SQUARE: Color = Color::Rgb(165, 117, 80);
+/// error marker, debug verbosity includes the error reason
+/// (e.g. `× InvalidMove(PawnNonDiagonalCapture)`)
+pub fn format_error(err: MoveError, verbosity: Verbosity) -> String {
+    let ambiguous = matches!(err, MoveError::AmbiguousSource(_));
+    let marker = if ambiguous { ERROR_AMBIGUOUS } else { ERROR_MOVE };
+    match verbosity {
+        Verbosity::Normal => marker.to_string(),
+        Verbosity::Debug if ambiguous => marker.to_string(),
+        Verbosity::Debug => format!("{} {:?}", marker, err),
+    }
+}
+
+/// ambiguous move marker listing the moves that tell the pieces apart
+/// (e.g. `? Nbd7 or Nfd7`)
+pub fn format_ambiguous(moves: &[String]) -> String {
+    if moves.is_empty() {
+        ERROR_AMBIGUOUS.to_string()
+    } else {
+        format!("? {}", or_list(moves))
+    }
+}
+
+/// move list entry, debug verbosity includes the from/to squares (e.g. `Nf3 g1f3`)
+pub fn format_move(mv: &MoveInfo, verbosity: Verbosity) -> String {
+    match verbosity {
+        Verbosity::Normal => mv.san.clone(),
+        Verbosity::Debug => format!(
+            "{} {}{}",
+            mv.san,
+            square_name(mv.from).unwrap_or_default(),
+            square_name(mv.to).unwrap_or_default()
+        ),
+    }
+}
 
 const DEFAULT_SQUARE_SIZE: u16 = 11;
 const LARGE_SQUARE_SIZE: u16 = 15;
@@ -76,24 +118,23 @@ fn compute_board_layouts(area: Rect, square_size: u16) -> (Rc<[Rect]>, Rc<[Rect]
     (rank_layout, rank_label_layout, file_label_layout)
 }
 
-fn render_rank_label(frame: &mut Frame, rank: usize, area: Rect) {
-    let rank_label = Paragraph::new(format!("{}", rank))
-        .fg(Color::Yellow)
-        .bold()
-        .alignment(Alignment::Center);
-    frame.render_widget(rank_label, area);
-}
-
-fn render_file_labels(frame: &mut Frame, file_label_layout: Rc<[Rect]>, flipped: bool) {
-    for file in 0..8 {
-        let actual_file = if flipped { 7 - file } else { file }; // Flip files
-
-        let f = (actual_file as u8 + 'A' as u8) as char;
-        let file_label = Paragraph::new(format!("{}", f))
-            .fg(Color::Yellow)
-            .bold()
-            .alignment(Alignment::Left);
-        frame.render_widget(file_label, file_label_layout[file])
+/// renders `1`-`8` left of the board and `a`-`h` below it, each label centered on
+/// its rank or file so it lines up with the squares whatever the square size
+fn render_coordinates(
+    buf: &mut Buffer,
+    rank_label_layout: &[Rect],
+    file_label_layout: &[Rect],
+    flipped: bool,
+    color: Color,
+) {
+    let label = |text: String, area: Rect, buf: &mut Buffer| {
+        let cell = center_rect(area, Rect::new(0, 0, 1, 1));
+        Paragraph::new(text).fg(color).bold().render(cell, buf);
+    };
+    // the layouts are in screen order, the rank and file mappings are their own inverse
+    for idx in 0..8 {
+        label((actual_rank(idx, flipped) + 1).to_string(), rank_label_layout[idx], buf);
+        label(((b'a' + actual_file(idx, flipped) as u8) as char).to_string(), file_label_layout[idx], buf);
     }
 }
 
@@ -113,6 +154,44 @@ fn actual_rank(rank: usize, flipped: bool) -> usize {
     } // Flip ranks
 }
 
+/// board square (single bit bitboard) under the screen cell. `board` is the area of the
+/// 8x8 squares without the labels, both file and rank are inverted when flipped
+pub fn screen_to_square(board: Rect, square_size: u16, column: u16, row: u16, flipped: bool) -> Option<u64> {
+    let square_height = square_size / 2;
+    if square_height == 0 || !board.contains(Position::new(column, row)) {
+        return None;
+    }
+
+    let screen_file = ((column - board.x) / square_size) as usize;
+    let screen_rank = ((row - board.y) / square_height) as usize;
+    if screen_file > 7 || screen_rank > 7 {
+        return None;
+    }
+
+    // the mapping is its own inverse
+    let file = actual_file(screen_file, flipped);
+    let rank = actual_rank(screen_rank, flipped);
+    bitboard_single((b'a' + file as u8) as char, rank as u64 + 1)
+}
+
+/// screen area of the board square (single bit bitboard), inverse of [`screen_to_square`]
+pub fn square_to_screen(board: Rect, square_size: u16, square: u64, flipped: bool) -> Option<Rect> {
+    if square.count_ones() != 1 {
+        return None;
+    }
+
+    let idx = square.trailing_zeros() as usize;
+    let column = actual_file(idx % 8, flipped) as u16;
+    let row = actual_rank(idx / 8, flipped) as u16;
+    let square_height = square_size / 2;
+    Some(Rect::new(
+        board.x + column * square_size,
+        board.y + row * square_height,
+        square_size,
+        square_height,
+    ))
+}
+
 fn is_light_square(rank: usize, file: usize) -> bool {
     (rank + file) & 1 == 1
 }
@@ -123,9 +202,17 @@ fn render_square(
     rank: usize,
     file: usize,
     flipped: bool,
+    highlight: Option<Color>,
+    theme: &Theme,
 ) {
     let actual_file = actual_file(file, flipped);
-    let bg = if is_light_square(rank, file) { LIGHT_SQUARE } else { DARK_SQUARE };
+    let bg = if let Some(color) = highlight {
+        color
+    } else if is_light_square(rank, file) {
+        theme.light_square
+    } else {
+        theme.dark_square
+    };
     let square = Block::default().bg(bg);
     frame.render_widget(square, file_layout[actual_file]);
 }
@@ -144,12 +231,39 @@ fn render_piece(
     if piece != '.' {
         let chess_pieces_map = if is_light_square(rank, file) { &app.chess_pieces_light_bg } else { &app.chess_pieces_dark_bg };
         let protocol_ref = chess_pieces_map.get(&piece).unwrap();
-        let img = StatefulImage::default();
-        frame.render_stateful_widget(img, file_layout[actual_file], &mut protocol_ref.borrow_mut());
+        render_sprite(&mut protocol_ref.borrow_mut(), file_layout[actual_file], frame.buffer_mut());
+    }
+}
+
+/// returns rect of `size` centered within `area`, clamped so it never exceeds `area`
+fn center_rect(area: Rect, size: Rect) -> Rect {
+    let width = min(size.width, area.width);
+    let height = min(size.height, area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// render piece sprite centered within the square. The sprite is fitted to the square
+/// first (keeping aspect ratio) so it never overflows into the neighbouring squares
+fn render_sprite(protocol: &mut StatefulProtocol, square: Rect, buf: &mut Buffer) {
+    if square.width == 0 || square.height == 0 {
+        return;
     }
+
+    let resize = Resize::Fit(None);
+    if let Some(rect) = protocol.needs_resize(&resize, square) {
+        protocol.resize_encode(&resize, protocol.background_color(), rect);
+    }
+    protocol.render(center_rect(square, protocol.area()), buf);
 }
 
-fn render_board(app: &App, frame: &mut Frame, area: Rect, large_board: bool) {
+/// renders the board and returns the area of the 8x8 squares (without labels) and
+/// the square size, used to map screen cells back to squares
+fn render_board(app: &App, frame: &mut Frame, area: Rect, large_board: bool) -> (Rect, u16) {
     let square_size = if large_board {
         LARGE_SQUARE_SIZE
     } else {
@@ -158,6 +272,20 @@ fn render_board(app: &App, frame: &mut Frame, area: Rect, large_board: bool) {
 
     let (rank_layout, rank_label_layout, file_label_layout) = compute_board_layouts(area, square_size);
     let pieces = app.game.board.pieces_array(false);
+    // squares reachable by the piece being clicked (with its square) or typed, otherwise
+    // (when in check) the checkers with their line of attack and the other squares that
+    // get out of check, the first highlight containing a square wins
+    let selection = match app.selected {
+        Some(from) => app.game.legal_destinations(from) | from,
+        None => typed_destinations(&app.input, &app.game),
+    };
+    let highlights = match selection {
+        0 => vec![
+            (app.game.check_info().map_or(0, |info| info.lines), app.theme.check_line),
+            (app.game.check_resolution_squares(), app.theme.check_resolution),
+        ],
+        destinations => vec![(destinations, app.theme.legal_destination)],
+    };
     for (rank, files) in pieces.iter().enumerate().rev() {
         let actual_rank = actual_rank(rank, app.flipped);
         let rank_layout_idx = actual_rank; // in reverse order for rendering
@@ -165,15 +293,27 @@ fn render_board(app: &App, frame: &mut Frame, area: Rect, large_board: bool) {
         let file_layout = Layout::horizontal([Constraint::Length(square_size); 8])
             .split(rank_layout[rank_layout_idx]);
 
-        render_rank_label(frame, rank + 1, rank_label_layout[rank_layout_idx]);
-
         // iterate files
         for (file, piece) in files.iter().enumerate() {
-            render_square(frame, &file_layout, rank, file, app.flipped);
+            let highlight = highlights
+                .iter()
+                .find(|(squares, _)| squares & (1 << (rank * 8 + file)) != 0)
+                .map(|(_, color)| *color);
+            render_square(frame, &file_layout, rank, file, app.flipped, highlight, &app.theme);
             render_piece(frame, app, &file_layout, rank, file, *piece, app.flipped);
         }
     }
-    render_file_labels(frame, file_label_layout, app.flipped);
+    if app.show_coordinates {
+        render_coordinates(frame.buffer_mut(), &rank_label_layout, &file_label_layout, app.flipped, app.theme.label);
+    }
+
+    let board_area = rank_layout[0].union(rank_layout[7]);
+    // framed so the highlight of the square stays visible
+    if let Some(area) = app.cursor.and_then(|cursor| square_to_screen(board_area, square_size, cursor, app.flipped)) {
+        let frame_block = Block::bordered().border_type(BorderType::Thick).border_style(Style::default().fg(app.theme.cursor));
+        frame.render_widget(frame_block, area);
+    }
+    (board_area, square_size)
 }
 
 pub const MIN_WIDTH_LARGE: u16 = 164;
@@ -207,13 +347,95 @@ pub fn render(frame: &mut Frame, app: &mut App) {
     ])
     .split(main_layout[1]);
 
-    render_title(frame, main_layout[0]);
-    render_board(app, frame, content_layout[1], large_board);
+    render_title(frame, main_layout[0], app.game.opening());
+    (app.board_area, app.square_size) = render_board(app, frame, content_layout[1], large_board);
     render_moves(frame, app, content_layout[2]);
-    render_footer(frame, main_layout[3]);
+    // swapping sides against the computer, the move scores otherwise and in analysis
+    let swap_sides = app.ai_is_white.is_some() && !app.game.is_analysis();
+    render_footer(frame, main_layout[3], app.default_promotion, app.theme.name, app.bell, swap_sides);
 
     match app.current_screen {
         CurrentScreen::Main => {}
+        CurrentScreen::Pgn => render_pgn(frame, app),
+        CurrentScreen::ConfirmReset => {
+            let popup_block = Block::default()
+                .title("Confirm new game")
+                .borders(Borders::ALL)
+                .title_alignment(Alignment::Center)
+                .style(Style::default().bg(Color::DarkGray));
+
+            let reset_text = Text::styled(
+                "\nAbandon this game and start over? (y/n)",
+                Style::default().fg(Color::Black),
+            );
+
+            let reset_paragraph = Paragraph::new(reset_text)
+                .alignment(Alignment::Center)
+                .block(popup_block)
+                .wrap(Wrap { trim: false });
+
+            let area = centered_rect(40, 10, frame.area());
+            frame.render_widget(Clear, area); // clear the area behind popup
+            frame.render_widget(reset_paragraph, area);
+        }
+        CurrentScreen::DrawOffer => {
+            let popup_block = Block::default()
+                .title("Draw offer")
+                .borders(Borders::ALL)
+                .title_alignment(Alignment::Center)
+                .style(Style::default().bg(Color::DarkGray));
+
+            let (offering, answering) = if app.game.is_white() { ("White", "Black") } else { ("Black", "White") };
+            let offer_text = Text::styled(
+                format!("\n{} offers a draw\n{}, accept? (y/n)", offering, answering),
+                Style::default().fg(Color::Black),
+            );
+
+            let offer_paragraph = Paragraph::new(offer_text)
+                .alignment(Alignment::Center)
+                .block(popup_block)
+                .wrap(Wrap { trim: false });
+
+            let area = centered_rect(40, 10, frame.area());
+            frame.render_widget(Clear, area); // clear the area behind popup
+            frame.render_widget(offer_paragraph, area);
+        }
+        CurrentScreen::Promotion => {
+            let popup_block = Block::default()
+                .title("Promote to")
+                .borders(Borders::ALL)
+                .title_alignment(Alignment::Center)
+                .style(Style::default().bg(Color::DarkGray));
+
+            let mut choices = vec![];
+            for (piece, label) in [
+                (Piece::Queen, "[Q]ueen"),
+                (Piece::Rook, "[R]ook"),
+                (Piece::Bishop, "[B]ishop"),
+                (Piece::Knight, "K[n]ight"),
+            ] {
+                let choice = Span::from(label);
+                choices.push(if piece == app.default_promotion { choice.bold().underlined() } else { choice });
+                choices.push(Span::from("  "));
+            }
+            choices.pop();
+            let promotion_text = Text::from(vec![
+                Line::from(""),
+                Line::from(choices),
+                Line::from(""),
+                Line::from("Enter to confirm, Esc to cancel"),
+            ])
+            .style(Style::default().fg(Color::Black));
+
+            let promotion_paragraph = Paragraph::new(promotion_text)
+                .alignment(Alignment::Center)
+                .block(popup_block)
+                .wrap(Wrap { trim: false });
+
+            let area = centered_rect(40, 10, frame.area());
+            frame.render_widget(Clear, area); // clear the area behind popup
+            frame.render_widget(promotion_paragraph, area);
+        }
         CurrentScreen::Exiting => {
             let popup_block = Block::default()
                 .title("Confirm exit game")
@@ -243,7 +465,11 @@ pub fn render(frame: &mut Frame, app: &mut App) {
                 .title_alignment(Alignment::Center)
                 .style(Style::default().bg(Color::DarkGray));
 
-            let exit_text = Text::styled("Play again? (y/n)", Style::default().fg(Color::Black));
+            let mut lines = vec![Line::from(app.game.result().to_string()).bold(), Line::from("")];
+            lines.extend(render_statistics(app.game.statistics()));
+            lines.push(Line::from(""));
+            lines.push(Line::from("Play again? (y/n)"));
+            let exit_text = Text::from(lines).style(Style::default().fg(Color::Black));
 
             // the `trim: false` will stop the text from being cut off when over the edge of the block
             let exit_paragraph = Paragraph::new(exit_text)
@@ -251,45 +477,100 @@ pub fn render(frame: &mut Frame, app: &mut App) {
                 .block(popup_block)
                 .wrap(Wrap { trim: false });
 
-            let area = centered_rect(40, 10, frame.area());
+            let area = centered_rect(40, 30, frame.area());
             frame.render_widget(Clear, area); // clear the area behind popup
             frame.render_widget(exit_paragraph, area);
         }
     }
 }
 
-fn render_title(frame: &mut Frame, area: Rect) {
+fn render_statistics<'a>(stats: GameStats) -> Vec<Line<'a>> {
+    let row = |label: &str, white: String, black: String| {
+        Line::from(format!("{:<10}{:>7}{:>7}", label, white, black))
+    };
+    let castled = |side: SideStats| if side.castled { "yes" } else { "no" }.to_string();
+
+    vec![
+        row("", "White".to_string(), "Black".to_string()),
+        row("Moves", stats.white.moves.to_string(), stats.black.moves.to_string()),
+        row("Captures", stats.white.captures.to_string(), stats.black.captures.to_string()),
+        row("Checks", stats.white.checks.to_string(), stats.black.checks.to_string()),
+        row("Castled", castled(stats.white), castled(stats.black)),
+    ]
+}
+
+fn render_title(frame: &mut Frame, area: Rect, opening: Option<&str>) {
     let title_block = Block::default()
         .borders(Borders::ALL)
         .style(Style::default());
 
-    let title = Paragraph::new(Text::styled(
-        "chessterm 0.1.0",
-        Style::default().fg(Color::Green),
-    ))
+    let mut title_text = vec![Span::from("chessterm 0.1.0").fg(Color::Green)];
+    if let Some(opening) = opening {
+        title_text.push(Span::from(" - "));
+        title_text.push(Span::from(opening.to_string()).fg(Color::Yellow));
+    }
+
+    let title = Paragraph::new(Line::from(title_text))
     .alignment(Alignment::Center)
     .block(title_block);
     frame.render_widget(title, area);
 }
 
 fn render_moves(frame: &mut Frame, app: &mut App, area: Rect) {
-    let moves_layout = Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).split(area);
+    // the input grows to explain why the last move can't be played
+    let error_lines = app
+        .error_message
+        .as_deref()
+        .map_or_else(Vec::new, |message| wrap_words(message, area.width.saturating_sub(2) as usize));
+    // the move scores (`Ctrl-E`) take the bottom of the move list
+    let scores_height = app.move_scores.as_ref().map_or(0, |(_, scores)| (scores.len() as u16 + 2).min(MAX_SCORES_HEIGHT));
+    let moves_layout = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(3 + error_lines.len() as u16),
+        Constraint::Length(4),
+        Constraint::Length(4),
+        Constraint::Fill(1),
+        Constraint::Length(scores_height),
+    ])
+    .split(area);
 
-    let input_block = Block::default().title("Input").borders(Borders::ALL);
+    render_turn(frame, app, moves_layout[0]);
+
+    let thinking = app.search.as_ref().map(|search| {
+        let spinner = SPINNER[app.spinner / 2 % SPINNER.len()];
+        if app.engine.is_some() {
+            // the engine's progress isn't followed
+            return format!("{} thinking…", spinner);
+        }
+        // depth being searched, one past the last finished one
+        format!("{} thinking… depth {}", spinner, (search.depth + 1).min(app.ai.config.depth))
+    });
+    let message = app.status_message.as_ref().or(thinking.as_ref()).or(app.drill_message.as_ref());
+    let input_title = match (message, app.game.is_analysis()) {
+        (Some(message), _) => format!("Input - {}", message),
+        (None, true) => "Input - analysis (both colors)".to_string(),
+        (None, false) => "Input".to_string(),
+    };
+    let input_block = Block::default().title(input_title).borders(Borders::ALL);
 
     let input_texts = vec![
         Span::from(format!("{:<10}", app.input.as_str())).fg(Color::White),
-        render_error(app.error),
+        render_error(app.error, app.verbosity, &app.game, &app.input),
     ];
 
-    let input = Paragraph::new(Line::from(input_texts)).block(input_block);
-    frame.render_widget(input, moves_layout[0]);
+    let mut input_lines = vec![Line::from(input_texts)];
+    input_lines.extend(error_lines.into_iter().map(|line| Line::from(line).fg(Color::Red)));
+    let input = Paragraph::new(input_lines).block(input_block);
+    frame.render_widget(input, moves_layout[1]);
 
     frame.set_cursor_position(Position::new(
-        moves_layout[0].x + app.character_index as u16 + 1,
-        moves_layout[0].y + 1,
+        moves_layout[1].x + app.character_index as u16 + 1,
+        moves_layout[1].y + 1,
     ));
 
+    render_castling_rights(frame, app, moves_layout[2]);
+    render_captured(frame, app, moves_layout[3]);
+
     // let moves_list =
     let header = ["#", "White", "Black"]
         .into_iter()
@@ -320,7 +601,11 @@ fn render_moves(frame: &mut Frame, app: &mut App, area: Rect) {
         Constraint::Percentage(50),
     ];
 
-    let moves = Block::default().borders(Borders::ALL).title("Moves");
+    let moves_title = match app.verbosity {
+        Verbosity::Normal => "Moves".to_string(),
+        Verbosity::Debug => format!("Moves ({})", app.game),
+    };
+    let moves = Block::default().borders(Borders::ALL).title(moves_title);
 
     // update scrollbar state
     app.scrollbar_state = app
@@ -329,10 +614,10 @@ fn render_moves(frame: &mut Frame, app: &mut App, area: Rect) {
         .position(app.scroll_offset);
     *app.table_state.offset_mut() = app.scroll_offset;
 
-    app.visible_moves = (moves_layout[1].height as usize).saturating_sub(3);
+    app.visible_moves = (moves_layout[4].height as usize).saturating_sub(3);
 
     let table = Table::new(rows, widths).header(header).block(moves);
-    frame.render_stateful_widget(table, moves_layout[1], &mut app.table_state);
+    frame.render_stateful_widget(table, moves_layout[4], &mut app.table_state);
 
     let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
         .begin_symbol(Some("▲"))
@@ -341,24 +626,211 @@ fn render_moves(frame: &mut Frame, app: &mut App, area: Rect) {
     if app.show_scrollbar {
         frame.render_stateful_widget(
             scrollbar,
-            moves_layout[1].inner(Margin {
+            moves_layout[4].inner(Margin {
                 horizontal: 0,
                 vertical: 1,
             }),
             &mut app.scrollbar_state,
         );
     }
+
+    if let Some((_, scores)) = app.move_scores.as_ref() {
+        render_move_scores(frame, scores, moves_layout[5]);
+    }
+}
+
+/// legal moves with their search score, best first
+fn render_move_scores(frame: &mut Frame, scores: &[(String, i32)], area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Move scores (depth {})", MOVE_SCORES_DEPTH));
+    let lines: Vec<Line> = if scores.is_empty() {
+        vec![Line::from("No legal moves")]
+    } else {
+        scores
+            .iter()
+            .map(|(san, score)| Line::from(vec![Span::from(format!("{:<10}", san)), Span::from(score_label(*score)).fg(Color::Yellow)]))
+            .collect()
+    };
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// scrollable popup with the PGN movetext so it can be copied from the terminal
+fn render_pgn(frame: &mut Frame, app: &mut App) {
+    let area = centered_rect(60, 60, frame.area());
+    let block = Block::default()
+        .title("PGN (ESC to close)")
+        .borders(Borders::ALL)
+        .title_alignment(Alignment::Center)
+        .padding(Padding::horizontal(1));
+    let inner = block.inner(area);
+
+    let lines = wrap_words(&app.game.pgn_movetext(), inner.width as usize);
+    let max_scroll = lines.len().saturating_sub(inner.height as usize);
+    app.pgn_scroll = min(app.pgn_scroll, max_scroll);
+
+    let text: Vec<Line> = lines.into_iter().map(Line::from).collect();
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .scroll((app.pgn_scroll as u16, 0));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+
+    if max_scroll > 0 {
+        let mut scrollbar_state = ScrollbarState::new(max_scroll).position(app.pgn_scroll);
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("▲"))
+            .end_symbol(Some("▼"));
+        frame.render_stateful_widget(
+            scrollbar,
+            area.inner(Margin {
+                horizontal: 0,
+                vertical: 1,
+            }),
+            &mut scrollbar_state,
+        );
+    }
 }
 
-fn render_footer(frame: &mut Frame, area: Rect) {
-    let footer = Paragraph::new(Line::from(vec![
+/// splits `text` into lines of at most `width` characters on word boundaries, the
+/// lines are kept free of trailing spaces so they can be copied as is
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// side to move (or the result once the game is over) with a banner when it is in check
+fn render_turn(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default().title("Turn").borders(Borders::ALL);
+    let mut line = vec![turn_label(&app.game).fg(Color::White).bold()];
+    if app.game.check && app.game.status() == Status::Ongoing {
+        line.push("  ".into());
+        line.push(" CHECK! ".fg(Color::White).bg(app.theme.check_line).bold());
+    }
+    let mut line = Line::from(line);
+    if app.flash {
+        line = line.reversed();
+    }
+    frame.render_widget(Paragraph::new(line).block(block), area);
+}
+
+/// `White to move` / `Black to move`, the result once the game is over
+pub fn turn_label(game: &Game) -> String {
+    match game.result() {
+        GameResult::Ongoing if game.is_white() => "White to move".to_string(),
+        GameResult::Ongoing => "Black to move".to_string(),
+        result => result.to_string(),
+    }
+}
+
+fn render_castling_rights(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default().title("Castling").borders(Borders::ALL);
+    let castling = Paragraph::new(vec![
+        Line::from(vec![
+            "White: ".into(),
+            app.game.castling_rights_label(true).fg(Color::White).bold(),
+        ]),
+        Line::from(vec![
+            "Black: ".into(),
+            app.game.castling_rights_label(false).fg(Color::White).bold(),
+        ]),
+    ])
+    .block(block);
+    frame.render_widget(castling, area);
+}
+
+/// search score in pawns for the side to move (e.g. `+0.35`), `#3` when mating in 3
+/// and `#-2` when getting mated in 2
+pub fn score_label(score: i32) -> String {
+    let plies = MATE_SCORE - score.abs();
+    if plies > 100 {
+        return format!("{:+.2}", score as f64 / 100.0);
+    }
+    let moves = (plies + 1) / 2;
+    format!("#{}", if score > 0 { moves } else { -moves })
+}
+
+/// pieces taken by one side as board glyphs in the opponent's color (e.g. `♕♙♙`),
+/// followed by the material lead (e.g. `+3`) if the side is ahead
+pub fn captured_label(captured: &[Piece], by_white: bool, material_lead: i32) -> String {
+    let mut label: String = captured.iter().map(|piece| piece_char(*piece, !by_white, true)).collect();
+    if material_lead > 0 {
+        if !label.is_empty() {
+            label.push(' ');
+        }
+        label.push_str(&format!("+{}", material_lead));
+    }
+    label
+}
+
+fn render_captured(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default().title("Captured").borders(Borders::ALL);
+    let lead = app.game.board.material(true) - app.game.board.material(false);
+    let captured = Paragraph::new(vec![
+        Line::from(vec![
+            "White: ".into(),
+            captured_label(&app.game.captured_pieces(true), true, lead).fg(Color::White).bold(),
+        ]),
+        Line::from(vec![
+            "Black: ".into(),
+            captured_label(&app.game.captured_pieces(false), false, -lead).fg(Color::White).bold(),
+        ]),
+    ])
+    .block(block);
+    frame.render_widget(captured, area);
+}
+
+/// key hints, view keys on the first line and game keys on the second. The line is
+/// only wide enough for one of swapping sides and the move scores
+fn render_footer(frame: &mut Frame, area: Rect, promotion: Piece, theme: &str, bell: bool, swap_sides: bool) {
+    let view_keys = Line::from(vec![
         "[.]".blue().bold(),
         " Flip  ".into(),
+        "[,]".blue().bold(),
+        " Labels  ".into(),
+        "[CTRL-T]".blue().bold(),
+        format!(" Theme {}  ", theme).into(),
         "[▲ / ▼]".blue().bold(),
-        " Scroll moves  ".into(),
-        "[ESC]".blue().bold(),
-        " Quit".into(),
-    ]))
+        " Scroll / recall  ".into(),
+        "[TAB]".blue().bold(),
+        " Board cursor  ".into(),
+        "[CTRL-F / CTRL-G]".blue().bold(),
+        " Copy FEN / PGN".into(),
+    ]);
+    let mut game_keys = vec![
+        "[CTRL-R]".blue().bold(),
+        " New game  ".into(),
+        "[CTRL-P]".blue().bold(),
+        " PGN  ".into(),
+        "[CTRL-A]".blue().bold(),
+        " Analysis  ".into(),
+        "[CTRL-O]".blue().bold(),
+        format!(" Promote to {}  ", promotion_letter(promotion)).into(),
+        "[CTRL-B]".blue().bold(),
+        if bell { " Bell on  " } else { " Bell off  " }.into(),
+    ];
+    if swap_sides {
+        game_keys.extend(["[CTRL-W]".blue().bold(), " Swap sides  ".into()]);
+    } else {
+        game_keys.extend(["[CTRL-E]".blue().bold(), " Move scores  ".into()]);
+    }
+    game_keys.extend(["[ESC]".blue().bold(), " Quit".into()]);
+    let game_keys = Line::from(game_keys);
+    let footer = Paragraph::new(vec![view_keys, game_keys])
     .alignment(Alignment::Center)
     .block(Block::default());
 
@@ -404,3 +876,211 @@ pub fn render_size_error(frame: &mut Frame, min_width: u16, min_height: u16, cur
     ]));
     frame.render_widget(paragraph, area);
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::ui::app::{get_file_contents, load_image, PIECE_SPRITES};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use ratatui_image::picker::ProtocolType;
+
+    fn halfblocks_picker(bg: [u8; 4]) -> Picker {
+        let mut picker = Picker::from_fontsize((10, 20));
+        picker.set_protocol_type(ProtocolType::Halfblocks);
+        picker.set_background_color(bg);
+        picker
+    }
+
+    #[test]
+    fn test_format_move_and_error() {
+        use crate::engine::board::bitboard_single;
+        use crate::engine::game::InvalidMoveReason;
+        use crate::engine::parser::Piece;
+
+        let mv = MoveInfo {
+            san: "Nf3".to_string(),
+            is_white: true,
+            piece: Piece::Knight,
+            from: bitboard_single('g', 1).unwrap(),
+            to: bitboard_single('f', 3).unwrap(),
+            is_capture: false,
+            is_check: false,
+        };
+        assert_eq!("Nf3", format_move(&mv, Verbosity::Normal));
+        assert_eq!("Nf3 g1f3", format_move(&mv, Verbosity::Debug));
+
+        let err = MoveError::InvalidMove(InvalidMoveReason::PawnNonDiagonalCapture);
+        assert_eq!(ERROR_MOVE, format_error(err, Verbosity::Normal));
+        let verbose = format_error(err, Verbosity::Debug);
+        assert!(verbose.contains("PawnNonDiagonalCapture"), "{}", verbose);
+        assert_eq!(ERROR_AMBIGUOUS, format_error(MoveError::AmbiguousSource(0b11), Verbosity::Debug));
+        assert_eq!(ERROR_AMBIGUOUS, format_ambiguous(&[]));
+        assert_eq!("? Nbd7 or Nfd7", format_ambiguous(&["Nbd7".to_string(), "Nfd7".to_string()]));
+    }
+
+    #[test]
+    fn test_turn_label() {
+        let mut game = Game::default();
+        assert_eq!("White to move", turn_label(&game));
+        game.process_move("f3").unwrap();
+        assert_eq!("Black to move", turn_label(&game));
+        for mv in ["e5", "g4", "Qh4"] {
+            game.process_move(mv).unwrap();
+        }
+        assert_eq!("Black wins by checkmate", turn_label(&game));
+    }
+
+    #[test]
+    fn test_score_label() {
+        assert_eq!("+0.35", score_label(35));
+        assert_eq!("-3.00", score_label(-300));
+        assert_eq!("+0.00", score_label(0));
+        assert_eq!("#1", score_label(MATE_SCORE - 1));
+        assert_eq!("#2", score_label(MATE_SCORE - 3));
+        assert_eq!("#-2", score_label(-MATE_SCORE + 4));
+    }
+
+    #[test]
+    fn test_captured_label() {
+        assert_eq!("", captured_label(&[], true, 0));
+        assert_eq!("♕♙♙ +7", captured_label(&[Piece::Queen, Piece::Pawn, Piece::Pawn], true, 7));
+        assert_eq!("♞", captured_label(&[Piece::Knight], false, -2));
+        assert_eq!("+1", captured_label(&[], false, 1));
+    }
+
+    #[test]
+    fn test_screen_to_square() {
+        let board = Rect::new(10, 5, 88, 40);
+        let square = |name: &str| crate::engine::board::parse_square(name).unwrap();
+
+        // top left, top right, bottom left, bottom right cells
+        let corners = [(10, 5), (97, 5), (10, 44), (97, 44)];
+        for (flipped, expected) in [(false, ["a8", "h8", "a1", "h1"]), (true, ["h1", "a1", "h8", "a8"])] {
+            for ((column, row), name) in corners.iter().zip(expected) {
+                assert_eq!(Some(square(name)), screen_to_square(board, 11, *column, *row, flipped));
+                assert!(square_to_screen(board, 11, square(name), flipped)
+                    .unwrap()
+                    .contains(Position::new(*column, *row)));
+            }
+        }
+
+        // round trip for every square and every cell of the square
+        for flipped in [false, true] {
+            for idx in 0..64 {
+                let area = square_to_screen(board, 11, 1 << idx, flipped).unwrap();
+                assert!(board.contains(area.as_position()));
+                for position in area.positions() {
+                    assert_eq!(Some(1 << idx), screen_to_square(board, 11, position.x, position.y, flipped));
+                }
+            }
+        }
+
+        // outside the board (labels)
+        assert_eq!(None, screen_to_square(board, 11, 9, 5, false));
+        assert_eq!(None, screen_to_square(board, 11, 98, 5, false));
+        assert_eq!(None, screen_to_square(board, 11, 10, 45, false));
+        assert_eq!(None, square_to_screen(board, 11, 0, false));
+    }
+
+    #[test]
+    fn test_render_coordinates() {
+        for (square_size, flipped) in [(DEFAULT_SQUARE_SIZE, false), (LARGE_SQUARE_SIZE, true)] {
+            let area = Rect::new(0, 0, 3 + square_size * 8, (square_size / 2) * 8 + 1);
+            let (rank_layout, rank_label_layout, file_label_layout) = compute_board_layouts(area, square_size);
+            let mut buf = Buffer::empty(area);
+            render_coordinates(&mut buf, &rank_label_layout, &file_label_layout, flipped, Color::Yellow);
+
+            let board = rank_layout[0].union(rank_layout[7]);
+            let square = |name: &str| crate::engine::board::parse_square(name).unwrap();
+            for (name, rank, file) in [("a1", "1", "a"), ("c5", "5", "c"), ("h8", "8", "h")] {
+                // centered on the row and column of the square
+                let cells = square_to_screen(board, square_size, square(name), flipped).unwrap();
+                let middle = cells.y + cells.height / 2;
+                let center = cells.x + cells.width / 2;
+                assert_eq!(rank, buf[(1, middle)].symbol(), "{} {}", name, square_size);
+                assert_eq!(file, buf[(center, area.height - 1)].symbol(), "{} {}", name, square_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrap_words() {
+        assert_eq!(Vec::<String>::new(), wrap_words("", 10));
+        assert_eq!(vec!["1. e4 e5 *"], wrap_words("1. e4 e5 *", 10));
+        assert_eq!(
+            vec!["1. e4 e5 2.", "Nf3 Nc6 3.", "Bb5 *"],
+            wrap_words("1. e4 e5 2. Nf3 Nc6 3. Bb5 *", 11)
+        );
+        // words longer than the width get their own line
+        assert_eq!(vec!["1/2-1/2"], wrap_words("1/2-1/2", 3));
+    }
+
+    #[test]
+    fn test_center_rect() {
+        let area = Rect::new(10, 20, 11, 5);
+        assert_eq!(Rect::new(10, 20, 11, 5), center_rect(area, Rect::new(0, 0, 11, 5)));
+        assert_eq!(Rect::new(10, 20, 10, 5), center_rect(area, Rect::new(0, 0, 10, 5)));
+        assert_eq!(Rect::new(13, 21, 5, 3), center_rect(area, Rect::new(0, 0, 5, 3)));
+        // never bigger than the area
+        assert_eq!(area, center_rect(area, Rect::new(0, 0, 20, 10)));
+    }
+
+    #[test]
+    fn test_render_sprite_halfblocks_snapshot() {
+        // solid 60x60 sprite with 10x20 font = 6x3 cells, centered within 11x5 square
+        let picker = halfblocks_picker([235, 209, 166, 255]);
+        let sprite = DynamicImage::from(RgbaImage::from_pixel(60, 60, Rgba([255, 0, 0, 255])));
+        let mut protocol = picker.new_resize_protocol(sprite);
+
+        let square = Rect::new(0, 0, 11, 5);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 13, 7));
+        render_sprite(&mut protocol, square, &mut buf);
+
+        let red = Color::Rgb(255, 0, 0);
+        let mut expected = Buffer::empty(Rect::new(0, 0, 13, 7));
+        for y in 1..4 {
+            for x in 2..8 {
+                expected[(x, y)].set_char('▀').set_fg(red).set_bg(red);
+            }
+        }
+        assert_eq!(expected, buf);
+    }
+
+    #[test]
+    fn test_render_sprite_halfblocks_no_overflow() {
+        for (light, bg) in [(true, [235, 209, 166, 255]), (false, [165, 117, 80, 255])] {
+            let picker = halfblocks_picker(bg);
+            for (_, filename) in PIECE_SPRITES {
+                let sprite = load_image(get_file_contents(&format!("sprite/{}.png", filename)));
+                let mut protocol = picker.new_resize_protocol(sprite);
+
+                for square in [Rect::new(3, 2, 11, 5), Rect::new(3, 2, 15, 7), Rect::new(3, 2, 4, 2)] {
+                    let area = Rect::new(0, 0, 24, 12);
+                    let mut buf = Buffer::empty(area);
+                    render_sprite(&mut protocol, square, &mut buf);
+
+                    for y in area.top()..area.bottom() {
+                        for x in area.left()..area.right() {
+                            let inside = square.contains(Position::new(x, y));
+                            let drawn = buf[(x, y)].symbol() == "▀";
+                            assert!(inside || !drawn, "{} drawn outside square at {},{}", filename, x, y);
+                        }
+                    }
+
+                    // tiny squares only need to stay within bounds
+                    if square.width < DEFAULT_SQUARE_SIZE {
+                        continue;
+                    }
+
+                    // transparent parts of the sprite take the square color
+                    let sprite_area = center_rect(square, protocol.area());
+                    let expected = Color::Rgb(bg[0], bg[1], bg[2]);
+                    let has_square_color = sprite_area
+                        .positions()
+                        .any(|p| buf[p].fg == expected && buf[p].bg == expected);
+                    assert!(has_square_color, "{} light={}", filename, light);
+                }
+            }
+        }
+    }
+}